@@ -0,0 +1,91 @@
+// Evaluation, sampling and hit testing on curves the size of motion-capture takes
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use iced::{Point, Vector};
+use iced_curve_editor::curve::{self, CurveAlgorithm, Dot, SampleWindow, SplineSettings};
+use iced_curve_editor::spatial::DotIndex;
+
+const KEY_COUNTS: [usize; 4] = [100, 1_000, 20_000, 100_000];
+
+const MODES: [CurveAlgorithm; 5] = [
+    CurveAlgorithm::CatmullRom,
+    CurveAlgorithm::NaturalCubicSpline,
+    CurveAlgorithm::Pchip,
+    CurveAlgorithm::Bezier,
+    CurveAlgorithm::BSpline,
+];
+
+// Evenly spaced keys with a wobble, like a sampled channel
+fn dots(n: usize) -> Vec<Dot> {
+    (0..n)
+        .map(|i| {
+            let x = i as f32 / (n - 1) as f32;
+            Dot::new(Point::new(x, 0.5 + 0.4 * (x * 40.0).sin() * (x * 3.0).cos()))
+        })
+        .collect()
+}
+
+fn sampling(c: &mut Criterion) {
+    let settings = SplineSettings::default();
+    for mode in MODES {
+        let mut group = c.benchmark_group(format!("sample {mode:?}"));
+        for n in KEY_COUNTS {
+            let keys = curve::with_edge_keys(&dots(n));
+            group.bench_with_input(BenchmarkId::new("full", n), &keys, |b, keys| {
+                b.iter(|| curve::sample_curve(Some(mode), keys, settings))
+            });
+            group.bench_with_input(BenchmarkId::new("screen", n), &keys, |b, keys| {
+                b.iter(|| curve::sample_curve_in(Some(mode), keys, settings, SampleWindow::screen(1920.0)))
+            });
+            // A quarter of the curve, as when zoomed in
+            let window = SampleWindow {
+                start: 0.25,
+                end: 0.5,
+                ..SampleWindow::screen(1920.0 * 4.0)
+            };
+            group.bench_with_input(BenchmarkId::new("zoomed", n), &keys, |b, keys| {
+                b.iter(|| curve::sample_curve_in(Some(mode), keys, settings, window))
+            });
+        }
+        group.finish();
+    }
+}
+
+fn evaluation(c: &mut Criterion) {
+    let settings = SplineSettings::default();
+    let xs: Vec<f32> = (0..1024).map(|i| i as f32 / 1023.0).collect();
+    let mut group = c.benchmark_group("evaluate_at 1024 xs");
+    for n in KEY_COUNTS {
+        let dots = dots(n);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &dots, |b, dots| {
+            b.iter(|| curve::evaluate_at(Some(CurveAlgorithm::Pchip), dots, settings, &xs))
+        });
+    }
+    group.finish();
+}
+
+fn hit_testing(c: &mut Criterion) {
+    let reach = Vector::new(0.005, 0.005);
+    let cursor = Point::new(0.73, 0.5);
+    let mut group = c.benchmark_group("hit test");
+    for n in KEY_COUNTS {
+        let dots = curve::with_edge_keys(&dots(n));
+        let index = DotIndex::new(&dots);
+        group.bench_with_input(BenchmarkId::new("scan", n), &dots, |b, dots| {
+            b.iter(|| {
+                dots.iter().position(|dot| {
+                    (dot.position.x - cursor.x).abs() < reach.x && (dot.position.y - cursor.y).abs() < reach.y
+                })
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("index", n), &index, |b, index| {
+            b.iter(|| index.find(cursor, reach))
+        });
+        group.bench_with_input(BenchmarkId::new("build index", n), &dots, |b, dots| {
+            b.iter(|| DotIndex::new(dots))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, sampling, evaluation, hit_testing);
+criterion_main!(benches);
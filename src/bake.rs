@@ -0,0 +1,164 @@
+// Resampling the evaluated curve onto evenly spaced x values
+use std::fmt;
+use std::str::FromStr;
+
+use iced::Point;
+
+use crate::curve::{self, CurveAlgorithm, CurveError, Dot, SplineSettings};
+
+/// Most samples a bake may take, enough for minutes of frames at 60 fps.
+pub const MAX_SAMPLES: usize = 65_536;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BakeSpacing {
+    /// N samples spread evenly over the whole x range, both ends included.
+    Count(usize),
+    /// One sample per whole frame from `start` to `end` inclusive, mapped onto the x range.
+    Frames { start: i32, end: i32 },
+}
+
+impl BakeSpacing {
+    /// Number of samples taken, zero for a range that ends before it starts.
+    pub fn sample_count(self) -> usize {
+        match self {
+            BakeSpacing::Count(n) => n,
+            // Widened so the full i32 range doesn't overflow
+            BakeSpacing::Frames { start, end } => (i64::from(end) - i64::from(start) + 1).max(0) as usize,
+        }
+    }
+
+    /// The spacing, if it takes at least two samples and no more than `MAX_SAMPLES`.
+    pub fn check(self) -> Result<Self, ParseSpacingError> {
+        if (2..=MAX_SAMPLES).contains(&self.sample_count()) {
+            Ok(self)
+        } else {
+            Err(ParseSpacingError)
+        }
+    }
+
+    /// Sample positions in curve space x. A single sample sits at 0.
+    pub fn xs(self) -> Vec<f32> {
+        let n = self.sample_count();
+        let last = n.saturating_sub(1).max(1) as f32;
+        (0..n).map(|i| i as f32 / last).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSpacingError;
+
+impl fmt::Display for ParseSpacingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected a sample count from 2 to {MAX_SAMPLES} or a frame range like 0..120 of at most {MAX_SAMPLES} frames"
+        )
+    }
+}
+
+impl FromStr for BakeSpacing {
+    type Err = ParseSpacingError;
+
+    /// Parses `64` as a sample count and `0..120` as a frame range.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let spacing = if let Some((start, end)) = s.split_once("..") {
+            let start = start.trim().parse().map_err(|_| ParseSpacingError)?;
+            let end = end.trim().parse().map_err(|_| ParseSpacingError)?;
+            BakeSpacing::Frames { start, end }
+        } else {
+            BakeSpacing::Count(s.parse().map_err(|_| ParseSpacingError)?)
+        };
+        spacing.check()
+    }
+}
+
+/// Uniformly sampled values of a curve, kept apart from the keys so it can be viewed and exported.
+#[derive(Debug, Clone)]
+pub struct LookupTable {
+    pub spacing: BakeSpacing,
+    pub samples: Vec<Point>, // Curve space, sorted by x
+}
+
+/// Evaluates the curve through the dots at every sample position of the spacing.
+pub fn bake(
+    dots: &[Dot],
+    curve_mode: Option<CurveAlgorithm>,
+    spline_settings: SplineSettings,
+    spacing: BakeSpacing,
+) -> Result<LookupTable, CurveError> {
+    let xs = spacing.xs();
+    let ys = curve::evaluate_at(curve_mode, dots, spline_settings, &xs)?;
+    Ok(LookupTable {
+        spacing,
+        samples: xs.into_iter().zip(ys).map(|(x, y)| Point { x, y }).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_spread_over_the_unit_range() {
+        let spacing: BakeSpacing = "5".parse().unwrap();
+        assert_eq!(spacing, BakeSpacing::Count(5));
+        assert_eq!(spacing.xs(), [0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(" 2 ".parse::<BakeSpacing>().unwrap().xs(), [0.0, 1.0]);
+    }
+
+    #[test]
+    fn frames_map_onto_the_unit_range() {
+        let spacing: BakeSpacing = "-2..2".parse().unwrap();
+        assert_eq!(spacing, BakeSpacing::Frames { start: -2, end: 2 });
+        assert_eq!(spacing.sample_count(), 5);
+        assert_eq!(spacing.xs(), [0.0, 0.25, 0.5, 0.75, 1.0]);
+
+        let table = bake(
+            &[Dot::new(Point::new(0.0, 0.0)), Dot::new(Point::new(1.0, 1.0))],
+            None,
+            SplineSettings::default(),
+            "10..20".parse().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(table.samples.len(), 11);
+        assert!(table.samples.iter().all(|point| (point.x - point.y).abs() < 1e-5));
+    }
+
+    #[test]
+    fn out_of_range_spacings_are_rejected() {
+        for text in [
+            "",
+            "x",
+            "0",
+            "1",
+            "-3",
+            "65537",
+            "5..5",
+            "5..4",
+            "0..65536",
+            "a..3",
+            "-2147483648..2147483647",
+        ] {
+            assert_eq!(text.parse::<BakeSpacing>(), Err(ParseSpacingError), "{text:?}");
+        }
+        assert!("65536".parse::<BakeSpacing>().is_ok());
+        assert!("0..65535".parse::<BakeSpacing>().is_ok());
+    }
+
+    #[test]
+    fn degenerate_spacings_do_not_divide_by_zero() {
+        assert_eq!(BakeSpacing::Count(0).xs(), [0.0; 0]);
+        assert_eq!(BakeSpacing::Count(1).xs(), [0.0]);
+        assert_eq!(BakeSpacing::Frames { start: 3, end: 3 }.xs(), [0.0]);
+        assert_eq!(BakeSpacing::Frames { start: 3, end: 2 }.sample_count(), 0);
+        assert_eq!(
+            BakeSpacing::Frames {
+                start: i32::MIN,
+                end: i32::MAX
+            }
+            .sample_count(),
+            1 << 32
+        );
+    }
+}
@@ -0,0 +1,296 @@
+// Command line access to the curve engine, for build pipelines and CI without a display
+use std::process::ExitCode;
+
+use iced::{Size, Theme};
+use iced_curve_editor::curve::{self, CurveAlgorithm, CurveKind};
+use iced_curve_editor::document::{self, CurveDocument};
+use iced_curve_editor::easing::{CssEasing, Preset};
+use iced_curve_editor::export::{self, ExportFormat, ExportSettings};
+use iced_curve_editor::image_export::Drawing;
+use iced_curve_editor::style::{self, CanvasStyle};
+use iced_curve_editor::{fit, import};
+
+const USAGE: &str = "\
+Usage:
+  curve-cli validate <input>...
+  curve-cli eval <input> <x>...
+  curve-cli query <input> integral <a> <b> | inverse <y> | extrema <a> <b> | roots [<y>]
+  curve-cli sample <input> [--format csv|json|rs|glsl|wgsl|cube] [--resolution N] [--start X] [--end X]
+  curve-cli convert <input> <output> [--resolution N] [--start X] [--end X] [--size WxH] [--theme NAME]
+
+An input is a .curve document, a .csv of x, y samples (fitted with Bézier keys, --tolerance,
+default 0.01), svg:<path data> or css:<timing function>. The output format follows the extension:
+.curve, .csv, .json, .rs, .glsl, .wgsl, .cube, .svg, .png or .css. Images are drawn in the
+editor's default theme unless --theme names another, such as dracula or catppuccin-latte.";
+
+const DEFAULT_TOLERANCE: f32 = 0.01;
+const DEFAULT_IMAGE_SIZE: (u32, u32) = (1024, 768);
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let Some((command, arguments)) = arguments.split_first() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+    let options = match Options::parse(arguments) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{error}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = match (command.as_str(), options.positional.as_slice()) {
+        ("validate", inputs) if !inputs.is_empty() => validate(inputs, &options),
+        ("eval", [input, xs @ ..]) if !xs.is_empty() => eval(input, xs, &options),
+        ("query", [input, question, values @ ..]) => query(input, question, values, &options),
+        ("sample", [input]) => sample(input, &options),
+        ("convert", [input, output]) => convert(input, output, &options),
+        ("help" | "--help" | "-h", _) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Positional arguments and `--name value` options, in any order
+#[derive(Default)]
+struct Options {
+    positional: Vec<String>,
+    format: Option<ExportFormat>,
+    export: ExportSettings,
+    tolerance: Option<f32>,
+    size: Option<(u32, u32)>,
+    theme: Option<Theme>,
+}
+
+impl Options {
+    fn parse(arguments: &[String]) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut arguments = arguments.iter();
+        while let Some(argument) = arguments.next() {
+            let Some(name) = argument.strip_prefix("--") else {
+                options.positional.push(argument.clone());
+                continue;
+            };
+            let value = arguments.next().ok_or(format!("--{name} needs a value"))?;
+            let number = |value: &str| value.parse::<f32>().map_err(|_| format!("\"{value}\" is not a number"));
+            match name {
+                "format" => {
+                    options.format = Some(format_for_extension(value).ok_or(format!("unknown format \"{value}\""))?)
+                }
+                "resolution" => {
+                    options.export.resolution = value
+                        .parse()
+                        .ok()
+                        .filter(|&resolution| resolution >= 2)
+                        .ok_or("the resolution must be a whole number of at least 2")?
+                }
+                "start" => options.export.start = number(value)?,
+                "end" => options.export.end = number(value)?,
+                "tolerance" => {
+                    let tolerance = number(value)?;
+                    if tolerance <= 0.0 {
+                        return Err("the tolerance must be a positive number".into());
+                    }
+                    options.tolerance = Some(tolerance);
+                }
+                "size" => {
+                    let size = value
+                        .split_once('x')
+                        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+                        .filter(|&(width, height)| (1..=16384).contains(&width) && (1..=16384).contains(&height));
+                    options.size = Some(size.ok_or(format!("\"{value}\" is not an image size like 1024x768"))?);
+                }
+                "theme" => options.theme = Some(style::find_theme(value).ok_or(format!("unknown theme \"{value}\""))?),
+                _ => return Err(format!("unknown option --{name}")),
+            }
+        }
+        let (start, end) = (options.export.start, options.export.end);
+        if !(0.0..=1.0).contains(&start) || !(0.0..=1.0).contains(&end) || start >= end {
+            return Err("the range must satisfy 0 <= start < end <= 1".into());
+        }
+        Ok(options)
+    }
+}
+
+fn format_for_extension(extension: &str) -> Option<ExportFormat> {
+    ExportFormat::ALL
+        .into_iter()
+        .find(|format| format.extension().eq_ignore_ascii_case(extension))
+}
+
+// Loads any supported input as a document, so every command accepts the same inputs
+fn load(input: &str, options: &Options) -> Result<CurveDocument, String> {
+    if let Some(d) = input.strip_prefix("svg:") {
+        let segments = import::parse_svg_path(d).map_err(|error| error.to_string())?;
+        return Ok(bezier_document(curve::dots_from_beziers(
+            &import::fit_into_unit_square(&segments, true),
+        )));
+    }
+    if let Some(easing) = input.strip_prefix("css:") {
+        let easing: CssEasing = easing.parse().map_err(|error| format!("{error}"))?;
+        return Ok(bezier_document(Preset::Css(easing).dots()));
+    }
+    match extension(input).as_str() {
+        document::EXTENSION => CurveDocument::read(input).map_err(|error| error.to_string()),
+        "csv" => {
+            let samples = import::read_csv_samples(input).map_err(|error| error.to_string())?;
+            let segments = fit::fit_cubic_beziers(&samples, options.tolerance.unwrap_or(DEFAULT_TOLERANCE))
+                .map_err(|error| error.to_string())?;
+            Ok(bezier_document(curve::dots_from_beziers(
+                &import::fit_into_unit_square(&segments, false),
+            )))
+        }
+        _ => Err(format!(
+            "{input}: not a .curve or .csv file, svg:<path data> or css:<timing function>"
+        )),
+    }
+}
+
+// Evaluating at x and the data formats need one y per x, which free-form paths don't have
+fn function_curve(document: CurveDocument) -> Result<CurveDocument, String> {
+    match document.kind {
+        CurveKind::Function => Ok(document),
+        CurveKind::Path { .. } => Err("the document is a free-form path, which has no single y per x".into()),
+    }
+}
+
+fn bezier_document(dots: Vec<curve::Dot>) -> CurveDocument {
+    CurveDocument {
+        dots,
+        curve_mode: Some(CurveAlgorithm::Bezier),
+        ..CurveDocument::default()
+    }
+}
+
+fn extension(path: &str) -> String {
+    std::path::Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+// Reports every input before failing, so one run lists all broken files
+fn validate(inputs: &[String], options: &Options) -> Result<(), String> {
+    let mut failed = 0;
+    for input in inputs {
+        match load(input, options).and_then(|document| document.validate().map_err(|error| error.to_string())) {
+            Ok(()) => println!("{input}: ok"),
+            Err(error) => {
+                println!("{input}: {error}");
+                failed += 1;
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("{failed} of {} inputs are invalid", inputs.len())),
+    }
+}
+
+// One `x y` line per x value, in the order given
+fn eval(input: &str, xs: &[String], options: &Options) -> Result<(), String> {
+    let xs = xs
+        .iter()
+        .map(|x| x.parse::<f32>().map_err(|_| format!("\"{x}\" is not a number")))
+        .collect::<Result<Vec<f32>, String>>()?;
+    let ys = function_curve(load(input, options)?)?
+        .evaluate_at(&xs)
+        .map_err(|error| error.to_string())?;
+    for (x, y) in xs.iter().zip(ys) {
+        println!("{x} {y:.6}");
+    }
+    Ok(())
+}
+
+// Integrals, inverses, extremes and roots, printed one answer per line
+fn query(input: &str, question: &str, values: &[String], options: &Options) -> Result<(), String> {
+    let values = values
+        .iter()
+        .map(|value| value.parse::<f32>().map_err(|_| format!("\"{value}\" is not a number")))
+        .collect::<Result<Vec<f32>, String>>()?;
+    let curve = function_curve(load(input, options)?)?
+        .function_curve()
+        .map_err(|error| error.to_string())?;
+    match (question, values.as_slice()) {
+        ("integral", &[a, b]) => println!("{:.6}", curve.integral(a, b)),
+        ("inverse", &[y]) => match curve.inverse(y) {
+            Some(x) => println!("{x:.6}"),
+            None if !curve.is_monotone() => return Err("the curve is not monotone, try roots <y>".into()),
+            None => return Err(format!("the curve never reaches y = {y}")),
+        },
+        ("extrema", &[a, b]) => {
+            let (lowest, highest) = curve.extrema(a, b);
+            println!("min {:.6} {:.6}", lowest.x, lowest.y);
+            println!("max {:.6} {:.6}", highest.x, highest.y);
+        }
+        ("roots", &[]) | ("roots", &[_]) => {
+            for x in curve.solve(values.first().copied().unwrap_or(0.0)) {
+                println!("{x:.6}");
+            }
+        }
+        _ => return Err(format!("unknown query \"{question}\" or wrong number of values")),
+    }
+    Ok(())
+}
+
+fn sample(input: &str, options: &Options) -> Result<(), String> {
+    let document = function_curve(load(input, options)?)?;
+    let samples = document.export_samples(options.export).map_err(|error| error.to_string())?;
+    print!(
+        "{}",
+        export::format_samples(options.format.unwrap_or_default(), &samples).map_err(|error| error.to_string())?
+    );
+    Ok(())
+}
+
+fn convert(input: &str, output: &str, options: &Options) -> Result<(), String> {
+    let document = load(input, options)?;
+    let contents = match extension(output).as_str() {
+        document::EXTENSION => document.to_string().into_bytes(),
+        "css" => {
+            let document = function_curve(document)?;
+            let easing =
+                CssEasing::from_curve(&document.dots, document.curve_mode).map_err(|error| error.to_string())?;
+            format!("{easing}\n").into_bytes()
+        }
+        image @ ("svg" | "png") => {
+            let (width, height) = options.size.unwrap_or(DEFAULT_IMAGE_SIZE);
+            let drawing = Drawing {
+                dots: &document.dots,
+                kind: document.kind,
+                straight_mode: document.curve_mode.is_none(),
+                curve_mode: document.curve_mode,
+                spline_settings: document.spline_settings,
+                // The editor's saved theme is left out, so the same command draws the same image anywhere
+                style: CanvasStyle::from_theme(options.theme.as_ref().unwrap_or(&style::EDITOR_THEME)),
+            };
+            if image == "svg" {
+                drawing.to_svg(Size::new(width as f32, height as f32)).into_bytes()
+            } else {
+                drawing.to_png(width, height)?
+            }
+        }
+        other => {
+            let format = format_for_extension(other).ok_or(format!("{output}: unknown output format"))?;
+            let document = function_curve(document)?;
+            let samples = document.export_samples(options.export).map_err(|error| error.to_string())?;
+            export::format_samples(format, &samples)
+                .map_err(|error| error.to_string())?
+                .into_bytes()
+        }
+    };
+    std::fs::write(output, contents).map_err(|error| format!("{output}: {error}"))
+}
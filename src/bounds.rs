@@ -0,0 +1,149 @@
+// Limits on a curve's values, for curves such as normalized gains that must stay within [0, 1]
+use std::fmt;
+
+use iced::Point;
+
+use crate::curve::Dot;
+
+/// What keeps a bounded curve inside its range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BoundsPolicy {
+    /// Keys can't be placed or dragged outside. The curve between them can still overshoot.
+    #[default]
+    Reject,
+    /// Keys, and Bézier handles, are moved inside.
+    ClampKeys,
+    /// Keys are clamped and the curve is clipped to the range, so it never leaves it. This cuts
+    /// the interpolated curve rather than interpolating differently: where it would have overshot
+    /// it runs flat along the edge, with a corner where it meets it, and the keys keep the tangents
+    /// the algorithm gave them.
+    Bounded,
+}
+
+impl BoundsPolicy {
+    pub const ALL: [BoundsPolicy; 3] = [BoundsPolicy::Reject, BoundsPolicy::ClampKeys, BoundsPolicy::Bounded];
+
+    /// Name in documents.
+    pub fn name(self) -> &'static str {
+        match self {
+            BoundsPolicy::Reject => "reject",
+            BoundsPolicy::ClampKeys => "clamp-keys",
+            BoundsPolicy::Bounded => "bounded",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|policy| policy.name() == name)
+    }
+}
+
+impl fmt::Display for BoundsPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BoundsPolicy::Reject => "Reject edits outside",
+            BoundsPolicy::ClampKeys => "Clamp keys",
+            BoundsPolicy::Bounded => "Clamp keys, clip curve",
+        })
+    }
+}
+
+/// The range `min..=max` a curve's values must stay in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValueBounds {
+    pub min: f32,
+    pub max: f32, // Never below min
+    pub policy: BoundsPolicy,
+}
+
+impl Default for ValueBounds {
+    fn default() -> Self {
+        Self {
+            min: 0.0,
+            max: 1.0,
+            policy: BoundsPolicy::default(),
+        }
+    }
+}
+
+impl ValueBounds {
+    pub fn contains(self, y: f32) -> bool {
+        (self.min..=self.max).contains(&y)
+    }
+
+    pub fn clamp(self, y: f32) -> f32 {
+        y.clamp(self.min, self.max)
+    }
+
+    /// Moves keys inside the range under the clamping policies, along with the ends of their
+    /// Bézier handles, since a Bézier segment stays within the range of its control points.
+    /// Returns how many keys changed.
+    pub fn clamp_keys(self, dots: &mut [Dot]) -> usize {
+        if self.policy == BoundsPolicy::Reject {
+            return 0;
+        }
+        let mut changed = 0;
+        for dot in dots {
+            let before = *dot;
+            dot.position.y = self.clamp(dot.position.y);
+            for handle in [&mut dot.in_handle, &mut dot.out_handle].into_iter().flatten() {
+                handle.y = self.clamp(dot.position.y + handle.y) - dot.position.y;
+            }
+            changed += usize::from(*dot != before);
+        }
+        changed
+    }
+
+    /// Clips the samples to the range under the `Bounded` policy, moving each one that's outside
+    /// straight onto the nearest edge.
+    pub fn apply(self, samples: &mut [Point]) {
+        if self.policy == BoundsPolicy::Bounded {
+            for sample in samples {
+                sample.y = self.clamp(sample.y);
+            }
+        }
+    }
+}
+
+/// Reads a range typed as `min..max`, with min below max.
+pub fn parse_range(text: &str) -> Option<(f32, f32)> {
+    let (min, max) = text.trim().split_once("..")?;
+    let (min, max) = (min.trim().parse::<f32>().ok()?, max.trim().parse::<f32>().ok()?);
+    (min.is_finite() && max.is_finite() && min < max).then_some((min, max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{self, CurveAlgorithm, SplineSettings};
+
+    #[test]
+    fn bounded_curves_stay_in_range() {
+        // The natural cubic spline overshoots between these keys
+        let mut dots: Vec<Dot> = [(0.0, 0.0), (0.3, 1.0), (0.35, 1.0), (0.7, 0.0), (1.0, 0.1)]
+            .map(|(x, y)| Dot::new(Point::new(x, y)))
+            .to_vec();
+        let mode = Some(CurveAlgorithm::NaturalCubicSpline);
+        let mut samples = curve::sample_curve(mode, &dots, SplineSettings::default()).unwrap();
+        let bounds = ValueBounds {
+            policy: BoundsPolicy::Bounded,
+            ..ValueBounds::default()
+        };
+        assert!(samples.iter().any(|sample| !bounds.contains(sample.y)));
+        bounds.apply(&mut samples);
+        assert!(samples.iter().all(|sample| bounds.contains(sample.y)));
+
+        dots[1].position.y = 1.5;
+        dots[1].out_handle = Some(iced::Vector::new(0.1, -0.2));
+        assert_eq!(bounds.clamp_keys(&mut dots), 1);
+        assert_eq!(dots[1].position.y, 1.0);
+        // The handle's end was already in range
+        assert!(dots[1].out_handle.is_some_and(|handle| (handle.y + 0.2).abs() < 1e-6));
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(parse_range(" -1 .. 2.5"), Some((-1.0, 2.5)));
+        assert_eq!(parse_range("1..1"), None);
+        assert_eq!(parse_range("0..x"), None);
+    }
+}
@@ -0,0 +1,240 @@
+// B-spline and NURBS evaluation (approximating curves, unlike the interpolating schemes in curve.rs)
+use iced::Point;
+
+use crate::curve::{check_points, CurveError, SegmentSampler};
+
+// Guards the de Boor recurrence against zero-length knot spans (repeated knots).
+const KNOT_EPSILON: f32 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnotVector {
+    /// Evenly spaced knots; the curve floats inside the control polygon.
+    Uniform,
+    /// Degree + 1 repeated knots at each end so the curve starts and ends on the first and last points.
+    Clamped,
+}
+
+impl KnotVector {
+    pub fn toggle(self) -> Self {
+        match self {
+            KnotVector::Uniform => KnotVector::Clamped,
+            KnotVector::Clamped => KnotVector::Uniform,
+        }
+    }
+
+    /// Builds the `n_points + degree + 1` knots for a curve with `n_points` control points.
+    pub fn knots(self, n_points: usize, degree: usize) -> Vec<f32> {
+        let m = n_points + degree + 1;
+        match self {
+            KnotVector::Uniform => (0..m).map(|i| i as f32).collect(),
+            KnotVector::Clamped => {
+                let spans = (n_points - degree) as f32;
+                (0..m)
+                    .map(|i| {
+                        if i <= degree {
+                            0.0
+                        } else if i >= n_points {
+                            1.0
+                        } else {
+                            (i - degree) as f32 / spans
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Evaluates a NURBS curve at parameter `u` with de Boor's algorithm in homogeneous coordinates.
+/// A plain B-spline is the special case where every weight is 1.
+pub fn evaluate_nurbs(points: &[Point], weights: &[f32], degree: usize, knots: &[f32], u: f32) -> Point {
+    let n = points.len();
+
+    // Find the knot span k with knots[k] <= u < knots[k + 1], restricted to the valid domain.
+    let k = degree + knots[degree + 1..n].partition_point(|&knot| knot <= u);
+
+    let mut d: Vec<[f32; 3]> = (0..=degree)
+        .map(|j| {
+            let i = j + k - degree;
+            let w = weights[i];
+            [points[i].x * w, points[i].y * w, w]
+        })
+        .collect();
+
+    for r in 1..=degree {
+        for j in (r..=degree).rev() {
+            let i = j + k - degree;
+            let denom = knots[i + degree + 1 - r] - knots[i];
+            let alpha = if denom.abs() < KNOT_EPSILON {
+                0.0
+            } else {
+                (u - knots[i]) / denom
+            };
+            let previous = d[j - 1];
+            for (value, previous) in d[j].iter_mut().zip(previous) {
+                *value = (1.0 - alpha) * previous + alpha * *value;
+            }
+        }
+    }
+
+    let [x, y, w] = d[degree];
+    Point { x: x / w, y: y / w }
+}
+
+// Checks the control points and weights, and lowers the degree when there are too few points for it
+fn check_nurbs(points: &[Point], weights: &[f32], degree: usize) -> Result<usize, CurveError> {
+    let xs: Vec<f32> = points.iter().map(|point| point.x).collect();
+    let ys: Vec<f32> = points.iter().map(|point| point.y).collect();
+    check_points(&xs, &ys, 2)?;
+    check_points(&xs, weights, 2)?;
+    if let Some(&weight) = weights.iter().find(|&&weight| weight <= 0.0) {
+        // The rational basis divides by the weighted sum, which positive weights keep away from zero
+        return Err(CurveError::InvalidWeight(weight));
+    }
+    Ok(degree.clamp(1, points.len() - 1))
+}
+
+/// Samples a NURBS curve into a polyline. The degree is lowered when there are too few points for it.
+pub fn sample_nurbs(
+    points: &[Point],
+    weights: &[f32],
+    degree: usize,
+    knot_vector: KnotVector,
+    n_samples: usize,
+) -> Result<Vec<Point>, CurveError> {
+    let degree = check_nurbs(points, weights, degree)?;
+    let n_samples = n_samples.max(2);
+    let knots = knot_vector.knots(points.len(), degree);
+    let (start, end) = (knots[degree], knots[points.len()]);
+
+    Ok((0..n_samples)
+        .map(|i| {
+            let u = start + (end - start) * i as f32 / (n_samples - 1) as f32;
+            evaluate_nurbs(points, weights, degree, &knots, u)
+        })
+        .collect())
+}
+
+// Samples span by span, so spans outside the sampler's window are skipped. With positive weights
+// each span stays inside the hull of the degree + 1 control points it depends on.
+pub(crate) fn sample_nurbs_in(
+    points: &[Point],
+    weights: &[f32],
+    degree: usize,
+    knot_vector: KnotVector,
+    mut sampler: SegmentSampler,
+) -> Result<SegmentSampler, CurveError> {
+    let degree = check_nurbs(points, weights, degree)?;
+    let knots = knot_vector.knots(points.len(), degree);
+    for k in degree..points.len() {
+        let (start, end) = (knots[k], knots[k + 1]);
+        if end - start < KNOT_EPSILON {
+            continue;
+        }
+        sampler.segment(&points[k - degree..=k], |t| {
+            evaluate_nurbs(points, weights, degree, &knots, start + (end - start) * t)
+        });
+    }
+    Ok(sampler)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn points() -> Vec<Point> {
+        [(0.0, 0.0), (0.2, 0.9), (0.5, 0.1), (0.8, 0.7), (1.0, 0.3)]
+            .map(|(x, y)| Point::new(x, y))
+            .to_vec()
+    }
+
+    fn assert_near(a: Point, b: Point) {
+        assert!(
+            (a.x - b.x).abs() < EPSILON && (a.y - b.y).abs() < EPSILON,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn clamped_curves_start_and_end_on_the_control_polygon() {
+        let points = points();
+        let samples = sample_nurbs(&points, &[1.0; 5], 3, KnotVector::Clamped, 50).unwrap();
+        assert_near(samples[0], points[0]);
+        assert_near(*samples.last().unwrap(), points[4]);
+    }
+
+    #[test]
+    fn unit_weights_give_the_b_spline() {
+        // With every weight equal the rational basis reduces to the plain one, so scaling them
+        // all changes nothing either
+        let points = points();
+        for knots in [KnotVector::Uniform, KnotVector::Clamped] {
+            let ones = sample_nurbs(&points, &[1.0; 5], 3, knots, 50).unwrap();
+            let threes = sample_nurbs(&points, &[3.0; 5], 3, knots, 50).unwrap();
+            let knot_values = knots.knots(points.len(), 3);
+            for (i, (a, b)) in ones.iter().zip(&threes).enumerate() {
+                assert_near(*a, *b);
+                // The polynomial B-spline, straight from the basis functions. Those are half open,
+                // so the domain end is left out
+                let u = knot_values[3] + (knot_values[5] - knot_values[3]) * i as f32 / 49.0;
+                if i < 49 {
+                    assert_near(*a, b_spline(&points, 3, &knot_values, u));
+                }
+            }
+        }
+    }
+
+    // Cox-de Boor basis functions, evaluated independently of `evaluate_nurbs`
+    fn b_spline(points: &[Point], degree: usize, knots: &[f32], u: f32) -> Point {
+        fn basis(i: usize, p: usize, knots: &[f32], u: f32) -> f32 {
+            if p == 0 {
+                return if knots[i] <= u && u < knots[i + 1] { 1.0 } else { 0.0 };
+            }
+            let ratio = |numerator: f32, denominator: f32| {
+                if denominator == 0.0 {
+                    0.0
+                } else {
+                    numerator / denominator
+                }
+            };
+            ratio(u - knots[i], knots[i + p] - knots[i]) * basis(i, p - 1, knots, u)
+                + ratio(knots[i + p + 1] - u, knots[i + p + 1] - knots[i + 1]) * basis(i + 1, p - 1, knots, u)
+        }
+        points.iter().enumerate().fold(Point::ORIGIN, |sum, (i, point)| {
+            let weight = basis(i, degree, knots, u);
+            Point::new(sum.x + point.x * weight, sum.y + point.y * weight)
+        })
+    }
+
+    #[test]
+    fn degree_one_is_the_control_polygon() {
+        // Samples fall on the keys and halfway between them
+        let points = points();
+        let samples = sample_nurbs(&points, &[1.0; 5], 1, KnotVector::Uniform, 9).unwrap();
+        for (i, sample) in samples.iter().enumerate() {
+            let (a, b) = (points[i / 2], points[i.div_ceil(2)]);
+            assert_near(*sample, Point::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0));
+        }
+    }
+
+    #[test]
+    fn too_few_points_for_the_degree_lower_it() {
+        let points = &points()[..3];
+        for knots in [KnotVector::Uniform, KnotVector::Clamped] {
+            let samples = sample_nurbs(points, &[1.0; 3], 5, knots, 20).unwrap();
+            assert!(samples
+                .iter()
+                .all(|sample| sample.x.is_finite() && sample.y.is_finite()));
+            assert_eq!(samples, sample_nurbs(points, &[1.0; 3], 2, knots, 20).unwrap());
+        }
+        assert!(sample_nurbs(&points[..1], &[1.0], 3, KnotVector::Uniform, 20).is_err());
+    }
+
+    #[test]
+    fn non_positive_weights_are_reported() {
+        let result = sample_nurbs(&points(), &[1.0, 0.0, 1.0, 1.0, 1.0], 3, KnotVector::Uniform, 20);
+        assert_eq!(result, Err(CurveError::InvalidWeight(0.0)));
+    }
+}
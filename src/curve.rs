@@ -0,0 +1,1112 @@
+// Curve model shared by the canvas and the editing tools: keys, algorithms and sampling
+use std::fmt;
+use std::ops::Range;
+
+use iced::{Color, Point, Size, Vector};
+
+use crate::bounds::ValueBounds;
+use crate::bspline::{self, KnotVector};
+use crate::fit;
+use crate::path;
+use crate::query::FunctionCurve;
+use crate::hermite::{self, HermiteTangents};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CurveAlgorithm {
+    CatmullRom,
+    MonotonicSpline,
+    NaturalCubicSpline,
+    Pchip,
+    Steffen,
+    Akima,
+    Bezier,
+    BSpline,
+    Nurbs,
+}
+
+impl CurveAlgorithm {
+    pub const ALL: [CurveAlgorithm; 9] = [
+        CurveAlgorithm::CatmullRom,
+        CurveAlgorithm::MonotonicSpline,
+        CurveAlgorithm::NaturalCubicSpline,
+        CurveAlgorithm::Pchip,
+        CurveAlgorithm::Steffen,
+        CurveAlgorithm::Akima,
+        CurveAlgorithm::Bezier,
+        CurveAlgorithm::BSpline,
+        CurveAlgorithm::Nurbs,
+    ];
+
+    /// Short name used in curve documents and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            CurveAlgorithm::CatmullRom => "catmull-rom",
+            CurveAlgorithm::MonotonicSpline => "monotonic",
+            CurveAlgorithm::NaturalCubicSpline => "natural-cubic",
+            CurveAlgorithm::Pchip => "pchip",
+            CurveAlgorithm::Steffen => "steffen",
+            CurveAlgorithm::Akima => "akima",
+            CurveAlgorithm::Bezier => "bezier",
+            CurveAlgorithm::BSpline => "b-spline",
+            CurveAlgorithm::Nurbs => "nurbs",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|algorithm| algorithm.name() == name)
+    }
+
+    /// Position in `ALL`.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    // Approximating curves don't pass through the keys, so their control polygon is drawn as well
+    pub fn has_control_polygon(self) -> bool {
+        matches!(self, CurveAlgorithm::BSpline | CurveAlgorithm::Nurbs)
+    }
+
+    pub fn hermite_tangents(self) -> Option<HermiteTangents> {
+        match self {
+            CurveAlgorithm::Pchip => Some(HermiteTangents::Pchip),
+            CurveAlgorithm::Steffen => Some(HermiteTangents::Steffen),
+            CurveAlgorithm::Akima => Some(HermiteTangents::Akima),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CurveAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CurveAlgorithm::CatmullRom => "Catmull-Rom",
+            CurveAlgorithm::MonotonicSpline => "Monotonic",
+            CurveAlgorithm::NaturalCubicSpline => "Natural Cubic",
+            CurveAlgorithm::Pchip => "PCHIP",
+            CurveAlgorithm::Steffen => "Steffen",
+            CurveAlgorithm::Akima => "Akima",
+            CurveAlgorithm::Bezier => "Bézier",
+            CurveAlgorithm::BSpline => "B-Spline",
+            CurveAlgorithm::Nurbs => "NURBS",
+        })
+    }
+}
+
+/// Why a curve could not be built from its keys.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CurveError {
+    TooFewPoints { needed: usize, found: usize },
+    DuplicateX(f32), // Also reported for keys out of order, the interpolating splines need x strictly increasing
+    NonFinite,
+    SingularSystem,
+    InvalidWeight(f32), // A NURBS weight that isn't positive
+}
+
+impl fmt::Display for CurveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CurveError::TooFewPoints { needed, found } => {
+                write!(f, "at least {needed} points are needed, found {found}")
+            }
+            CurveError::DuplicateX(x) => write!(f, "more than one key at x = {x:.3}"),
+            CurveError::NonFinite => write!(f, "a key is infinite or not a number"),
+            CurveError::SingularSystem => write!(f, "the spline equations have no unique solution"),
+            CurveError::InvalidWeight(weight) => write!(f, "NURBS weights must be positive, found {weight}"),
+        }
+    }
+}
+
+// Input checks shared by the builders that take separate x and y values
+pub(crate) fn check_points(xs: &[f32], ys: &[f32], needed: usize) -> Result<(), CurveError> {
+    let found = xs.len().min(ys.len());
+    if found < needed {
+        return Err(CurveError::TooFewPoints { needed, found });
+    }
+    if xs.len() != ys.len() {
+        // Every x needs its y, the unpaired values count as missing points
+        return Err(CurveError::TooFewPoints {
+            needed: xs.len().max(ys.len()),
+            found,
+        });
+    }
+    if !xs.iter().chain(ys).all(|value| value.is_finite()) {
+        return Err(CurveError::NonFinite);
+    }
+    Ok(())
+}
+
+pub(crate) fn check_increasing(xs: &[f32]) -> Result<(), CurveError> {
+    match xs.windows(2).find(|pair| pair[1] <= pair[0]) {
+        Some(pair) => Err(CurveError::DuplicateX(pair[1])),
+        None => Ok(()),
+    }
+}
+
+// Settings shared by the B-spline and NURBS curve algorithms
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SplineSettings {
+    pub degree: usize,
+    pub knots: KnotVector,
+}
+
+impl Default for SplineSettings {
+    fn default() -> Self {
+        Self {
+            degree: 3,
+            knots: KnotVector::Uniform,
+        }
+    }
+}
+
+// Dots live in curve space: the unit square with y pointing up. The canvas stretches it over its bounds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dot {
+    pub position: iced::Point,
+    pub weight: f32, // NURBS weight, ignored by the other curve algorithms
+    // Bézier handles as offsets from the position; None uses a straight third of the way to the neighbour
+    pub in_handle: Option<Vector>,
+    pub out_handle: Option<Vector>,
+    pub color: Option<Color>, // Gradient key color; None is gray at the dot's height
+}
+
+impl Dot {
+    pub fn new(position: Point) -> Self {
+        Dot {
+            position,
+            weight: 1.0,
+            in_handle: None,
+            out_handle: None,
+            color: None,
+        }
+    }
+
+    // Same dot in canvas pixels, including its handles
+    pub fn to_canvas(self, size: Size) -> Self {
+        let scale = |v: Vector| Vector::new(v.x * size.width, -v.y * size.height);
+        Dot {
+            position: curve_to_canvas(self.position, size),
+            in_handle: self.in_handle.map(scale),
+            out_handle: self.out_handle.map(scale),
+            ..self
+        }
+    }
+}
+
+pub fn curve_to_canvas(position: Point, size: Size) -> Point {
+    Point::new(position.x * size.width, (1.0 - position.y) * size.height)
+}
+
+pub fn canvas_to_curve(position: Point, size: Size) -> Point {
+    Point::new(position.x / size.width, 1.0 - position.y / size.height)
+}
+
+/// Keys sorted by x, with flat extensions to the left and right edges of curve space as they are drawn.
+/// No edge key is added on a side where a dot already reaches the edge.
+pub fn with_edge_keys(dots: &[Dot]) -> Vec<Dot> {
+    let mut keys = dots.to_vec();
+    if keys.is_empty() {
+        return keys;
+    }
+    keys.sort_by(|a, b| a.position.x.total_cmp(&b.position.x));
+    let first = keys[0].position;
+    let last = keys[keys.len() - 1].position;
+    if first.x > 0.0 {
+        keys.insert(0, Dot::new(Point { x: 0.0, y: first.y }));
+    }
+    if last.x < 1.0 {
+        keys.push(Dot::new(Point { x: 1.0, y: last.y }));
+    }
+    keys
+}
+
+/// Smallest x distance between neighbouring keys of a function curve, in curve space.
+pub const MIN_KEY_SPACING: f32 = 1e-3;
+
+/// Makes the dots a function of x: sorted, without non-finite positions or keys closer than
+/// `MIN_KEY_SPACING` to the previous one, and with handles clamped by `clamp_handles`.
+/// Returns the new index of every dot by its old one, `None` for the dots that were removed.
+pub fn make_function_curve(dots: &mut Vec<Dot>) -> Vec<Option<usize>> {
+    let mut moved = vec![None; dots.len()];
+    let mut keys: Vec<(usize, Dot)> = dots.drain(..).enumerate().collect();
+    keys.retain(|(_, dot)| dot.position.x.is_finite() && dot.position.y.is_finite());
+    keys.sort_by(|(_, a), (_, b)| a.position.x.total_cmp(&b.position.x));
+    keys.dedup_by(|(_, later), (_, earlier)| later.position.x - earlier.position.x < MIN_KEY_SPACING);
+    for (index, (before, dot)) in keys.into_iter().enumerate() {
+        moved[before] = Some(index);
+        dots.push(dot);
+    }
+    let all = 0..dots.len();
+    clamp_handles(dots, all);
+    moved
+}
+
+/// How far in x the key at `index` of a function curve can move, staying in the unit square and
+/// at least `MIN_KEY_SPACING` from its neighbours.
+pub fn key_x_range(dots: &[Dot], index: usize) -> (f32, f32) {
+    // A little more than the minimum, so rounding can't leave the keys close enough to be merged
+    let spacing = MIN_KEY_SPACING * 1.001;
+    let before = index.checked_sub(1).and_then(|i| dots.get(i));
+    let low = before.map_or(0.0, |dot| dot.position.x + spacing);
+    let high = dots.get(index + 1).map_or(1.0, |dot| dot.position.x - spacing);
+    (low, high.max(low))
+}
+
+/// Shortens the Bézier handles of the x-sorted dots in `range` so no handle reaches past the
+/// neighbouring key (or the edge of the unit square) in x. With both control points inside the x
+/// range of their segment, x(t) can't decrease, so the curve never doubles back.
+pub fn clamp_handles(dots: &mut [Dot], range: Range<usize>) {
+    for i in range.start..range.end.min(dots.len()) {
+        let x = dots[i].position.x;
+        let before = if i > 0 { dots[i - 1].position.x } else { 0.0 };
+        let after = dots.get(i + 1).map_or(1.0, |dot| dot.position.x);
+        let dot = &mut dots[i];
+        dot.in_handle = dot.in_handle.map(|handle| clamp_handle(handle, (before - x).min(0.0)));
+        dot.out_handle = dot.out_handle.map(|handle| clamp_handle(handle, (after - x).max(0.0)));
+    }
+}
+
+// Scales a handle along its own direction until its x lies between 0 and `reach` (of either sign)
+fn clamp_handle(handle: Vector, reach: f32) -> Vector {
+    if handle.x * reach < 0.0 {
+        return Vector::new(0.0, handle.y); // Points the wrong way, only its height is kept
+    }
+    if handle.x.abs() > reach.abs() {
+        handle * (reach.abs() / handle.x.abs())
+    } else {
+        handle
+    }
+}
+
+/// Samples the curve through `keys` (as returned by `with_edge_keys`) into a polyline.
+/// `None` is the straight-line connector through the keys.
+///
+/// Every algorithm needs two finite keys; the interpolating splines (monotonic, PCHIP, Steffen and
+/// Akima) also need x strictly increasing.
+pub fn sample_curve(
+    curve_mode: Option<CurveAlgorithm>,
+    keys: &[Dot],
+    spline_settings: SplineSettings,
+) -> Result<Vec<Point>, CurveError> {
+    sample_curve_in(curve_mode, keys, spline_settings, SampleWindow::FULL)
+}
+
+// Segments never get more samples than this, which is the density of `SampleWindow::FULL`
+pub const MAX_SEGMENT_SAMPLES: usize = 50;
+
+// Pixels between samples when sampling for the screen
+const SCREEN_SAMPLE_SPACING: f32 = 2.0;
+
+/// The visible part of curve space and how densely to sample it. Segments entirely outside
+/// `start..=end` in x are skipped, the rest get `samples_per_unit` samples per unit of their
+/// extent, so a curve with tens of thousands of keys costs no more than the screen can show.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleWindow {
+    pub start: f32,
+    pub end: f32,
+    pub samples_per_unit: f32,
+}
+
+impl SampleWindow {
+    /// Every segment at the highest density, for exporting and evaluating.
+    pub const FULL: SampleWindow = SampleWindow {
+        start: f32::NEG_INFINITY,
+        end: f32::INFINITY,
+        samples_per_unit: f32::INFINITY,
+    };
+
+    /// The unit square shown `width` pixels wide.
+    pub fn screen(width: f32) -> Self {
+        Self {
+            start: 0.0,
+            end: 1.0,
+            samples_per_unit: width / SCREEN_SAMPLE_SPACING,
+        }
+    }
+
+    /// Number of samples for a segment that stays inside the bounding box of `hull`, or `None` when
+    /// that box is outside the window.
+    pub fn segment_samples(&self, hull: &[Point]) -> Option<usize> {
+        let (mut min, mut max) = (
+            Point::new(f32::INFINITY, f32::INFINITY),
+            Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+        );
+        for point in hull {
+            min = Point::new(min.x.min(point.x), min.y.min(point.y));
+            max = Point::new(max.x.max(point.x), max.y.max(point.y));
+        }
+        if max.x < self.start || min.x > self.end {
+            return None;
+        }
+        let visible_width = max.x.min(self.end) - min.x.max(self.start);
+        let samples = visible_width.max(max.y - min.y) * self.samples_per_unit;
+        Some(if samples.is_nan() {
+            MAX_SEGMENT_SAMPLES // A point-sized segment at infinite density
+        } else {
+            samples.ceil().clamp(1.0, MAX_SEGMENT_SAMPLES as f32) as usize
+        })
+    }
+}
+
+// Joins sampled segments into one polyline. A segment's start is only added when the segment
+// before it was culled, so neighbours don't repeat their shared point.
+pub(crate) struct SegmentSampler {
+    window: SampleWindow,
+    samples: Vec<Point>,
+    joined: bool,
+}
+
+impl SegmentSampler {
+    pub(crate) fn new(window: SampleWindow) -> Self {
+        Self {
+            window,
+            samples: Vec::new(),
+            joined: false,
+        }
+    }
+
+    // Samples `evaluate` over t in [0, 1] if the segment, which stays inside `hull`, is visible
+    pub(crate) fn segment(&mut self, hull: &[Point], evaluate: impl Fn(f32) -> Point) {
+        let Some(count) = self.window.segment_samples(hull) else {
+            self.joined = false;
+            return;
+        };
+        let first = usize::from(self.joined);
+        self.samples
+            .extend((first..=count).map(|j| evaluate(j as f32 / count as f32)));
+        self.joined = true;
+    }
+
+    pub(crate) fn finish(self) -> Vec<Point> {
+        self.samples
+    }
+}
+
+// Knot spacing exponent of function Catmull-Rom curves. 0.5 is the usual centripetal one
+const CATMULL_ROM_ALPHA: f32 = 0.3;
+
+/// Like `sample_curve`, but only samples the segments inside `window`, at its density.
+pub fn sample_curve_in(
+    curve_mode: Option<CurveAlgorithm>,
+    keys: &[Dot],
+    spline_settings: SplineSettings,
+    window: SampleWindow,
+) -> Result<Vec<Point>, CurveError> {
+    let xs: Vec<f32> = keys.iter().map(|dot| dot.position.x).collect();
+    let ys: Vec<f32> = keys.iter().map(|dot| dot.position.y).collect();
+    check_points(&xs, &ys, 2)?;
+    let Some(curve_mode) = curve_mode else {
+        return Ok(keys.iter().map(|dot| dot.position).collect());
+    };
+    let mut sampler = SegmentSampler::new(window);
+    let last = keys.len() - 1;
+
+    match curve_mode {
+        CurveAlgorithm::CatmullRom => {
+            let alpha = CATMULL_ROM_ALPHA;
+            for i in 0..last {
+                // Clamp control points at the ends for safe interpolation
+                let [p0, p1, p2, p3] = [i.saturating_sub(1), i, i + 1, (i + 2).min(last)].map(|j| keys[j].position);
+                // The segment can bulge slightly past these four points, which is fine for culling
+                sampler.segment(&[p0, p1, p2, p3], |t| Point {
+                    x: catmull_rom_centripetal(t, p0.x, p1.x, p2.x, p3.x, alpha),
+                    y: catmull_rom_centripetal(t, p0.y, p1.y, p2.y, p3.y, alpha),
+                });
+            }
+        }
+        CurveAlgorithm::MonotonicSpline
+        | CurveAlgorithm::Pchip
+        | CurveAlgorithm::Steffen
+        | CurveAlgorithm::Akima => {
+            let tangents = match curve_mode.hermite_tangents() {
+                Some(method) => method.tangents(&xs, &ys)?,
+                None => monotonic_tangents(&xs, &ys)?,
+            };
+            for i in 0..last {
+                let (x0, x1, y0, y1) = (xs[i], xs[i + 1], ys[i], ys[i + 1]);
+                let h = x1 - x0;
+                let (m0, m1) = (tangents[i] * h, tangents[i + 1] * h);
+                // Bézier control points of the segment, whose hull contains it
+                let hull = [
+                    Point::new(x0, y0),
+                    Point::new(x0 + h / 3.0, y0 + m0 / 3.0),
+                    Point::new(x1 - h / 3.0, y1 - m1 / 3.0),
+                    Point::new(x1, y1),
+                ];
+                sampler.segment(&hull, |t| Point {
+                    x: x0 + t * h,
+                    y: hermite::hermite_segment(y0, y1, m0, m1, t),
+                });
+            }
+        }
+        CurveAlgorithm::NaturalCubicSpline => {
+            // Compute natural cubic spline coefficients
+            let x_spline = compute_natural_cubic_spline(&xs)?;
+            let y_spline = compute_natural_cubic_spline(&ys)?;
+
+            for (x_cubic, y_cubic) in x_spline.iter().zip(&y_spline) {
+                let hull: Vec<Point> = cubic_hull(x_cubic)
+                    .into_iter()
+                    .zip(cubic_hull(y_cubic))
+                    .map(|(x, y)| Point { x, y })
+                    .collect();
+                sampler.segment(&hull, |t| Point {
+                    x: evaluate_cubic(x_cubic, t),
+                    y: evaluate_cubic(y_cubic, t),
+                });
+            }
+        }
+        CurveAlgorithm::Bezier => {
+            for pair in keys.windows(2) {
+                let bezier = bezier_segment(&pair[0], &pair[1]);
+                sampler.segment(&bezier, |t| fit::evaluate_bezier(&bezier, t));
+            }
+        }
+        CurveAlgorithm::BSpline | CurveAlgorithm::Nurbs => {
+            let points: Vec<Point> = keys.iter().map(|dot| dot.position).collect();
+            // A plain B-spline is a NURBS curve with all weights equal to 1
+            let weights: Vec<f32> = if curve_mode == CurveAlgorithm::Nurbs {
+                keys.iter().map(|dot| dot.weight).collect()
+            } else {
+                vec![1.0; keys.len()]
+            };
+            sampler = bspline::sample_nurbs_in(
+                &points,
+                &weights,
+                spline_settings.degree,
+                spline_settings.knots,
+                sampler,
+            )?;
+        }
+    }
+
+    let mut samples = sampler.finish();
+    // Finite keys can still overflow (huge weights or handles), drop what didn't survive
+    samples.retain(|point| point.x.is_finite() && point.y.is_finite());
+    Ok(samples)
+}
+
+/// How the keys make a curve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CurveKind {
+    /// y as a function of x: keys are sorted by x and held level out to the edges.
+    #[default]
+    Function,
+    /// A free-form path through the keys in the order they were placed, which can loop and cross
+    /// itself. A closed path joins the last key back to the first.
+    Path { closed: bool },
+}
+
+impl CurveKind {
+    /// The keys the curve is built from: `with_edge_keys` for functions, the dots as they are for
+    /// paths.
+    pub fn keys(self, dots: &[Dot]) -> Vec<Dot> {
+        match self {
+            CurveKind::Function => with_edge_keys(dots),
+            CurveKind::Path { .. } => dots.to_vec(),
+        }
+    }
+
+    /// Samples the curve through `keys`, as returned by `keys`. Paths are centripetal Catmull-Rom
+    /// splines whatever the algorithm, and straight lines without one.
+    pub fn sample_in(
+        self,
+        curve_mode: Option<CurveAlgorithm>,
+        keys: &[Dot],
+        spline_settings: SplineSettings,
+        window: SampleWindow,
+    ) -> Result<Vec<Point>, CurveError> {
+        match self {
+            CurveKind::Function => sample_curve_in(curve_mode, keys, spline_settings, window),
+            CurveKind::Path { closed } => path::sample_path(keys, closed, curve_mode.is_some(), window),
+        }
+    }
+}
+
+/// The curve through `keys` (as returned by `kind.keys`) as cubic Bézier segments, exactly, for
+/// the curves made of cubics: straight lines, Catmull-Rom, the Hermite splines, the natural cubic
+/// spline, Bézier keys and paths. `None` for B-splines and NURBS, whose spans aren't single cubics
+/// in general.
+pub fn cubic_segments(
+    kind: CurveKind,
+    curve_mode: Option<CurveAlgorithm>,
+    keys: &[Dot],
+) -> Result<Option<Vec<[Point; 4]>>, CurveError> {
+    let xs: Vec<f32> = keys.iter().map(|dot| dot.position.x).collect();
+    let ys: Vec<f32> = keys.iter().map(|dot| dot.position.y).collect();
+    check_points(&xs, &ys, 2)?;
+    let points: Vec<Point> = keys.iter().map(|dot| dot.position).collect();
+    let line = |a: Point, b: Point| [a, a + (b - a) * (1.0 / 3.0), a + (b - a) * (2.0 / 3.0), b];
+
+    if let CurveKind::Path { closed } = kind {
+        let segments = path::segment_points(&points, closed);
+        return Ok(Some(match curve_mode {
+            None => segments.into_iter().map(|[_, a, b, _]| line(a, b)).collect(),
+            Some(_) => segments
+                .into_iter()
+                .map(|hull| {
+                    cubic_through(|t| catmull_rom_centripetal_2d(t, hull, path::CENTRIPETAL_ALPHA))
+                })
+                .collect(),
+        }));
+    }
+    let Some(curve_mode) = curve_mode else {
+        return Ok(Some(points.windows(2).map(|pair| line(pair[0], pair[1])).collect()));
+    };
+    let last = keys.len() - 1;
+    let segments = match curve_mode {
+        CurveAlgorithm::CatmullRom => (0..last)
+            .map(|i| {
+                let [p0, p1, p2, p3] = [i.saturating_sub(1), i, i + 1, (i + 2).min(last)].map(|j| points[j]);
+                cubic_through(|t| Point {
+                    x: catmull_rom_centripetal(t, p0.x, p1.x, p2.x, p3.x, CATMULL_ROM_ALPHA),
+                    y: catmull_rom_centripetal(t, p0.y, p1.y, p2.y, p3.y, CATMULL_ROM_ALPHA),
+                })
+            })
+            .collect(),
+        CurveAlgorithm::MonotonicSpline
+        | CurveAlgorithm::Pchip
+        | CurveAlgorithm::Steffen
+        | CurveAlgorithm::Akima => {
+            let tangents = match curve_mode.hermite_tangents() {
+                Some(method) => method.tangents(&xs, &ys)?,
+                None => monotonic_tangents(&xs, &ys)?,
+            };
+            (0..last)
+                .map(|i| {
+                    let h = xs[i + 1] - xs[i];
+                    let (m0, m1) = (tangents[i] * h, tangents[i + 1] * h);
+                    [
+                        points[i],
+                        points[i] + Vector::new(h / 3.0, m0 / 3.0),
+                        points[i + 1] - Vector::new(h / 3.0, m1 / 3.0),
+                        points[i + 1],
+                    ]
+                })
+                .collect()
+        }
+        CurveAlgorithm::NaturalCubicSpline => {
+            let x_spline = compute_natural_cubic_spline(&xs)?;
+            let y_spline = compute_natural_cubic_spline(&ys)?;
+            x_spline
+                .iter()
+                .zip(&y_spline)
+                .map(|(x_cubic, y_cubic)| {
+                    let (x, y) = (cubic_hull(x_cubic), cubic_hull(y_cubic));
+                    std::array::from_fn(|j| Point::new(x[j], y[j]))
+                })
+                .collect()
+        }
+        CurveAlgorithm::Bezier => keys.windows(2).map(|pair| bezier_segment(&pair[0], &pair[1])).collect(),
+        CurveAlgorithm::BSpline | CurveAlgorithm::Nurbs => return Ok(None),
+    };
+    Ok(Some(segments))
+}
+
+// Bézier control points of a segment known to be a cubic in t, from its values at t = 0, 1/3, 2/3
+// and 1
+fn cubic_through(evaluate: impl Fn(f32) -> Point) -> [Point; 4] {
+    let [p0, a, b, p3] = [0.0, 1.0 / 3.0, 2.0 / 3.0, 1.0].map(evaluate);
+    // a = (8 p0 + 12 p1 + 6 p2 + p3) / 27 and b = (p0 + 6 p1 + 12 p2 + 8 p3) / 27
+    let u = Vector::new(27.0 * a.x - 8.0 * p0.x - p3.x, 27.0 * a.y - 8.0 * p0.y - p3.y);
+    let v = Vector::new(27.0 * b.x - p0.x - 8.0 * p3.x, 27.0 * b.y - p0.y - 8.0 * p3.y);
+    [
+        p0,
+        Point::new((2.0 * u.x - v.x) / 18.0, (2.0 * u.y - v.y) / 18.0),
+        Point::new((2.0 * v.x - u.x) / 18.0, (2.0 * v.y - u.y) / 18.0),
+        p3,
+    ]
+}
+
+// Everything a curve's samples depend on
+type SampleInputs = (Vec<Dot>, CurveKind, Option<CurveAlgorithm>, SplineSettings, Option<ValueBounds>, SampleWindow);
+
+/// Keys and samples of one curve, kept until its dots, algorithm or settings change so that
+/// redraws and unrelated edits don't re-sort and resample it.
+#[derive(Debug)]
+pub struct SampleCache {
+    inputs: Option<SampleInputs>,
+    keys: Vec<Dot>,
+    samples: Result<Vec<Point>, CurveError>,
+}
+
+impl Default for SampleCache {
+    fn default() -> Self {
+        Self {
+            inputs: None,
+            keys: Vec::new(),
+            samples: Ok(Vec::new()),
+        }
+    }
+}
+
+impl SampleCache {
+    /// Resamples if any input differs from the last call. Returns whether it did. The samples are
+    /// clipped to `bounds` under `BoundsPolicy::Bounded`.
+    pub fn update(
+        &mut self,
+        dots: &[Dot],
+        kind: CurveKind,
+        curve_mode: Option<CurveAlgorithm>,
+        spline_settings: SplineSettings,
+        bounds: Option<ValueBounds>,
+        window: SampleWindow,
+    ) -> bool {
+        if let Some((last_dots, last_kind, last_mode, last_settings, last_bounds, last_window)) = &self.inputs {
+            if last_dots.as_slice() == dots
+                && *last_kind == kind
+                && *last_mode == curve_mode
+                && *last_settings == spline_settings
+                && *last_bounds == bounds
+                && *last_window == window
+            {
+                return false;
+            }
+        }
+        self.keys = kind.keys(dots);
+        self.samples = kind.sample_in(curve_mode, &self.keys, spline_settings, window);
+        if let (Ok(samples), Some(bounds)) = (&mut self.samples, bounds) {
+            bounds.apply(samples);
+        }
+        self.inputs = Some((dots.to_vec(), kind, curve_mode, spline_settings, bounds, window));
+        true
+    }
+
+    /// Kind of the curve last sampled.
+    pub fn kind(&self) -> CurveKind {
+        self.inputs.as_ref().map_or_else(CurveKind::default, |inputs| inputs.1)
+    }
+
+    /// The keys the curve was built from, see `CurveKind::keys`.
+    pub fn keys(&self) -> &[Dot] {
+        &self.keys
+    }
+
+    pub fn samples(&self) -> Result<&[Point], CurveError> {
+        self.samples.as_deref().map_err(|error| *error)
+    }
+}
+
+/// Control points of the Bézier segment between two neighbouring keys.
+pub fn bezier_segment(start: &Dot, end: &Dot) -> [Point; 4] {
+    let chord = end.position - start.position;
+    [
+        start.position,
+        start.position + start.out_handle.unwrap_or(chord * (1.0 / 3.0)),
+        end.position + end.in_handle.unwrap_or(chord * (-1.0 / 3.0)),
+        end.position,
+    ]
+}
+
+/// Keys whose handles reproduce a chain of Bézier segments, the inverse of `bezier_segment`.
+pub fn dots_from_beziers(segments: &[[Point; 4]]) -> Vec<Dot> {
+    let mut dots = Vec::with_capacity(segments.len() + 1);
+    for (i, [start, control_a, control_b, end]) in segments.iter().copied().enumerate() {
+        if i == 0 {
+            dots.push(Dot::new(start));
+        }
+        if let Some(previous) = dots.last_mut() {
+            previous.out_handle = Some(control_a - start);
+        }
+        dots.push(Dot {
+            in_handle: Some(control_b - end),
+            ..Dot::new(end)
+        });
+    }
+    dots
+}
+
+/// Heights of the function curve through `dots` at each of the `xs`, exact wherever the curve is
+/// made of cubic segments (see `FunctionCurve::new`).
+pub fn evaluate_at(
+    curve_mode: Option<CurveAlgorithm>,
+    dots: &[Dot],
+    spline_settings: SplineSettings,
+    xs: &[f32],
+) -> Result<Vec<f32>, CurveError> {
+    if xs.iter().any(|x| x.is_nan()) {
+        return Err(CurveError::NonFinite);
+    }
+    let curve = FunctionCurve::new(curve_mode, dots, spline_settings)?;
+    Ok(xs.iter().map(|&x| curve.evaluate(x)).collect())
+}
+
+/// Height of an x-sorted polyline at `x`, holding the end values outside its range.
+pub fn polyline_y_at(samples: &[Point], x: f32) -> Option<f32> {
+    let first = samples.first()?;
+    let last = samples.last()?;
+    if x.is_nan() {
+        return None;
+    }
+    if x <= first.x {
+        return Some(first.y);
+    }
+    if x >= last.x {
+        return Some(last.y);
+    }
+    // Curves that double back in x aren't sorted, so keep the search inside the polyline
+    let i = samples.partition_point(|point| point.x <= x).clamp(1, samples.len() - 1);
+    let (a, b) = (samples[i - 1], samples[i]);
+    let t = if b.x > a.x { (x - a.x) / (b.x - a.x) } else { 0.0 };
+    Some(a.y + (b.y - a.y) * t)
+}
+
+fn safe_powf_distance(a: f32, b: f32, alpha: f32) -> f32 {
+    let dist = (a - b).abs();
+    if dist < 1e-9 {
+        // fallback to small epsilon
+        1e-9_f32.powf(alpha)
+    } else {
+        dist.powf(alpha)
+    }
+}
+
+pub fn catmull_rom_centripetal(t: f32, p0: f32, p1: f32, p2: f32, p3: f32, alpha: f32) -> f32 {
+    let d01 = safe_powf_distance(p0, p1, alpha);
+    let d12 = safe_powf_distance(p1, p2, alpha);
+    let d23 = safe_powf_distance(p2, p3, alpha);
+    catmull_rom_with_knots(t, [p0, p1, p2, p3], [d01, d12, d23])
+}
+
+/// Centripetal Catmull-Rom between `p1` and `p2` with knots spaced by the distances between the
+/// points, rather than along each axis on its own as `catmull_rom_centripetal` does for function
+/// curves. This is the form that doesn't cusp or self-intersect within a segment.
+pub fn catmull_rom_centripetal_2d(t: f32, [p0, p1, p2, p3]: [Point; 4], alpha: f32) -> Point {
+    let distance = |a: Point, b: Point| {
+        let distance = a.distance(b);
+        if distance < 1e-9 {
+            1e-9_f32.powf(alpha)
+        } else {
+            distance.powf(alpha)
+        }
+    };
+    let intervals = [distance(p0, p1), distance(p1, p2), distance(p2, p3)];
+    Point::new(
+        catmull_rom_with_knots(t, [p0.x, p1.x, p2.x, p3.x], intervals),
+        catmull_rom_with_knots(t, [p0.y, p1.y, p2.y, p3.y], intervals),
+    )
+}
+
+// Barry-Goldman evaluation of the segment from p1 to p2, with knot intervals [d01, d12, d23]
+fn catmull_rom_with_knots(t: f32, [p0, p1, p2, p3]: [f32; 4], [d01, d12, d23]: [f32; 3]) -> f32 {
+    let t0 = 0.0;
+    let t1 = t0 + d01;
+    let t2 = t1 + d12;
+    let t3 = t2 + d23;
+
+    // We only interpolate between p1 and p2, so we re-map t from [0..1] to [t1..t2].
+    let t = t1 + (t * (t2 - t1));
+
+    // If t1==t0, t2==t1, or t3==t2 (which can happen if d01/d12/d23 = 0), we can early-return:
+    if (t1 - t0).abs() < 1e-12 || (t2 - t1).abs() < 1e-12 || (t3 - t2).abs() < 1e-12 {
+        // fallback: linear interpolation or just pick p1
+        return p1;
+    }
+
+    // ... compute A1, A2, A3
+    let a1 = (t1 - t) / (t1 - t0) * p0 + (t - t0) / (t1 - t0) * p1;
+    let a2 = (t2 - t) / (t2 - t1) * p1 + (t - t1) / (t2 - t1) * p2;
+    let a3 = (t3 - t) / (t3 - t2) * p2 + (t - t2) / (t3 - t2) * p3;
+
+    // ... then B1, B2, final
+    let b1 = (t2 - t) / (t2 - t0) * a1 + (t - t0) / (t2 - t0) * a2;
+    let b2 = (t3 - t) / (t3 - t1) * a2 + (t - t1) / (t3 - t1) * a3;
+
+    (t2 - t) / (t2 - t1) * b1 + (t - t1) / (t2 - t1) * b2
+}
+
+pub fn monotonic_cubic_spline(xs: &[f32], ys: &[f32]) -> Result<Vec<(f32, f32)>, CurveError> {
+    let tangents = monotonic_tangents(xs, ys)?;
+    hermite::sample_hermite(xs, ys, &tangents, 100) // Number of points per segment for rendering
+}
+
+/// Tangents (dy/dx) of the monotonic spline, averaged secants limited as in Fritsch & Carlson.
+pub fn monotonic_tangents(xs: &[f32], ys: &[f32]) -> Result<Vec<f32>, CurveError> {
+    check_points(xs, ys, 2)?;
+    check_increasing(xs)?;
+
+    let n = xs.len();
+    let mut slopes = vec![0.0; n - 1];
+    let mut tangents = vec![0.0; n];
+
+    // Step 1: Compute slopes between points
+    for i in 0..n - 1 {
+        slopes[i] = (ys[i + 1] - ys[i]) / (xs[i + 1] - xs[i]);
+    }
+
+    // Step 2: Compute tangents
+    tangents[0] = slopes[0];
+    tangents[n - 1] = slopes[n - 2];
+    for i in 1..n - 1 {
+        tangents[i] = (slopes[i - 1] + slopes[i]) / 2.0;
+    }
+
+    // Step 3: Adjust tangents to ensure monotonicity
+    for i in 0..n - 1 {
+        if slopes[i] == 0.0 {
+            tangents[i] = 0.0;
+            tangents[i + 1] = 0.0;
+        } else {
+            let alpha = tangents[i] / slopes[i];
+            let beta = tangents[i + 1] / slopes[i];
+            let s = alpha * alpha + beta * beta;
+            if s > 9.0 {
+                let tau = 3.0 / s.sqrt();
+                tangents[i] = tau * alpha * slopes[i];
+                tangents[i + 1] = tau * beta * slopes[i];
+            }
+        }
+    }
+
+    Ok(tangents)
+}
+
+pub fn compute_natural_cubic_spline(points: &[f32]) -> Result<Vec<[f32; 4]>, CurveError> {
+    check_points(points, points, 2)?;
+    let n = points.len() - 1;
+    let a = points.to_vec();
+    let mut b = vec![0.0; n];
+    let mut d = vec![0.0; n];
+    let h = vec![1.0; n]; // Assuming equal spacing between points
+    let mut alpha = vec![0.0; n];
+
+    for i in 1..n {
+        alpha[i] = (3.0 / h[i]) * (a[i + 1] - a[i]) - (3.0 / h[i - 1]) * (a[i] - a[i - 1]);
+    }
+
+    let mut c = vec![0.0; points.len()];
+    let mut l = vec![1.0; points.len()];
+    let mut mu = vec![0.0; points.len()];
+    let mut z = vec![0.0; points.len()];
+
+    for i in 1..n {
+        l[i] = 2.0 * (h[i - 1] + h[i]) - h[i - 1] * mu[i - 1];
+        if l[i] == 0.0 || !l[i].is_finite() {
+            return Err(CurveError::SingularSystem);
+        }
+        mu[i] = h[i] / l[i];
+        z[i] = (alpha[i] - h[i - 1] * z[i - 1]) / l[i];
+    }
+
+    for i in (0..n).rev() {
+        c[i] = z[i] - mu[i] * c[i + 1];
+        b[i] = (a[i + 1] - a[i]) / h[i] - h[i] * (c[i + 1] + 2.0 * c[i]) / 3.0;
+        d[i] = (c[i + 1] - c[i]) / (3.0 * h[i]);
+    }
+
+    let mut coefficients = Vec::new();
+    for i in 0..n {
+        coefficients.push([a[i], b[i], c[i], d[i]]);
+    }
+
+    if coefficients.iter().flatten().any(|value| !value.is_finite()) {
+        return Err(CurveError::SingularSystem);
+    }
+    Ok(coefficients)
+}
+// Bernstein coefficients of a + bt + ct² + dt³, the cubic's values stay between them on [0, 1]
+fn cubic_hull(&[a, b, c, d]: &[f32; 4]) -> [f32; 4] {
+    [a, a + b / 3.0, a + (2.0 * b + c) / 3.0, a + b + c + d]
+}
+
+pub fn evaluate_cubic(coefficients: &[f32; 4], t: f32) -> f32 {
+    coefficients[0] + coefficients[1] * t + coefficients[2] * t * t + coefficients[3] * t * t * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fit, simplify};
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    const MODES: [Option<CurveAlgorithm>; 10] = [
+        None,
+        Some(CurveAlgorithm::CatmullRom),
+        Some(CurveAlgorithm::MonotonicSpline),
+        Some(CurveAlgorithm::NaturalCubicSpline),
+        Some(CurveAlgorithm::Pchip),
+        Some(CurveAlgorithm::Steffen),
+        Some(CurveAlgorithm::Akima),
+        Some(CurveAlgorithm::Bezier),
+        Some(CurveAlgorithm::BSpline),
+        Some(CurveAlgorithm::Nurbs),
+    ];
+
+    // Mostly ordinary values, with every bit pattern (NaN, infinities, subnormals) mixed in
+    fn value() -> impl Strategy<Value = f32> {
+        prop_oneof![
+            4 => -0.5f32..1.5,
+            1 => any::<f32>(),
+            1 => Just(0.0),
+            1 => Just(1.0),
+        ]
+    }
+
+    fn dot() -> impl Strategy<Value = Dot> {
+        (
+            value(),
+            value(),
+            value(),
+            proptest::option::of((value(), value())),
+            proptest::option::of((value(), value())),
+        )
+            .prop_map(|(x, y, weight, in_handle, out_handle)| Dot {
+                position: Point::new(x, y),
+                weight,
+                in_handle: in_handle.map(|(x, y)| Vector::new(x, y)),
+                out_handle: out_handle.map(|(x, y)| Vector::new(x, y)),
+                color: None,
+            })
+    }
+
+    fn settings() -> impl Strategy<Value = SplineSettings> {
+        (0usize..8, any::<bool>()).prop_map(|(degree, clamped)| SplineSettings {
+            degree,
+            knots: if clamped { KnotVector::Clamped } else { KnotVector::Uniform },
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn sampling_never_panics(dots in vec(dot(), 0..10), settings in settings()) {
+            let keys = with_edge_keys(&dots);
+            for mode in MODES {
+                if let Ok(samples) = sample_curve(mode, &keys, settings) {
+                    prop_assert!(samples.iter().all(|p| p.x.is_finite() && p.y.is_finite()));
+                }
+                let _ = evaluate_at(mode, &dots, settings, &[-1.0, 0.0, 0.5, 1.0, f32::NAN]);
+                let _ = simplify::simplify(&dots, CurveKind::Function, mode, settings, 0.01);
+            }
+            // Paths only tell straight lines from smooth ones
+            for mode in [None, Some(CurveAlgorithm::CatmullRom)] {
+                for closed in [false, true] {
+                    let _ = simplify::simplify(&dots, CurveKind::Path { closed }, mode, settings, 0.01);
+                }
+            }
+        }
+
+        #[test]
+        fn builders_never_panic(xs in vec(value(), 0..10), ys in vec(value(), 0..10)) {
+            let _ = monotonic_cubic_spline(&xs, &ys);
+            let _ = compute_natural_cubic_spline(&xs);
+            for method in [HermiteTangents::Pchip, HermiteTangents::Steffen, HermiteTangents::Akima] {
+                if let Ok(tangents) = method.tangents(&xs, &ys) {
+                    let _ = hermite::sample_hermite(&xs, &ys, &tangents, 4);
+                }
+            }
+            let points: Vec<Point> = xs.iter().zip(&ys).map(|(&x, &y)| Point::new(x, y)).collect();
+            let _ = bspline::sample_nurbs(&points, &ys, 3, KnotVector::Clamped, 16);
+            let _ = fit::fit_cubic_beziers(&points, 0.01);
+        }
+
+        #[test]
+        fn distinct_keys_always_build(ys in vec(0.0f32..1.0, 1..10), settings in settings()) {
+            // Evenly spread keys inside the unit square are valid input for every algorithm
+            let dots: Vec<Dot> = ys
+                .iter()
+                .enumerate()
+                .map(|(i, &y)| Dot::new(Point::new((i + 1) as f32 / (ys.len() + 1) as f32, y)))
+                .collect();
+            let keys = with_edge_keys(&dots);
+            for mode in MODES {
+                prop_assert!(sample_curve(mode, &keys, settings).is_ok(), "{mode:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_inputs_are_errors() {
+        assert_eq!(
+            compute_natural_cubic_spline(&[]),
+            Err(CurveError::TooFewPoints { needed: 2, found: 0 })
+        );
+        assert_eq!(
+            monotonic_cubic_spline(&[0.0, 0.5, 0.5], &[0.0, 1.0, 0.0]),
+            Err(CurveError::DuplicateX(0.5))
+        );
+        assert_eq!(
+            HermiteTangents::Pchip.tangents(&[0.0, f32::NAN], &[0.0, 1.0]),
+            Err(CurveError::NonFinite)
+        );
+        assert_eq!(
+            sample_curve(Some(CurveAlgorithm::Pchip), &[Dot::new(Point::ORIGIN)], SplineSettings::default()),
+            Err(CurveError::TooFewPoints { needed: 2, found: 1 })
+        );
+        assert_eq!(
+            evaluate_at(None, &[], SplineSettings::default(), &[0.5]),
+            Err(CurveError::TooFewPoints { needed: 2, found: 0 })
+        );
+        assert_eq!(
+            evaluate_at(None, &[Dot::new(Point::ORIGIN)], SplineSettings::default(), &[f32::NAN]),
+            Err(CurveError::NonFinite)
+        );
+    }
+
+    #[test]
+    fn evaluation_is_exact_between_samples() {
+        let points = [(0.0, 0.0), (0.4, 0.9), (1.0, 0.2)];
+        let dots: Vec<Dot> = points.iter().map(|&(x, y)| Dot::new(Point::new(x, y))).collect();
+        let (xs, ys): (Vec<f32>, Vec<f32>) = points.iter().copied().unzip();
+        let tangents = HermiteTangents::Pchip.tangents(&xs, &ys).unwrap();
+        let at: Vec<f32> = (0..=97).map(|i| i as f32 / 97.0).collect();
+        let values = evaluate_at(Some(CurveAlgorithm::Pchip), &dots, SplineSettings::default(), &at).unwrap();
+        for (x, y) in at.into_iter().zip(values) {
+            let i = usize::from(x > 0.4);
+            let h = xs[i + 1] - xs[i];
+            let t = (x - xs[i]) / h;
+            let expected = hermite::hermite_segment(ys[i], ys[i + 1], tangents[i] * h, tangents[i + 1] * h, t);
+            assert!((y - expected).abs() < 1e-5, "{y} != {expected} at x = {x}");
+        }
+    }
+
+    #[test]
+    fn keys_dragged_against_a_neighbour_are_kept() {
+        for i in 0..200 {
+            let x = 0.01 + i as f32 * 0.0049;
+            let mut dots = vec![
+                Dot::new(Point::new(x, 0.2)),
+                Dot::new(Point::new(x + 0.3, 0.5)),
+                Dot::new(Point::new(x + 0.6, 0.8)),
+            ];
+            // As far as it goes towards either neighbour
+            for target in [-1.0, 2.0] {
+                let (low, high) = key_x_range(&dots, 1);
+                dots[1].position.x = f32::clamp(target, low, high);
+                assert_eq!(make_function_curve(&mut dots), [Some(0), Some(1), Some(2)], "x = {x}");
+            }
+        }
+    }
+
+    #[test]
+    fn clamping_around_a_dragged_key_matches_a_full_pass() {
+        let mut dots: Vec<Dot> = (0..6)
+            .map(|i| Dot {
+                in_handle: Some(Vector::new(-0.1, 0.05)),
+                out_handle: Some(Vector::new(0.1, -0.05)),
+                ..Dot::new(Point::new(0.1 + i as f32 * 0.15, 0.5))
+            })
+            .collect();
+        make_function_curve(&mut dots);
+        let (_, high) = key_x_range(&dots, 3);
+        dots[3].position.x = high;
+
+        let mut full = dots.clone();
+        make_function_curve(&mut full);
+        clamp_handles(&mut dots, 2..5);
+        assert_eq!(dots, full);
+    }
+
+    #[test]
+    fn making_a_function_curve_reports_where_keys_went() {
+        let mut dots = vec![
+            Dot::new(Point::new(0.5, 0.0)),
+            Dot::new(Point::new(f32::NAN, 0.0)),
+            Dot::new(Point::new(0.1, 0.0)),
+            Dot::new(Point::new(0.5005, 0.0)),
+            Dot::new(Point::new(0.9, 0.0)),
+        ];
+        assert_eq!(make_function_curve(&mut dots), [Some(1), None, Some(0), None, Some(2)]);
+        let xs: Vec<f32> = dots.iter().map(|dot| dot.position.x).collect();
+        assert_eq!(xs, [0.1, 0.5, 0.9]);
+    }
+}
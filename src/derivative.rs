@@ -0,0 +1,187 @@
+// Slope, second derivative and curvature, for judging how smooth a curve is. Curves made of cubic
+// segments (see `curve::cubic_segments`) are differentiated in closed form, others from their samples
+use iced::{Point, Vector};
+
+use crate::fit::{first_derivative, second_derivative};
+
+/// Second derivatives on either side of a join that differ by more than this, relative to the
+/// larger of them (or absolutely below 1), count as a discontinuity.
+pub const JUMP_TOLERANCE: f32 = 1e-3;
+
+/// dy/dx of a cubic Bézier segment at `t`, infinite where it runs vertically.
+pub fn slope(segment: &[Point; 4], t: f32) -> f32 {
+    let velocity = first_derivative(segment, t);
+    velocity.y / velocity.x
+}
+
+/// d²y/dx² of a cubic Bézier segment at `t`.
+pub fn second_derivative_at(segment: &[Point; 4], t: f32) -> f32 {
+    let velocity = first_derivative(segment, t);
+    let acceleration = second_derivative(segment, t);
+    (acceleration.y * velocity.x - acceleration.x * velocity.y) / velocity.x.powi(3)
+}
+
+/// Signed curvature of a cubic Bézier segment at `t`, positive where it turns counterclockwise in
+/// its own axes.
+pub fn curvature(segment: &[Point; 4], t: f32) -> f32 {
+    let velocity = first_derivative(segment, t);
+    let acceleration = second_derivative(segment, t);
+    let speed_squared = velocity.x * velocity.x + velocity.y * velocity.y;
+    (velocity.x * acceleration.y - velocity.y * acceleration.x) / (speed_squared * speed_squared.sqrt())
+}
+
+/// Points `(x, dy/dx)` at `per_segment` steps along every segment, leaving out vertical spots.
+pub fn slope_curve(segments: &[[Point; 4]], per_segment: usize) -> Vec<Point> {
+    along(segments, per_segment)
+        .map(|(segment, t)| Point::new(crate::fit::evaluate_bezier(segment, t).x, slope(segment, t)))
+        .filter(|point| point.y.is_finite())
+        .collect()
+}
+
+/// Like `slope_curve` for a curve only known by its samples, by central differences.
+pub fn sampled_slope_curve(samples: &[Point]) -> Vec<Point> {
+    (0..samples.len())
+        .filter_map(|i| {
+            let (before, after) = (samples[i.saturating_sub(1)], samples[(i + 1).min(samples.len() - 1)]);
+            let slope = (after.y - before.y) / (after.x - before.x);
+            slope.is_finite().then_some(Point::new(samples[i].x, slope))
+        })
+        .collect()
+}
+
+/// A curvature comb: points at `per_segment` steps along every segment, each with a tooth along
+/// the normal, away from the center of curvature and as long as the curvature.
+pub fn comb(segments: &[[Point; 4]], per_segment: usize) -> Vec<(Point, Vector)> {
+    along(segments, per_segment)
+        .filter_map(|(segment, t)| {
+            let normal = unit_normal(first_derivative(segment, t))?;
+            let curvature = curvature(segment, t);
+            curvature
+                .is_finite()
+                .then(|| (crate::fit::evaluate_bezier(segment, t), normal * -curvature))
+        })
+        .collect()
+}
+
+/// Like `comb` for a curve only known by its samples, from the circle through each sample and its
+/// neighbours.
+pub fn sampled_comb(samples: &[Point]) -> Vec<(Point, Vector)> {
+    samples
+        .windows(3)
+        .filter_map(|triple| {
+            let [a, b, c] = [triple[0], triple[1], triple[2]];
+            let (ab, bc, ca) = (b - a, c - b, a - c);
+            let cross = ab.x * bc.y - ab.y * bc.x;
+            let lengths = length(ab) * length(bc) * length(ca);
+            let curvature = 2.0 * cross / lengths;
+            let normal = unit_normal(c - a)?;
+            curvature.is_finite().then_some((b, normal * -curvature))
+        })
+        .collect()
+}
+
+/// Joins between segments where `value` (such as `second_derivative_at`, or `curvature` for paths)
+/// jumps by more than `JUMP_TOLERANCE`. A closed path also joins its last segment to its first.
+pub fn discontinuities(segments: &[[Point; 4]], closed: bool, value: impl Fn(&[Point; 4], f32) -> f32) -> Vec<Point> {
+    let joins = if closed && segments.len() > 1 {
+        segments.len()
+    } else {
+        segments.len().saturating_sub(1)
+    };
+    (0..joins)
+        .filter_map(|i| {
+            let (before, after) = (&segments[i], &segments[(i + 1) % segments.len()]);
+            let (left, right) = (value(before, 1.0), value(after, 0.0));
+            let jump = match (left.is_finite(), right.is_finite()) {
+                (true, true) => (left - right).abs() > JUMP_TOLERANCE * left.abs().max(right.abs()).max(1.0),
+                (false, false) => false,
+                _ => true,
+            };
+            jump.then_some(before[3])
+        })
+        .collect()
+}
+
+// Every segment with the parameters of `per_segment` steps along it, both ends included
+fn along(segments: &[[Point; 4]], per_segment: usize) -> impl Iterator<Item = (&[Point; 4], f32)> {
+    let steps = per_segment.max(1);
+    segments
+        .iter()
+        .flat_map(move |segment| (0..=steps).map(move |j| (segment, j as f32 / steps as f32)))
+}
+
+fn length(v: Vector) -> f32 {
+    (v.x * v.x + v.y * v.y).sqrt()
+}
+
+// The direction a quarter turn counterclockwise from `tangent`, if it has one
+fn unit_normal(tangent: Vector) -> Option<Vector> {
+    let length = length(tangent);
+    (length > 0.0 && length.is_finite()).then(|| Vector::new(-tangent.y / length, tangent.x / length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{self, CurveAlgorithm, CurveKind, Dot};
+
+    // y = x² on [0, 1]
+    const PARABOLA: [Point; 4] = [
+        Point::new(0.0, 0.0),
+        Point::new(1.0 / 3.0, 0.0),
+        Point::new(2.0 / 3.0, 1.0 / 3.0),
+        Point::new(1.0, 1.0),
+    ];
+
+    #[test]
+    fn differentiates_a_parabola() {
+        assert!((slope(&PARABOLA, 0.5) - 1.0).abs() < 1e-5);
+        assert!((second_derivative_at(&PARABOLA, 0.25) - 2.0).abs() < 1e-4);
+        assert!((curvature(&PARABOLA, 0.0) - 2.0).abs() < 1e-4);
+        let line = [
+            Point::new(1.0, 1.0),
+            Point::new(4.0 / 3.0, 5.0 / 3.0),
+            Point::new(5.0 / 3.0, 7.0 / 3.0),
+            Point::new(2.0, 3.0),
+        ];
+        // The line continues the parabola's slope but not its second derivative
+        assert_eq!(
+            discontinuities(&[PARABOLA, line], false, second_derivative_at),
+            [Point::new(1.0, 1.0)]
+        );
+    }
+
+    #[test]
+    fn natural_splines_have_no_jumps() {
+        let keys: Vec<Dot> = [(0.0, 0.1), (0.3, 0.8), (0.5, 0.4), (0.8, 0.9), (1.0, 0.2)]
+            .map(|(x, y)| Dot::new(Point::new(x, y)))
+            .to_vec();
+        let mode = Some(CurveAlgorithm::NaturalCubicSpline);
+        let segments = curve::cubic_segments(CurveKind::Function, mode, &keys)
+            .unwrap()
+            .unwrap();
+        assert!(discontinuities(&segments, false, second_derivative_at).is_empty());
+        // Akima only matches slopes, so its second derivative jumps at the keys
+        let segments = curve::cubic_segments(CurveKind::Function, Some(CurveAlgorithm::Akima), &keys)
+            .unwrap()
+            .unwrap();
+        assert!(!discontinuities(&segments, false, second_derivative_at).is_empty());
+    }
+
+    #[test]
+    fn catmull_rom_segments_are_exact() {
+        let keys: Vec<Dot> = [(0.0, 0.1), (0.3, 0.8), (0.5, 0.4), (1.0, 0.2)]
+            .map(|(x, y)| Dot::new(Point::new(x, y)))
+            .to_vec();
+        let mode = Some(CurveAlgorithm::CatmullRom);
+        let segments = curve::cubic_segments(CurveKind::Function, mode, &keys)
+            .unwrap()
+            .unwrap();
+        let samples = curve::sample_curve(mode, &keys, Default::default()).unwrap();
+        // Halfway along the second segment, whose samples follow the first segment's 51 without
+        // repeating the key they share
+        let sample = samples[51 + 24];
+        let point = crate::fit::evaluate_bezier(&segments[1], 0.5);
+        assert!(point.distance(sample) < 1e-4, "{point:?} != {sample:?}");
+    }
+}
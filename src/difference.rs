@@ -0,0 +1,76 @@
+// How far a curve has moved from a reference copy of it, for seeing what an edit changed
+use crate::curve::{CurveError, CurveKind};
+use crate::document::CurveDocument;
+
+/// Evenly spaced x positions the curves are compared at, ends included.
+pub const STEPS: usize = 1001;
+
+/// Largest and root mean square differences in y over the unit domain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurveDifference {
+    pub max: f32,
+    pub max_at: f32, // x where the difference is largest
+    pub rms: f32,
+}
+
+/// Compares two function curves as `current - reference` at `STEPS` positions. Paths have no
+/// single y per x, so comparing them gives `None`.
+pub fn difference(reference: &CurveDocument, current: &CurveDocument) -> Result<Option<CurveDifference>, CurveError> {
+    if reference.kind != CurveKind::Function || current.kind != CurveKind::Function {
+        return Ok(None);
+    }
+    if reference.dots.is_empty() || current.dots.is_empty() {
+        return Ok(None);
+    }
+    let xs: Vec<f32> = (0..STEPS).map(|i| i as f32 / (STEPS - 1) as f32).collect();
+    let before = reference.evaluate_at(&xs)?;
+    let after = current.evaluate_at(&xs)?;
+
+    let mut result = CurveDifference {
+        max: 0.0,
+        max_at: 0.0,
+        rms: 0.0,
+    };
+    let mut squares = 0.0f64;
+    for ((&x, before), after) in xs.iter().zip(before).zip(after) {
+        let difference = (after - before).abs();
+        if difference > result.max {
+            result.max = difference;
+            result.max_at = x;
+        }
+        squares += f64::from(difference) * f64::from(difference);
+    }
+    result.rms = (squares / STEPS as f64).sqrt() as f32;
+    Ok(Some(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::Dot;
+    use iced::Point;
+
+    fn line(start: f32, end: f32) -> CurveDocument {
+        CurveDocument {
+            dots: vec![Dot::new(Point::new(0.0, start)), Dot::new(Point::new(1.0, end))],
+            ..CurveDocument::default()
+        }
+    }
+
+    #[test]
+    fn measures_how_far_the_curve_moved() {
+        let same = difference(&line(0.0, 1.0), &line(0.0, 1.0)).unwrap().unwrap();
+        assert_eq!((same.max, same.rms), (0.0, 0.0));
+
+        // Lifting one end grows the difference linearly, so the RMS is max / sqrt(3)
+        let moved = difference(&line(0.0, 1.0), &line(0.0, 0.4)).unwrap().unwrap();
+        assert!((moved.max - 0.6).abs() < 1e-5 && moved.max_at == 1.0);
+        assert!((moved.rms - 0.6 / 3f32.sqrt()).abs() < 1e-3);
+
+        let path = CurveDocument {
+            kind: CurveKind::Path { closed: false },
+            ..line(0.0, 1.0)
+        };
+        assert_eq!(difference(&path, &line(0.0, 1.0)).unwrap(), None);
+    }
+}
@@ -0,0 +1,286 @@
+// Saving and loading a curve as a plain text document, one setting or key per line:
+//
+//     curve pchip
+//     degree 3
+//     knots uniform
+//     key 0.1 0.2
+//     key 0.5 0.8 weight 2 in -0.1 0 out 0.1 0 color #ff8000
+//
+// `curve none` is the straight-line connector. Free-form paths add a `path open` or `path closed`
+// line and keep their keys in path order. Bounded curves add `bounds 0 1 clamp-keys`, with the
+// policy `reject`, `clamp-keys` or `bounded`. Blank lines and `#` comments are skipped.
+use std::fmt;
+use std::str::FromStr;
+
+use iced::{Point, Vector};
+
+use crate::bounds::{BoundsPolicy, ValueBounds};
+use crate::bspline::KnotVector;
+use crate::curve::{self, CurveAlgorithm, CurveError, CurveKind, Dot, SampleWindow, SplineSettings};
+use crate::export::{self, ExportSettings};
+use crate::gradient;
+use crate::query::FunctionCurve;
+
+pub const EXTENSION: &str = "curve";
+
+/// Keys and the settings needed to evaluate them the way the editor does.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CurveDocument {
+    pub dots: Vec<Dot>,
+    pub kind: CurveKind,
+    pub curve_mode: Option<CurveAlgorithm>,
+    pub spline_settings: SplineSettings,
+    pub bounds: Option<ValueBounds>,
+}
+
+#[derive(Debug)]
+pub enum DocumentError {
+    Io(std::io::Error),
+    InvalidLine(usize), // 1-based number of a line that isn't a setting or a key
+    UnknownAlgorithm(String),
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocumentError::Io(error) => write!(f, "could not read file: {error}"),
+            DocumentError::InvalidLine(line) => write!(f, "line {line} is not a setting or a key"),
+            DocumentError::UnknownAlgorithm(name) => {
+                let names: Vec<&str> = CurveAlgorithm::ALL.iter().map(|algorithm| algorithm.name()).collect();
+                write!(
+                    f,
+                    "unknown curve \"{name}\", expected none or one of {}",
+                    names.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for DocumentError {
+    fn from(error: std::io::Error) -> Self {
+        DocumentError::Io(error)
+    }
+}
+
+impl CurveDocument {
+    pub fn read(path: &str) -> Result<Self, DocumentError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    /// Checks that the curve can be built, as the editor does before drawing it.
+    pub fn validate(&self) -> Result<(), CurveError> {
+        self.sample().map(|_| ())
+    }
+
+    pub fn sample(&self) -> Result<Vec<Point>, CurveError> {
+        let mut samples = self.kind.sample_in(
+            self.curve_mode,
+            &self.kind.keys(&self.dots),
+            self.spline_settings,
+            SampleWindow::FULL,
+        )?;
+        self.clip(&mut samples);
+        Ok(samples)
+    }
+
+    /// Evenly spaced samples of a function curve for the data export formats.
+    pub fn export_samples(&self, settings: ExportSettings) -> Result<Vec<Point>, CurveError> {
+        let mut samples = export::sample(&self.dots, self.curve_mode, self.spline_settings, settings)?;
+        self.clip(&mut samples);
+        Ok(samples)
+    }
+
+    fn clip(&self, samples: &mut [Point]) {
+        if let Some(bounds) = self.bounds {
+            bounds.apply(samples);
+        }
+    }
+
+    /// The curve for integrals, inverses, extremes and roots, clipped like the samples under
+    /// `BoundsPolicy::Bounded`. Only meaningful for function curves.
+    pub fn function_curve(&self) -> Result<FunctionCurve, CurveError> {
+        let curve = FunctionCurve::new(self.curve_mode, &self.dots, self.spline_settings)?;
+        if let Some(bounds) = self.bounds.filter(|bounds| bounds.policy == BoundsPolicy::Bounded) {
+            return Ok(curve.clipped(bounds.min, bounds.max));
+        }
+        Ok(curve)
+    }
+
+    /// y at each x, for function curves.
+    pub fn evaluate_at(&self, xs: &[f32]) -> Result<Vec<f32>, CurveError> {
+        let mut ys = curve::evaluate_at(self.curve_mode, &self.dots, self.spline_settings, xs)?;
+        if let Some(bounds) = self.bounds.filter(|bounds| bounds.policy == BoundsPolicy::Bounded) {
+            ys.iter_mut().for_each(|y| *y = bounds.clamp(*y));
+        }
+        Ok(ys)
+    }
+}
+
+impl fmt::Display for CurveDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# iced curve editor document")?;
+        writeln!(f, "curve {}", self.curve_mode.map_or("none", CurveAlgorithm::name))?;
+        writeln!(f, "degree {}", self.spline_settings.degree)?;
+        let knots = match self.spline_settings.knots {
+            KnotVector::Uniform => "uniform",
+            KnotVector::Clamped => "clamped",
+        };
+        writeln!(f, "knots {knots}")?;
+        if let CurveKind::Path { closed } = self.kind {
+            writeln!(f, "path {}", if closed { "closed" } else { "open" })?;
+        }
+        if let Some(bounds) = self.bounds {
+            writeln!(f, "bounds {} {} {}", bounds.min, bounds.max, bounds.policy.name())?;
+        }
+        // Floats are written in their shortest form that reads back to the same value
+        for dot in &self.dots {
+            write!(f, "key {} {}", dot.position.x, dot.position.y)?;
+            if dot.weight != 1.0 {
+                write!(f, " weight {}", dot.weight)?;
+            }
+            if let Some(handle) = dot.in_handle {
+                write!(f, " in {} {}", handle.x, handle.y)?;
+            }
+            if let Some(handle) = dot.out_handle {
+                write!(f, " out {} {}", handle.x, handle.y)?;
+            }
+            if let Some(color) = dot.color {
+                write!(f, " color {}", gradient::to_hex(color))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for CurveDocument {
+    type Err = DocumentError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut document = CurveDocument::default();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || DocumentError::InvalidLine(index + 1);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["curve", "none"] => document.curve_mode = None,
+                ["curve", name] => {
+                    document.curve_mode =
+                        Some(CurveAlgorithm::from_name(name).ok_or(DocumentError::UnknownAlgorithm(name.to_string()))?)
+                }
+                ["degree", degree] => {
+                    document.spline_settings.degree =
+                        degree.parse().ok().filter(|&degree| degree >= 1).ok_or_else(invalid)?
+                }
+                ["knots", "uniform"] => document.spline_settings.knots = KnotVector::Uniform,
+                ["knots", "clamped"] => document.spline_settings.knots = KnotVector::Clamped,
+                ["path", "open"] => document.kind = CurveKind::Path { closed: false },
+                ["path", "closed"] => document.kind = CurveKind::Path { closed: true },
+                ["bounds", min, max, policy] => {
+                    let number = |field: &str| field.parse::<f32>().ok().filter(|value| value.is_finite());
+                    let (min, max) = number(min)
+                        .zip(number(max))
+                        .filter(|(min, max)| min <= max)
+                        .ok_or_else(invalid)?;
+                    let policy = BoundsPolicy::from_name(policy).ok_or_else(invalid)?;
+                    document.bounds = Some(ValueBounds { min, max, policy });
+                }
+                ["key", rest @ ..] => document.dots.push(parse_key(rest).ok_or_else(invalid)?),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(document)
+    }
+}
+
+// `x y` followed by optional `weight w`, `in dx dy`, `out dx dy` and `color #rrggbb`
+fn parse_key(fields: &[&str]) -> Option<Dot> {
+    let number = |field: &str| field.parse::<f32>().ok().filter(|value| value.is_finite());
+    let [x, y, rest @ ..] = fields else {
+        return None;
+    };
+    let mut dot = Dot::new(Point::new(number(x)?, number(y)?));
+    let mut rest = rest;
+    while !rest.is_empty() {
+        rest = match rest {
+            ["weight", weight, tail @ ..] => {
+                dot.weight = number(weight)?;
+                tail
+            }
+            ["in", dx, dy, tail @ ..] => {
+                dot.in_handle = Some(Vector::new(number(dx)?, number(dy)?));
+                tail
+            }
+            ["out", dx, dy, tail @ ..] => {
+                dot.out_handle = Some(Vector::new(number(dx)?, number(dy)?));
+                tail
+            }
+            ["color", hex, tail @ ..] => {
+                dot.color = Some(gradient::parse_hex(hex)?);
+                tail
+            }
+            _ => return None,
+        };
+    }
+    Some(dot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn dot() -> impl Strategy<Value = Dot> {
+        let handle = proptest::option::of((-1.0f32..1.0, -1.0f32..1.0).prop_map(|(x, y)| Vector::new(x, y)));
+        let color = proptest::option::of(any::<[u8; 3]>().prop_map(|[r, g, b]| iced::Color::from_rgb8(r, g, b)));
+        (0.0f32..1.0, 0.0f32..1.0, 0.1f32..10.0, handle.clone(), handle, color).prop_map(
+            |(x, y, weight, in_handle, out_handle, color)| Dot {
+                position: Point::new(x, y),
+                weight,
+                in_handle,
+                out_handle,
+                color,
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn round_trips(
+            dots in proptest::collection::vec(dot(), 0..10),
+            mode in proptest::option::of(proptest::sample::select(CurveAlgorithm::ALL.to_vec())),
+            degree in 1usize..6,
+            clamped in any::<bool>(),
+            kind in proptest::sample::select(vec![
+                CurveKind::Function,
+                CurveKind::Path { closed: false },
+                CurveKind::Path { closed: true },
+            ]),
+            bounds in proptest::option::of(
+                (-1.0f32..0.5, 0.5f32..2.0, proptest::sample::select(BoundsPolicy::ALL.to_vec()))
+                    .prop_map(|(min, max, policy)| ValueBounds { min, max, policy })
+            ),
+        ) {
+            let document = CurveDocument {
+                dots,
+                kind,
+                curve_mode: mode,
+                spline_settings: SplineSettings {
+                    degree,
+                    knots: if clamped { KnotVector::Clamped } else { KnotVector::Uniform },
+                },
+                bounds,
+            };
+            prop_assert_eq!(document.to_string().parse::<CurveDocument>().unwrap(), document);
+        }
+    }
+}
@@ -0,0 +1,569 @@
+// Easing presets and conversion to and from CSS timing functions
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+use iced::Point;
+
+use crate::curve::{self, CurveAlgorithm, Dot};
+use crate::fit;
+
+// Presets that aren't a single cubic are sampled this densely and refitted within this tolerance
+const PRESET_SAMPLES: usize = 256;
+const PRESET_TOLERANCE: f32 = 0.001;
+
+/// Most jumps `steps()` may have, each of which becomes two keys.
+pub const MAX_STEPS: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PennerFamily {
+    Sine,
+    Quad,
+    Cubic,
+    Quart,
+    Quint,
+    Expo,
+    Circ,
+    Back,
+    Elastic,
+    Bounce,
+}
+
+impl PennerFamily {
+    pub const ALL: [PennerFamily; 10] = [
+        PennerFamily::Sine,
+        PennerFamily::Quad,
+        PennerFamily::Cubic,
+        PennerFamily::Quart,
+        PennerFamily::Quint,
+        PennerFamily::Expo,
+        PennerFamily::Circ,
+        PennerFamily::Back,
+        PennerFamily::Elastic,
+        PennerFamily::Bounce,
+    ];
+
+    // Quadratic and cubic "in" and "out" are single cubics, so they can round-trip through CSS
+    fn as_css(self, direction: EaseDirection) -> Option<CssEasing> {
+        let third = 1.0 / 3.0;
+        let [x1, y1, x2, y2] = match self {
+            PennerFamily::Quad => [third, 0.0, 2.0 * third, third],
+            PennerFamily::Cubic => [third, 0.0, 2.0 * third, 0.0],
+            _ => return None,
+        };
+        match direction {
+            EaseDirection::In => Some(CssEasing::CubicBezier([x1, y1, x2, y2])),
+            EaseDirection::Out => Some(CssEasing::CubicBezier([1.0 - x2, 1.0 - y2, 1.0 - x1, 1.0 - y1])),
+            EaseDirection::InOut => None,
+        }
+    }
+
+    // The "in" form of each equation, the other directions are derived from it
+    fn ease_in(self, t: f32) -> f32 {
+        match self {
+            PennerFamily::Sine => 1.0 - (t * PI / 2.0).cos(),
+            PennerFamily::Quad => t.powi(2),
+            PennerFamily::Cubic => t.powi(3),
+            PennerFamily::Quart => t.powi(4),
+            PennerFamily::Quint => t.powi(5),
+            PennerFamily::Expo if t <= 0.0 => 0.0,
+            PennerFamily::Expo => 2f32.powf(10.0 * t - 10.0),
+            PennerFamily::Circ => 1.0 - (1.0 - t * t).max(0.0).sqrt(),
+            PennerFamily::Back => {
+                let overshoot = 1.70158;
+                (overshoot + 1.0) * t.powi(3) - overshoot * t.powi(2)
+            }
+            PennerFamily::Elastic if t <= 0.0 || t >= 1.0 => t.clamp(0.0, 1.0),
+            PennerFamily::Elastic => -(2f32.powf(10.0 * t - 10.0)) * ((10.0 * t - 10.75) * 2.0 * PI / 3.0).sin(),
+            PennerFamily::Bounce => 1.0 - bounce_out(1.0 - t),
+        }
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    let (n, d) = (7.5625, 2.75);
+    if t < 1.0 / d {
+        n * t * t
+    } else if t < 2.0 / d {
+        let t = t - 1.5 / d;
+        n * t * t + 0.75
+    } else if t < 2.5 / d {
+        let t = t - 2.25 / d;
+        n * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / d;
+        n * t * t + 0.984375
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EaseDirection {
+    In,
+    Out,
+    InOut,
+}
+
+/// Entries of the preset picker.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Preset {
+    Css(CssEasing),
+    Penner(PennerFamily, EaseDirection),
+    Spring,
+}
+
+impl Preset {
+    pub fn all() -> Vec<Preset> {
+        let mut presets: Vec<Preset> = ["linear", "ease", "ease-in", "ease-out", "ease-in-out", "steps(4)"]
+            .iter()
+            .filter_map(|css| css.parse().ok())
+            .map(Preset::Css)
+            .collect();
+        for family in PennerFamily::ALL {
+            for direction in [EaseDirection::In, EaseDirection::Out, EaseDirection::InOut] {
+                presets.push(Preset::Penner(family, direction));
+            }
+        }
+        presets.push(Preset::Spring);
+        presets
+    }
+
+    /// Bézier keys from x = 0 to x = 1 in curve space, starting at (0, 0) and ending at (1, 1)
+    /// unless the curve overshoots. Those are scaled down vertically to fit inside the unit square,
+    /// which lifts the first key above 0 when the curve dips below it and lowers the last one when
+    /// it rises past 1.
+    pub fn dots(self) -> Vec<Dot> {
+        let segments = match self {
+            Preset::Css(easing) => easing.segments(),
+            Preset::Penner(family, direction) => match family.as_css(direction) {
+                Some(easing) => easing.segments(),
+                None => fit_function(|t| match direction {
+                    EaseDirection::In => family.ease_in(t),
+                    EaseDirection::Out => 1.0 - family.ease_in(1.0 - t),
+                    EaseDirection::InOut if t < 0.5 => family.ease_in(2.0 * t) / 2.0,
+                    EaseDirection::InOut => 1.0 - family.ease_in(2.0 - 2.0 * t) / 2.0,
+                }),
+            },
+            Preset::Spring => fit_function(spring),
+        };
+
+        let (low, high) = segments
+            .iter()
+            .flatten()
+            .fold((0.0f32, 1.0f32), |(low, high), p| (low.min(p.y), high.max(p.y)));
+        let segments: Vec<[Point; 4]> = segments
+            .iter()
+            .map(|segment| segment.map(|p| Point::new(p.x, (p.y - low) / (high - low))))
+            .collect();
+        curve::dots_from_beziers(&segments)
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Preset::Css(easing) => easing.fmt(f),
+            Preset::Penner(family, direction) => {
+                let direction = match direction {
+                    EaseDirection::In => "In",
+                    EaseDirection::Out => "Out",
+                    EaseDirection::InOut => "InOut",
+                };
+                write!(f, "ease{direction}{family:?}")
+            }
+            Preset::Spring => f.write_str("spring"),
+        }
+    }
+}
+
+// Damped spring (stiffness 100, damping 10, mass 1) from rest at 0 to 1, timed to settle at t = 1
+fn spring(t: f32) -> f32 {
+    let (natural, damping_ratio) = (10.0f32, 0.5f32);
+    let decay = natural * damping_ratio;
+    let damped = natural * (1.0 - damping_ratio * damping_ratio).sqrt();
+    let duration = 1000f32.ln() / decay; // Until the envelope falls to 0.1%
+    let t = t * duration;
+    1.0 - (-decay * t).exp() * ((damped * t).cos() + decay / damped * (damped * t).sin())
+}
+
+fn fit_function(function: impl Fn(f32) -> f32) -> Vec<[Point; 4]> {
+    let mut samples: Vec<Point> = (0..PRESET_SAMPLES)
+        .map(|i| {
+            let t = i as f32 / (PRESET_SAMPLES - 1) as f32;
+            Point::new(t, function(t))
+        })
+        .collect();
+    // Pin the ends so every preset starts at (0, 0) and ends at (1, 1)
+    samples[0] = Point::ORIGIN;
+    samples[PRESET_SAMPLES - 1] = Point::new(1.0, 1.0);
+    // The samples step along x, so there are always enough distinct ones to fit
+    fit::fit_cubic_beziers(&samples, PRESET_TOLERANCE).unwrap_or_default()
+}
+
+/// Where the jumps of `steps()` happen, as in CSS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepPosition {
+    Start,
+    End,
+    None,
+    Both,
+}
+
+impl StepPosition {
+    const ALL: [StepPosition; 4] = [StepPosition::Start, StepPosition::End, StepPosition::None, StepPosition::Both];
+}
+
+/// A CSS `<easing-function>` that maps onto keys exactly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CssEasing {
+    CubicBezier([f32; 4]), // x1, y1, x2, y2
+    Steps(u32, StepPosition),
+}
+
+const KEYWORDS: [(&str, [f32; 4]); 5] = [
+    ("linear", [0.0, 0.0, 1.0, 1.0]),
+    ("ease", [0.25, 0.1, 0.25, 1.0]),
+    ("ease-in", [0.42, 0.0, 1.0, 1.0]),
+    ("ease-out", [0.0, 0.0, 0.58, 1.0]),
+    ("ease-in-out", [0.42, 0.0, 0.58, 1.0]),
+];
+
+impl CssEasing {
+    // From (0, 0) to (1, 1), steps as straight horizontal and vertical segments
+    fn segments(self) -> Vec<[Point; 4]> {
+        match self {
+            CssEasing::CubicBezier([x1, y1, x2, y2]) => {
+                vec![[Point::ORIGIN, Point::new(x1, y1), Point::new(x2, y2), Point::new(1.0, 1.0)]]
+            }
+            CssEasing::Steps(..) => {
+                let points = self.step_points();
+                points
+                    .windows(2)
+                    .map(|pair| {
+                        let chord = pair[1] - pair[0];
+                        [pair[0], pair[0] + chord * (1.0 / 3.0), pair[0] + chord * (2.0 / 3.0), pair[1]]
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    // Corners of the staircase, or the two ends for a cubic
+    fn step_points(self) -> Vec<Point> {
+        let CssEasing::Steps(count, position) = self else {
+            return vec![Point::ORIGIN, Point::new(1.0, 1.0)];
+        };
+        let jumps_at_start = matches!(position, StepPosition::Start | StepPosition::Both);
+        let jumps = match position {
+            StepPosition::Start | StepPosition::End => count,
+            StepPosition::None => count - 1,
+            StepPosition::Both => count + 1,
+        } as f32;
+
+        let mut points = Vec::new();
+        if jumps_at_start {
+            points.push(Point::ORIGIN);
+        }
+        for i in 0..count {
+            let level = (i + jumps_at_start as u32) as f32 / jumps;
+            points.push(Point::new(i as f32 / count as f32, level));
+            points.push(Point::new((i + 1) as f32 / count as f32, level));
+        }
+        if points.last().is_some_and(|last| last.y < 1.0) {
+            points.push(Point::new(1.0, 1.0));
+        }
+        points
+    }
+
+    /// The curve through `dots` as CSS, when it is a single cubic (straight or Bézier) from its
+    /// first key to its last or a `steps()` staircase. The curve is stretched so its first key
+    /// is at (0, 0) and its last at (1, 1), as CSS timing functions are.
+    pub fn from_curve(dots: &[Dot], curve_mode: Option<CurveAlgorithm>) -> Result<CssEasing, NotRepresentable> {
+        let mut dots = dots.to_vec();
+        dots.sort_by(|a, b| a.position.x.total_cmp(&b.position.x));
+        if curve_mode.is_some_and(|mode| mode != CurveAlgorithm::Bezier) {
+            return Err(NotRepresentable::Algorithm);
+        }
+        if curve_mode.is_none() {
+            // Straight connectors ignore the handles
+            for dot in &mut dots {
+                dot.in_handle = None;
+                dot.out_handle = None;
+            }
+        }
+        let (Some(first), Some(last)) = (dots.first(), dots.last()) else {
+            return Err(NotRepresentable::TooFewKeys);
+        };
+        let span = last.position - first.position;
+        if dots.len() < 2 || span.x <= 0.0 || span.y == 0.0 {
+            return Err(NotRepresentable::NoProgress);
+        }
+        let normalize = |p: Point| {
+            Point::new(
+                (p.x - first.position.x) / span.x,
+                (p.y - first.position.y) / span.y,
+            )
+        };
+        let segments: Vec<[Point; 4]> = dots
+            .windows(2)
+            .map(|pair| curve::bezier_segment(&pair[0], &pair[1]).map(normalize))
+            .collect();
+
+        if let [[_, control_a, control_b, _]] = segments[..] {
+            if !(0.0..=1.0).contains(&control_a.x) || !(0.0..=1.0).contains(&control_b.x) {
+                return Err(NotRepresentable::HandlesOutsideRange);
+            }
+            return Ok(CssEasing::CubicBezier(
+                [control_a.x, control_a.y, control_b.x, control_b.y].map(round_css),
+            ));
+        }
+
+        // More keys can still be a staircase if every segment is straight
+        const TOLERANCE: f32 = 1e-3;
+        let close = |a: Point, b: Point| (a.x - b.x).abs() < TOLERANCE && (a.y - b.y).abs() < TOLERANCE;
+        let straight = segments.iter().all(|&[start, control_a, control_b, end]| {
+            let chord = end - start;
+            close(control_a, start + chord * (1.0 / 3.0)) && close(control_b, start + chord * (2.0 / 3.0))
+        });
+        let corners: Vec<Point> = dots.iter().map(|dot| normalize(dot.position)).collect();
+        if straight {
+            for count in 1..=corners.len() as u32 {
+                for position in StepPosition::ALL {
+                    if position == StepPosition::None && count < 2 {
+                        continue;
+                    }
+                    let steps = CssEasing::Steps(count, position);
+                    let points = steps.step_points();
+                    if points.len() == corners.len() && points.iter().zip(&corners).all(|(&a, &b)| close(a, b)) {
+                        return Ok(steps);
+                    }
+                }
+            }
+        }
+        Err(NotRepresentable::TooManyKeys)
+    }
+}
+
+// Three decimals is what CSS authors write and keeps copied values readable
+fn round_css(value: f32) -> f32 {
+    (value * 1000.0).round() / 1000.0
+}
+
+impl fmt::Display for CssEasing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CssEasing::CubicBezier(values) => match KEYWORDS.iter().find(|(_, keyword)| *keyword == values) {
+                Some((name, _)) => f.write_str(name),
+                None => {
+                    let [x1, y1, x2, y2] = values;
+                    write!(f, "cubic-bezier({x1}, {y1}, {x2}, {y2})")
+                }
+            },
+            CssEasing::Steps(count, StepPosition::End) => write!(f, "steps({count})"),
+            CssEasing::Steps(count, position) => {
+                let position = match position {
+                    StepPosition::Start => "jump-start",
+                    StepPosition::End => "jump-end",
+                    StepPosition::None => "jump-none",
+                    StepPosition::Both => "jump-both",
+                };
+                write!(f, "steps({count}, {position})")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseEasingError;
+
+impl fmt::Display for ParseEasingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected a CSS easing keyword, cubic-bezier(x1, y1, x2, y2) with x1 and x2 in 0..1, \
+             or steps(n, position) with at most {MAX_STEPS} steps"
+        )
+    }
+}
+
+impl FromStr for CssEasing {
+    type Err = ParseEasingError;
+
+    /// Parses keywords, `cubic-bezier()` and `steps()`, ignoring case and a trailing semicolon.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().trim_end_matches(';').trim().to_ascii_lowercase();
+        if let Some((_, values)) = KEYWORDS.iter().find(|(name, _)| *name == s) {
+            return Ok(CssEasing::CubicBezier(*values));
+        }
+        match s.as_str() {
+            "step-start" => return Ok(CssEasing::Steps(1, StepPosition::Start)),
+            "step-end" => return Ok(CssEasing::Steps(1, StepPosition::End)),
+            _ => {}
+        }
+
+        let (name, arguments) = s.split_once('(').ok_or(ParseEasingError)?;
+        let arguments: Vec<&str> = arguments
+            .strip_suffix(')')
+            .ok_or(ParseEasingError)?
+            .split(',')
+            .map(str::trim)
+            .collect();
+        match (name.trim(), &arguments[..]) {
+            ("cubic-bezier", &[x1, y1, x2, y2]) => {
+                let values = [x1, y1, x2, y2].map(|value| value.parse::<f32>().ok().filter(|v| v.is_finite()));
+                let [Some(x1), Some(y1), Some(x2), Some(y2)] = values else {
+                    return Err(ParseEasingError);
+                };
+                if !(0.0..=1.0).contains(&x1) || !(0.0..=1.0).contains(&x2) {
+                    return Err(ParseEasingError);
+                }
+                Ok(CssEasing::CubicBezier([x1, y1, x2, y2]))
+            }
+            ("steps", [count, position @ ..]) => {
+                let count: u32 = count.parse().map_err(|_| ParseEasingError)?;
+                let position = match position {
+                    [] | ["jump-end" | "end"] => StepPosition::End,
+                    ["jump-start" | "start"] => StepPosition::Start,
+                    ["jump-none"] => StepPosition::None,
+                    ["jump-both"] => StepPosition::Both,
+                    _ => return Err(ParseEasingError),
+                };
+                let minimum = if position == StepPosition::None { 2 } else { 1 };
+                if !(minimum..=MAX_STEPS).contains(&count) {
+                    return Err(ParseEasingError);
+                }
+                Ok(CssEasing::Steps(count, position))
+            }
+            _ => Err(ParseEasingError),
+        }
+    }
+}
+
+/// Why the current curve can't be copied out as a CSS timing function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotRepresentable {
+    Algorithm,
+    TooFewKeys,
+    NoProgress,
+    HandlesOutsideRange,
+    TooManyKeys,
+}
+
+impl fmt::Display for NotRepresentable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NotRepresentable::Algorithm => "only straight and Bézier curves map onto CSS timing functions",
+            NotRepresentable::TooFewKeys => "at least two keys are needed",
+            NotRepresentable::NoProgress => "the last key must be to the right of the first and at a different height",
+            NotRepresentable::HandlesOutsideRange => "cubic-bezier() handles must stay between the first and last key in x",
+            NotRepresentable::TooManyKeys => "cubic-bezier() has exactly two keys and this is not a steps() staircase",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keywords_parse_to_their_cubics() {
+        for (name, values) in KEYWORDS {
+            assert_eq!(name.parse(), Ok(CssEasing::CubicBezier(values)));
+        }
+        assert_eq!(
+            " Ease-In-Out; ".parse(),
+            Ok(CssEasing::CubicBezier([0.42, 0.0, 0.58, 1.0]))
+        );
+        assert_eq!("step-start".parse(), Ok(CssEasing::Steps(1, StepPosition::Start)));
+        assert_eq!("step-end".parse(), Ok(CssEasing::Steps(1, StepPosition::End)));
+        assert_eq!("bounce".parse::<CssEasing>(), Err(ParseEasingError));
+    }
+
+    #[test]
+    fn cubic_bezier_needs_four_numbers_with_x_in_range() {
+        assert_eq!(
+            "cubic-bezier(0.1, -0.5, .9, 1.5)".parse(),
+            Ok(CssEasing::CubicBezier([0.1, -0.5, 0.9, 1.5]))
+        );
+        for text in [
+            "cubic-bezier(1.1, 0, 0.5, 1)",
+            "cubic-bezier(0.5, 0, -0.1, 1)",
+            "cubic-bezier(0.5, 0, 0.5)",
+            "cubic-bezier(0.5, 0, 0.5, 1, 1)",
+            "cubic-bezier(0.5, x, 0.5, 1)",
+            "cubic-bezier(0.5, inf, 0.5, 1)",
+            "cubic-bezier(0.5, 0, 0.5, 1",
+        ] {
+            assert_eq!(text.parse::<CssEasing>(), Err(ParseEasingError), "{text}");
+        }
+    }
+
+    #[test]
+    fn steps_take_a_bounded_count_and_a_position() {
+        assert_eq!("steps(4)".parse(), Ok(CssEasing::Steps(4, StepPosition::End)));
+        assert_eq!("steps(4, end)".parse(), Ok(CssEasing::Steps(4, StepPosition::End)));
+        assert_eq!("steps(4, start)".parse(), Ok(CssEasing::Steps(4, StepPosition::Start)));
+        assert_eq!(
+            "steps(2, jump-none)".parse(),
+            Ok(CssEasing::Steps(2, StepPosition::None))
+        );
+        assert_eq!(
+            "steps(1, jump-both)".parse(),
+            Ok(CssEasing::Steps(1, StepPosition::Both))
+        );
+        assert_eq!(
+            format!("steps({MAX_STEPS})").parse(),
+            Ok(CssEasing::Steps(MAX_STEPS, StepPosition::End))
+        );
+        for text in [
+            "steps(0)",
+            "steps(1, jump-none)",
+            "steps(-1)",
+            "steps(1001)",
+            "steps(4294967295)",
+            "steps(2, middle)",
+            "steps()",
+        ] {
+            assert_eq!(text.parse::<CssEasing>(), Err(ParseEasingError), "{text}");
+        }
+    }
+
+    #[test]
+    fn copied_css_matches_what_was_loaded() {
+        for text in [
+            "linear",
+            "ease",
+            "ease-in",
+            "ease-out",
+            "ease-in-out",
+            "cubic-bezier(0.1, -0.5, 0.9, 1.5)",
+            "steps(4)",
+            "steps(3, jump-start)",
+            "steps(3, jump-none)",
+            "steps(2, jump-both)",
+        ] {
+            let easing: CssEasing = text.parse().unwrap();
+            assert_eq!(easing.to_string(), text);
+            // Loading keeps the Bézier algorithm, as the editor does
+            let dots = Preset::Css(easing).dots();
+            assert_eq!(
+                CssEasing::from_curve(&dots, Some(CurveAlgorithm::Bezier)),
+                Ok(easing),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn overshooting_presets_fit_inside_the_unit_square() {
+        for preset in Preset::all() {
+            let dots = preset.dots();
+            assert_eq!(dots.first().unwrap().position.x, 0.0, "{preset}");
+            assert!((dots.last().unwrap().position.x - 1.0).abs() < 1e-6, "{preset}");
+            for dot in &dots {
+                assert!((-1e-6..=1.0 + 1e-6).contains(&dot.position.y), "{preset}");
+            }
+        }
+        let back = Preset::Penner(PennerFamily::Back, EaseDirection::In).dots();
+        assert!(back[0].position.y > 0.0);
+    }
+}
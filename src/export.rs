@@ -0,0 +1,253 @@
+// Writing the evaluated curve out as data and source code
+use std::fmt::{self, Write};
+
+use iced::Point;
+
+use crate::curve::{self, CurveAlgorithm, CurveError, Dot, SplineSettings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+    Rust,
+    Glsl,
+    Wgsl,
+    Cube,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 6] = [
+        ExportFormat::Csv,
+        ExportFormat::Json,
+        ExportFormat::Rust,
+        ExportFormat::Glsl,
+        ExportFormat::Wgsl,
+        ExportFormat::Cube,
+    ];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Rust => "rs",
+            ExportFormat::Glsl => "glsl",
+            ExportFormat::Wgsl => "wgsl",
+            ExportFormat::Cube => "cube",
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Json => "JSON array",
+            ExportFormat::Rust => "Rust const array",
+            ExportFormat::Glsl => "GLSL function",
+            ExportFormat::Wgsl => "WGSL function",
+            ExportFormat::Cube => "1D .cube LUT",
+        })
+    }
+}
+
+/// Where and how densely to sample. `start` and `end` are curve space x values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExportSettings {
+    pub resolution: usize,
+    pub start: f32,
+    pub end: f32,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 256,
+            start: 0.0,
+            end: 1.0,
+        }
+    }
+}
+
+/// Evaluates the curve through the dots at `resolution` evenly spaced x values (at least two).
+pub fn sample(
+    dots: &[Dot],
+    curve_mode: Option<CurveAlgorithm>,
+    spline_settings: SplineSettings,
+    settings: ExportSettings,
+) -> Result<Vec<Point>, CurveError> {
+    let n = settings.resolution.max(2);
+    let xs: Vec<f32> = (0..n)
+        .map(|i| settings.start + (settings.end - settings.start) * i as f32 / (n - 1) as f32)
+        .collect();
+    let ys = curve::evaluate_at(curve_mode, dots, spline_settings, &xs)?;
+    Ok(xs.into_iter().zip(ys).map(|(x, y)| Point { x, y }).collect())
+}
+
+/// Samples the curve and formats it in one step, for use without the editor.
+pub fn export_curve(
+    dots: &[Dot],
+    curve_mode: Option<CurveAlgorithm>,
+    spline_settings: SplineSettings,
+    settings: ExportSettings,
+    format: ExportFormat,
+) -> Result<String, CurveError> {
+    format_samples(format, &sample(dots, curve_mode, spline_settings, settings)?)
+}
+
+/// Formats evenly spaced samples, such as those from `sample` or a baked lookup table. The shader
+/// functions interpolate between neighbouring entries, so at least two samples are needed.
+pub fn format_samples(format: ExportFormat, samples: &[Point]) -> Result<String, CurveError> {
+    let n = samples.len();
+    if n < 2 {
+        return Err(CurveError::TooFewPoints { needed: 2, found: n });
+    }
+    let values = samples
+        .iter()
+        .map(|point| format!("{:.6}", point.y))
+        .collect::<Vec<_>>();
+    let (start, end) = (samples[0].x, samples[n - 1].x);
+
+    let mut out = String::new();
+    match format {
+        ExportFormat::Csv => {
+            out.push_str("x,y\n");
+            for point in samples {
+                let _ = writeln!(out, "{:.6},{:.6}", point.x, point.y);
+            }
+        }
+        ExportFormat::Json => {
+            let _ = writeln!(out, "[{}]", values.join(", "));
+        }
+        ExportFormat::Rust => {
+            let _ = writeln!(out, "// Curve sampled at {n} points for x in [{start:.6}, {end:.6}]");
+            let _ = writeln!(out, "pub const CURVE: [f32; {n}] = [\n    {},\n];", values.join(",\n    "));
+        }
+        ExportFormat::Glsl => {
+            let _ = writeln!(
+                out,
+                "const float CURVE_LUT[{n}] = float[{n}](\n    {}\n);\n",
+                values.join(",\n    ")
+            );
+            let _ = writeln!(
+                out,
+                "// Linear interpolation of the table for x in [{start:.6}, {end:.6}]\n\
+                 float curve(float x) {{\n    \
+                 float t = clamp((x - {start:.6}) / {span:.6}, 0.0, 1.0) * {last}.0;\n    \
+                 int i = min(int(t), {before_last});\n    \
+                 return mix(CURVE_LUT[i], CURVE_LUT[i + 1], t - float(i));\n\
+                 }}",
+                span = end - start,
+                last = n - 1,
+                before_last = n - 2,
+            );
+        }
+        ExportFormat::Wgsl => {
+            let _ = writeln!(
+                out,
+                "var<private> CURVE_LUT: array<f32, {n}> = array<f32, {n}>(\n    {}\n);\n",
+                values.join(",\n    ")
+            );
+            let _ = writeln!(
+                out,
+                "// Linear interpolation of the table for x in [{start:.6}, {end:.6}]\n\
+                 fn curve(x: f32) -> f32 {{\n    \
+                 let t = clamp((x - {start:.6}) / {span:.6}, 0.0, 1.0) * {last}.0;\n    \
+                 let i = min(u32(t), {before_last}u);\n    \
+                 return mix(CURVE_LUT[i], CURVE_LUT[i + 1u], t - f32(i));\n\
+                 }}",
+                span = end - start,
+                last = n - 1,
+                before_last = n - 2,
+            );
+        }
+        ExportFormat::Cube => {
+            // A 1D LUT applies the same curve to all three channels
+            let _ = writeln!(out, "TITLE \"curve\"");
+            let _ = writeln!(out, "LUT_1D_SIZE {n}");
+            let _ = writeln!(out, "DOMAIN_MIN {start:.6} {start:.6} {start:.6}");
+            let _ = writeln!(out, "DOMAIN_MAX {end:.6} {end:.6} {end:.6}");
+            for value in &values {
+                let _ = writeln!(out, "{value} {value} {value}");
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn three_samples() -> [Point; 3] {
+        [Point::new(0.0, 0.0), Point::new(0.5, 0.25), Point::new(1.0, 1.0)]
+    }
+
+    fn format(format: ExportFormat) -> String {
+        format_samples(format, &three_samples()).unwrap()
+    }
+
+    #[test]
+    fn csv_lists_both_coordinates() {
+        assert_eq!(
+            format(ExportFormat::Csv),
+            "x,y\n0.000000,0.000000\n0.500000,0.250000\n1.000000,1.000000\n"
+        );
+    }
+
+    #[test]
+    fn json_lists_the_values() {
+        assert_eq!(format(ExportFormat::Json), "[0.000000, 0.250000, 1.000000]\n");
+    }
+
+    #[test]
+    fn rust_declares_a_sized_array() {
+        assert_eq!(
+            format(ExportFormat::Rust),
+            "// Curve sampled at 3 points for x in [0.000000, 1.000000]\n\
+             pub const CURVE: [f32; 3] = [\n    0.000000,\n    0.250000,\n    1.000000,\n];\n"
+        );
+    }
+
+    #[test]
+    fn glsl_interpolates_inside_the_table() {
+        let glsl = format(ExportFormat::Glsl);
+        assert!(
+            glsl.starts_with("const float CURVE_LUT[3] = float[3](\n    0.000000,\n    0.250000,\n    1.000000\n);")
+        );
+        assert!(glsl.contains("float t = clamp((x - 0.000000) / 1.000000, 0.0, 1.0) * 2.0;"));
+        assert!(glsl.contains("int i = min(int(t), 1);"));
+    }
+
+    #[test]
+    fn wgsl_interpolates_inside_the_table() {
+        let wgsl = format(ExportFormat::Wgsl);
+        assert!(wgsl.starts_with("var<private> CURVE_LUT: array<f32, 3> = array<f32, 3>(\n    0.000000,"));
+        assert!(wgsl.contains("let t = clamp((x - 0.000000) / 1.000000, 0.0, 1.0) * 2.0;"));
+        assert!(wgsl.contains("let i = min(u32(t), 1u);"));
+    }
+
+    #[test]
+    fn cube_repeats_the_value_per_channel() {
+        assert_eq!(
+            format(ExportFormat::Cube),
+            "TITLE \"curve\"\nLUT_1D_SIZE 3\n\
+             DOMAIN_MIN 0.000000 0.000000 0.000000\nDOMAIN_MAX 1.000000 1.000000 1.000000\n\
+             0.000000 0.000000 0.000000\n0.250000 0.250000 0.250000\n1.000000 1.000000 1.000000\n"
+        );
+    }
+
+    #[test]
+    fn fewer_than_two_samples_are_refused() {
+        for format in ExportFormat::ALL {
+            assert!(matches!(
+                format_samples(format, &[]),
+                Err(CurveError::TooFewPoints { needed: 2, found: 0 })
+            ));
+            assert!(matches!(
+                format_samples(format, &three_samples()[..1]),
+                Err(CurveError::TooFewPoints { needed: 2, found: 1 })
+            ));
+        }
+    }
+}
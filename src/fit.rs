@@ -0,0 +1,338 @@
+// Least-squares cubic Bézier fitting, after Philip J. Schneider,
+// "An Algorithm for Automatically Fitting Digitized Curves", Graphics Gems (1990)
+use iced::{Point, Vector};
+
+use crate::curve::CurveError;
+
+// Newton-Raphson reparameterization passes tried before a segment is split
+const MAX_ITERATIONS: usize = 4;
+
+/// Fits a chain of cubic Bézier segments `[start, control_a, control_b, end]` through the samples,
+/// splitting until every sample lies within `tolerance` of the curve. Fails when fewer than two
+/// distinct samples are left after dropping repeats.
+pub fn fit_cubic_beziers(samples: &[Point], tolerance: f32) -> Result<Vec<[Point; 4]>, CurveError> {
+    // Repeated samples have no direction, drop them so tangents and chord lengths stay finite
+    let mut points: Vec<Point> = Vec::with_capacity(samples.len());
+    for &sample in samples {
+        if points.last() != Some(&sample) {
+            points.push(sample);
+        }
+    }
+
+    if points.len() < 2 {
+        return Err(CurveError::TooFewPoints {
+            needed: 2,
+            found: points.len(),
+        });
+    }
+
+    let last = points.len() - 1;
+    let start_tangent = end_tangent(&points[..points.len().min(3)]);
+    let reversed: Vec<Point> = points[points.len().saturating_sub(3)..].iter().rev().copied().collect();
+    let end_tangent = end_tangent(&reversed);
+    let mut segments = Vec::new();
+    fit_cubic(
+        &points,
+        0,
+        last,
+        start_tangent,
+        end_tangent,
+        tolerance * tolerance,
+        &mut segments,
+    );
+    Ok(segments)
+}
+
+fn fit_cubic(
+    points: &[Point],
+    first: usize,
+    last: usize,
+    start_tangent: Vector,
+    end_tangent: Vector,
+    squared_tolerance: f32,
+    segments: &mut Vec<[Point; 4]>,
+) {
+    if last - first == 1 {
+        // Two points: place the handles a third of the way along the tangents
+        let distance = length(points[last] - points[first]) / 3.0;
+        segments.push([
+            points[first],
+            points[first] + start_tangent * distance,
+            points[last] + end_tangent * distance,
+            points[last],
+        ]);
+        return;
+    }
+
+    // Chord lengths suit hand-drawn or measured data. Samples taken at even parameter steps, such as
+    // a curve evaluated by another tool, are fitted exactly by even parameters, which reparameterizing
+    // from chord lengths only approaches slowly
+    let (mut u, mut bezier, (mut max_error, mut split)) = [
+        chord_length_parameterize(points, first, last),
+        uniform_parameterize(first, last),
+    ]
+    .into_iter()
+    .map(|u| {
+        let bezier = generate_bezier(points, first, last, &u, start_tangent, end_tangent);
+        let error = compute_max_error(points, first, last, &bezier, &u);
+        (u, bezier, error)
+    })
+    .min_by(|a, b| a.2 .0.total_cmp(&b.2 .0))
+    .expect("two parameterizations");
+    if max_error < squared_tolerance {
+        segments.push(bezier);
+        return;
+    }
+
+    // Close misses are usually a bad parameterization rather than a bad shape
+    if max_error < squared_tolerance * 4.0 {
+        for _ in 0..MAX_ITERATIONS {
+            u = reparameterize(points, first, &u, &bezier);
+            bezier = generate_bezier(points, first, last, &u, start_tangent, end_tangent);
+            (max_error, split) = compute_max_error(points, first, last, &bezier, &u);
+            if max_error < squared_tolerance {
+                segments.push(bezier);
+                return;
+            }
+        }
+    }
+
+    // Split at the worst point and fit both halves, sharing a tangent so the join stays smooth
+    let center_tangent = normalize(points[split - 1] - points[split + 1]);
+    fit_cubic(points, first, split, start_tangent, center_tangent, squared_tolerance, segments);
+    fit_cubic(points, split, last, center_tangent * -1.0, end_tangent, squared_tolerance, segments);
+}
+
+// Direction the samples leave the first one in. With three samples it's the derivative of the parabola
+// through them in chord length, which is exact for the quadratic part of the curve. A plain chord to
+// the second sample bends the tangent by the curvature, enough to stop smooth input from fitting with
+// one segment at tight tolerances
+fn end_tangent(points: &[Point]) -> Vector {
+    let chord = points[1] - points[0];
+    let [a, b, c] = points else {
+        return normalize(chord);
+    };
+    let (d1, d2) = (length(*b - *a), length(*c - *b));
+    let derivative = (*b - *a) * ((d1 + d2) / (d1 * d2)) - (*c - *a) * (d1 / (d2 * (d1 + d2)));
+    if dot(derivative, chord) > 0.0 {
+        normalize(derivative)
+    } else {
+        normalize(chord)
+    }
+}
+
+// Least-squares handle lengths along the fixed end tangents for the given parameters
+fn generate_bezier(
+    points: &[Point],
+    first: usize,
+    last: usize,
+    u: &[f32],
+    start_tangent: Vector,
+    end_tangent: Vector,
+) -> [Point; 4] {
+    let (p0, p3) = (points[first], points[last]);
+    let mut c = [[0.0_f32; 2]; 2];
+    let mut x = [0.0_f32; 2];
+
+    for (i, &t) in u.iter().enumerate() {
+        let [b0, b1, b2, b3] = bernstein(t);
+        let a0 = start_tangent * b1;
+        let a1 = end_tangent * b2;
+        c[0][0] += dot(a0, a0);
+        c[0][1] += dot(a0, a1);
+        c[1][1] += dot(a1, a1);
+
+        let fixed = Point::ORIGIN + (p0 - Point::ORIGIN) * (b0 + b1) + (p3 - Point::ORIGIN) * (b2 + b3);
+        let residual = points[first + i] - fixed;
+        x[0] += dot(a0, residual);
+        x[1] += dot(a1, residual);
+    }
+    c[1][0] = c[0][1];
+
+    let det_c0_c1 = c[0][0] * c[1][1] - c[1][0] * c[0][1];
+    let det_c0_x = c[0][0] * x[1] - c[1][0] * x[0];
+    let det_x_c1 = x[0] * c[1][1] - x[1] * c[0][1];
+    let (alpha_start, alpha_end) = if det_c0_c1 == 0.0 {
+        (0.0, 0.0)
+    } else {
+        (det_x_c1 / det_c0_c1, det_c0_x / det_c0_c1)
+    };
+
+    // Degenerate or backwards handles fall back to the two-point heuristic
+    let segment_length = length(p3 - p0);
+    let epsilon = 1.0e-6 * segment_length;
+    let (alpha_start, alpha_end) = if alpha_start < epsilon || alpha_end < epsilon {
+        (segment_length / 3.0, segment_length / 3.0)
+    } else {
+        (alpha_start, alpha_end)
+    };
+
+    [
+        p0,
+        p0 + start_tangent * alpha_start,
+        p3 + end_tangent * alpha_end,
+        p3,
+    ]
+}
+
+fn chord_length_parameterize(points: &[Point], first: usize, last: usize) -> Vec<f32> {
+    let mut u = Vec::with_capacity(last - first + 1);
+    u.push(0.0);
+    for i in first + 1..=last {
+        let previous = u[i - first - 1];
+        u.push(previous + length(points[i] - points[i - 1]));
+    }
+    let total = u[last - first];
+    u.iter_mut().for_each(|t| *t /= total);
+    u
+}
+
+fn uniform_parameterize(first: usize, last: usize) -> Vec<f32> {
+    (first..=last).map(|i| (i - first) as f32 / (last - first) as f32).collect()
+}
+
+// One Newton-Raphson step per sample towards the closest point on the curve
+fn reparameterize(points: &[Point], first: usize, u: &[f32], bezier: &[Point; 4]) -> Vec<f32> {
+    u.iter()
+        .enumerate()
+        .map(|(i, &t)| {
+            let point = points[first + i];
+            let q = evaluate_bezier(bezier, t) - point;
+            let q1 = first_derivative(bezier, t);
+            let q2 = second_derivative(bezier, t);
+            let denominator = dot(q1, q1) + dot(q, q2);
+            if denominator == 0.0 {
+                t
+            } else {
+                (t - dot(q, q1) / denominator).clamp(0.0, 1.0)
+            }
+        })
+        .collect()
+}
+
+// Largest squared distance between a sample and its point on the curve, and where it happens
+fn compute_max_error(
+    points: &[Point],
+    first: usize,
+    last: usize,
+    bezier: &[Point; 4],
+    u: &[f32],
+) -> (f32, usize) {
+    let mut max_error = 0.0;
+    let mut split = (first + last) / 2;
+    for i in first + 1..last {
+        let error = squared_length(evaluate_bezier(bezier, u[i - first]) - points[i]);
+        if error >= max_error {
+            max_error = error;
+            split = i;
+        }
+    }
+    (max_error, split)
+}
+
+fn bernstein(t: f32) -> [f32; 4] {
+    let s = 1.0 - t;
+    [s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t]
+}
+
+pub fn evaluate_bezier(bezier: &[Point; 4], t: f32) -> Point {
+    let [b0, b1, b2, b3] = bernstein(t);
+    Point::new(
+        b0 * bezier[0].x + b1 * bezier[1].x + b2 * bezier[2].x + b3 * bezier[3].x,
+        b0 * bezier[0].y + b1 * bezier[1].y + b2 * bezier[2].y + b3 * bezier[3].y,
+    )
+}
+
+/// Derivative of a cubic Bézier with respect to t.
+pub fn first_derivative(bezier: &[Point; 4], t: f32) -> Vector {
+    let s = 1.0 - t;
+    (bezier[1] - bezier[0]) * (3.0 * s * s)
+        + (bezier[2] - bezier[1]) * (6.0 * s * t)
+        + (bezier[3] - bezier[2]) * (3.0 * t * t)
+}
+
+pub fn second_derivative(bezier: &[Point; 4], t: f32) -> Vector {
+    let first = (bezier[2] - bezier[1]) - (bezier[1] - bezier[0]);
+    let second = (bezier[3] - bezier[2]) - (bezier[2] - bezier[1]);
+    first * (6.0 * (1.0 - t)) + second * (6.0 * t)
+}
+
+fn dot(a: Vector, b: Vector) -> f32 {
+    a.x * b.x + a.y * b.y
+}
+
+fn squared_length(v: Vector) -> f32 {
+    dot(v, v)
+}
+
+fn length(v: Vector) -> f32 {
+    squared_length(v).sqrt()
+}
+
+fn normalize(v: Vector) -> Vector {
+    let length = length(v);
+    if length == 0.0 {
+        v
+    } else {
+        v * (1.0 / length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Distance from a point to a fitted chain, measured against densely evaluated segments
+    fn distance_to(segments: &[[Point; 4]], point: Point) -> f32 {
+        segments
+            .iter()
+            .flat_map(|segment| (0..=400).map(move |i| evaluate_bezier(segment, i as f32 / 400.0)))
+            .map(|on_curve| length(on_curve - point))
+            .fold(f32::MAX, f32::min)
+    }
+
+    #[test]
+    fn fits_stay_within_the_tolerance() {
+        let samples: Vec<Point> = (0..=200)
+            .map(|i| {
+                let x = i as f32 / 200.0;
+                Point::new(x, 0.5 + 0.4 * (x * 9.0).sin() * (1.0 - x))
+            })
+            .collect();
+        for tolerance in [0.05, 0.01, 0.002] {
+            let segments = fit_cubic_beziers(&samples, tolerance).unwrap();
+            assert!(segments.len() > 1);
+            assert_eq!(segments[0][0], samples[0]);
+            assert_eq!(segments.last().unwrap()[3], *samples.last().unwrap());
+            for &sample in &samples {
+                // A little slack for measuring against a discretized curve
+                assert!(distance_to(&segments, sample) <= tolerance * 1.01 + 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn a_cubic_is_fitted_with_one_segment() {
+        let cubic = [
+            Point::new(0.0, 0.0),
+            Point::new(0.2, 0.8),
+            Point::new(0.7, 1.0),
+            Point::new(1.0, 0.3),
+        ];
+        let samples: Vec<Point> = (0..=60).map(|i| evaluate_bezier(&cubic, i as f32 / 60.0)).collect();
+        let segments = fit_cubic_beziers(&samples, 1e-3).unwrap();
+        assert_eq!(segments.len(), 1);
+        for (fitted, expected) in segments[0].iter().zip(cubic) {
+            assert!(length(*fitted - expected) < 0.01, "{:?} != {cubic:?}", segments[0]);
+        }
+    }
+
+    #[test]
+    fn repeated_samples_alone_are_refused() {
+        let samples = [Point::new(0.5, 0.5); 4];
+        assert_eq!(
+            fit_cubic_beziers(&samples, 0.01),
+            Err(CurveError::TooFewPoints { needed: 2, found: 1 })
+        );
+    }
+}
@@ -0,0 +1,229 @@
+// Color gradients: every key carries a color and each channel is its own curve through the keys,
+// built with the same algorithm as the y curve
+use std::fmt::{self, Write};
+
+use iced::widget::canvas::{self, Path};
+use iced::{mouse, Color, Point, Rectangle, Renderer, Size, Theme};
+
+use crate::curve::{self, CurveAlgorithm, CurveError, Dot, SplineSettings};
+
+/// Space the channels are interpolated in. `iced::Color` itself is sRGB encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorSpace {
+    /// Perceptually uniform, so ramps don't dip in brightness or turn gray halfway.
+    #[default]
+    Oklab,
+    LinearRgb,
+    Srgb,
+}
+
+impl ColorSpace {
+    pub const ALL: [ColorSpace; 3] = [ColorSpace::Oklab, ColorSpace::LinearRgb, ColorSpace::Srgb];
+
+    pub fn to_channels(self, color: Color) -> [f32; 3] {
+        let linear = [color.r, color.g, color.b].map(srgb_to_linear);
+        match self {
+            ColorSpace::Srgb => [color.r, color.g, color.b],
+            ColorSpace::LinearRgb => linear,
+            ColorSpace::Oklab => linear_to_oklab(linear),
+        }
+    }
+
+    /// The color with these channels, clamped into the sRGB gamut.
+    pub fn from_channels(self, channels: [f32; 3]) -> Color {
+        let [r, g, b] = match self {
+            ColorSpace::Srgb => channels,
+            ColorSpace::LinearRgb => channels.map(linear_to_srgb),
+            ColorSpace::Oklab => oklab_to_linear(channels).map(linear_to_srgb),
+        }
+        .map(|channel| if channel.is_nan() { 0.0 } else { channel.clamp(0.0, 1.0) });
+        Color::from_rgb(r, g, b)
+    }
+}
+
+impl fmt::Display for ColorSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ColorSpace::Oklab => "OKLab",
+            ColorSpace::LinearRgb => "Linear RGB",
+            ColorSpace::Srgb => "sRGB",
+        })
+    }
+}
+
+/// Color of a gradient key; keys without one are gray at their height.
+pub fn key_color(dot: &Dot) -> Color {
+    dot.color.unwrap_or_else(|| {
+        let gray = dot.position.y.clamp(0.0, 1.0);
+        Color::from_rgb(gray, gray, gray)
+    })
+}
+
+/// The gradient at `n` evenly spaced x values from 0 to 1 (at least two).
+pub fn sample_gradient(
+    dots: &[Dot],
+    curve_mode: Option<CurveAlgorithm>,
+    spline_settings: SplineSettings,
+    space: ColorSpace,
+    n: usize,
+) -> Result<Vec<Color>, CurveError> {
+    let n = n.max(2);
+    let xs: Vec<f32> = (0..n).map(|i| i as f32 / (n - 1) as f32).collect();
+    let channels: Vec<[f32; 3]> = dots.iter().map(|dot| space.to_channels(key_color(dot))).collect();
+
+    let mut values = [const { Vec::new() }; 3];
+    for (channel, values) in values.iter_mut().enumerate() {
+        // Handles shape the y curve, not the colors, so channel keys keep only position and weight
+        let keys: Vec<Dot> = dots
+            .iter()
+            .zip(&channels)
+            .map(|(dot, color)| Dot {
+                weight: dot.weight,
+                ..Dot::new(Point::new(dot.position.x, color[channel]))
+            })
+            .collect();
+        *values = curve::evaluate_at(curve_mode, &keys, spline_settings, &xs)?;
+    }
+    Ok((0..n)
+        .map(|i| space.from_channels([values[0][i], values[1][i], values[2][i]]))
+        .collect())
+}
+
+/// `#rrggbb`, as typed into the key color field.
+pub fn to_hex(color: Color) -> String {
+    let [r, g, b, _] = color.into_rgba8();
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+pub fn parse_hex(hex: &str) -> Option<Color> {
+    let hex = hex.trim().strip_prefix('#').unwrap_or(hex.trim());
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::from_rgb8(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// A 1D `.cube` LUT with the gradient's sRGB values, so channel i of the input maps to channel i
+/// of the gradient.
+pub fn format_cube(colors: &[Color]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "TITLE \"gradient\"");
+    let _ = writeln!(out, "LUT_1D_SIZE {}", colors.len());
+    for color in colors {
+        let _ = writeln!(out, "{:.6} {:.6} {:.6}", color.r, color.g, color.b);
+    }
+    out
+}
+
+pub fn format_csv(colors: &[Color]) -> String {
+    let mut out = String::from("x,r,g,b\n");
+    let last = colors.len().saturating_sub(1).max(1) as f32;
+    for (i, color) in colors.iter().enumerate() {
+        let _ = writeln!(out, "{:.6},{:.6},{:.6},{:.6}", i as f32 / last, color.r, color.g, color.b);
+    }
+    out
+}
+
+/// Canvas program drawing sampled gradient colors side by side, as the strip under the editor.
+pub struct GradientStrip<'a> {
+    pub colors: &'a [Color],
+}
+
+impl<Message> canvas::Program<Message> for GradientStrip<'_> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<canvas::Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        let width = bounds.width / self.colors.len().max(1) as f32;
+        for (i, &color) in self.colors.iter().enumerate() {
+            // Slightly wider than a column so no background shows between them
+            frame.fill(
+                &Path::rectangle(Point::new(i as f32 * width, 0.0), Size::new(width + 1.0, bounds.height)),
+                color,
+            );
+        }
+        vec![frame.into_geometry()]
+    }
+}
+
+// sRGB transfer functions from IEC 61966-2-1
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// OKLab matrices from Björn Ottosson, "A perceptual color space for image processing" (2020),
+// kept digit for digit as published
+#[allow(clippy::excessive_precision)]
+fn linear_to_oklab([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+#[allow(clippy::excessive_precision)]
+fn oklab_to_linear([lightness, a, b]: [f32; 3]) -> [f32; 3] {
+    let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_spaces_round_trip() {
+        for space in ColorSpace::ALL {
+            for color in [Color::BLACK, Color::WHITE, Color::from_rgb(0.9, 0.2, 0.4)] {
+                let back = space.from_channels(space.to_channels(color));
+                for (a, b) in [(back.r, color.r), (back.g, color.g), (back.b, color.b)] {
+                    assert!((a - b).abs() < 1e-4, "{space}: {back:?} != {color:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn two_keys_blend_between_their_colors() {
+        let key = |x: f32, color: Color| Dot {
+            color: Some(color),
+            ..Dot::new(Point::new(x, 0.5))
+        };
+        let dots = [key(0.0, Color::BLACK), key(1.0, Color::WHITE)];
+        let srgb = sample_gradient(&dots, None, SplineSettings::default(), ColorSpace::Srgb, 3).unwrap();
+        assert!((srgb[1].r - 0.5).abs() < 1e-6);
+        // Halfway in linear light is brighter than halfway in sRGB values
+        let linear = sample_gradient(&dots, None, SplineSettings::default(), ColorSpace::LinearRgb, 3).unwrap();
+        assert!(linear[1].r > 0.7);
+    }
+}
@@ -0,0 +1,379 @@
+// Rendering the canvas to SVG and PNG, with the same layers and style as the editor
+use std::fmt::Write;
+
+use iced::{Color, Point, Size};
+
+use crate::curve::{self, curve_to_canvas, CurveAlgorithm, CurveError, CurveKind, Dot, SampleWindow, SplineSettings};
+use crate::fit;
+use crate::style::CanvasStyle;
+
+// Sampled curves are refitted as cubic Béziers this close to the samples, in pixels
+const CURVE_FIT_TOLERANCE: f32 = 0.25;
+
+/// Everything that ends up in the picture.
+pub struct Drawing<'a> {
+    pub dots: &'a [Dot],
+    pub kind: CurveKind,
+    pub straight_mode: bool,
+    pub curve_mode: Option<CurveAlgorithm>,
+    pub spline_settings: SplineSettings,
+    pub style: CanvasStyle,
+}
+
+enum Shape {
+    Line(Point, Point),
+    Polyline(Vec<Point>),
+    Beziers(Vec<[Point; 4]>),
+    Circle(Point, f32),
+    Polygon(Vec<Point>), // Closed
+    Border(Size),
+}
+
+enum Paint {
+    Stroke(Color, f32),
+    Dashed(Color, f32, &'static [f32]), // Dash and gap lengths in pixels
+    Fill(Color),
+}
+
+impl Drawing<'_> {
+    // The canvas as a list of shapes in drawing order, in pixels for a canvas of the given size
+    fn scene(&self, size: Size) -> Vec<(Shape, Paint)> {
+        let style = &self.style;
+        let mut scene = Vec::new();
+
+        let spacing = style.grid_spacing as usize;
+        for x in (0..size.width as usize).step_by(spacing) {
+            let x = x as f32;
+            scene.push((
+                Shape::Line(Point::new(x, 0.0), Point::new(x, size.height)),
+                Paint::Stroke(style.grid_vertical, 1.0),
+            ));
+        }
+        for y in (0..size.height as usize).step_by(spacing) {
+            let y = y as f32;
+            scene.push((
+                Shape::Line(Point::new(0.0, y), Point::new(size.width, y)),
+                Paint::Stroke(style.grid_horizontal, 1.0),
+            ));
+        }
+        scene.push((Shape::Border(size), Paint::Stroke(style.border, style.border_width)));
+
+        for dot in self.dots {
+            let center = curve_to_canvas(dot.position, size);
+            let shape = match style.dot_shape.corners(center, style.dot_radius) {
+                Some(corners) => Shape::Polygon(corners.to_vec()),
+                None => Shape::Circle(center, style.dot_radius),
+            };
+            scene.push((shape, Paint::Fill(style.dot)));
+        }
+
+        let keys = self.kind.keys(self.dots);
+        if self.straight_mode && keys.len() >= 2 {
+            let mut points: Vec<Point> = keys.iter().map(|dot| curve_to_canvas(dot.position, size)).collect();
+            if self.kind == (CurveKind::Path { closed: true }) {
+                points.push(points[0]);
+            }
+            scene.push((Shape::Polyline(points), Paint::Stroke(style.line, style.line_width)));
+        }
+
+        // Control polygons of approximating curves and Bézier handles, as the editor shows them
+        if self.kind == CurveKind::Function && self.curve_mode.is_some_and(CurveAlgorithm::has_control_polygon) {
+            let points = keys.iter().map(|dot| curve_to_canvas(dot.position, size)).collect();
+            scene.push((
+                Shape::Polyline(points),
+                Paint::Dashed(style.polygon, 1.0, style.polygon_dash),
+            ));
+        }
+        if self.kind == CurveKind::Function && self.curve_mode == Some(CurveAlgorithm::Bezier) {
+            for dot in self.dots.iter().map(|dot| dot.to_canvas(size)) {
+                for handle in [dot.in_handle, dot.out_handle].into_iter().flatten() {
+                    let end = dot.position + handle;
+                    scene.push((Shape::Line(dot.position, end), Paint::Stroke(style.polygon, 1.0)));
+                    scene.push((Shape::Circle(end, style.handle_radius), Paint::Fill(style.dot)));
+                }
+            }
+        }
+
+        // A curve that can't be built is left out, as on the canvas
+        if self.curve_mode.is_some() && keys.len() >= 2 {
+            if let Ok(segments) = self.curve_beziers(&keys, size) {
+                scene.push((Shape::Beziers(segments), Paint::Stroke(style.line, style.line_width)));
+            }
+        }
+        scene
+    }
+
+    // Bézier keys are exported as they are, every other curve is refitted from its samples
+    fn curve_beziers(&self, keys: &[Dot], size: Size) -> Result<Vec<[Point; 4]>, CurveError> {
+        if self.kind == CurveKind::Function && self.curve_mode == Some(CurveAlgorithm::Bezier) {
+            let keys: Vec<Dot> = keys.iter().map(|dot| dot.to_canvas(size)).collect();
+            return Ok(keys
+                .windows(2)
+                .map(|pair| curve::bezier_segment(&pair[0], &pair[1]))
+                .collect());
+        }
+        let samples: Vec<Point> = self
+            .kind
+            .sample_in(self.curve_mode, keys, self.spline_settings, SampleWindow::FULL)?
+            .into_iter()
+            .map(|point| curve_to_canvas(point, size))
+            .collect();
+        fit::fit_cubic_beziers(&samples, CURVE_FIT_TOLERANCE)
+    }
+
+    pub fn to_svg(&self, size: Size) -> String {
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = size.width,
+            h = size.height
+        );
+        let _ = writeln!(
+            svg,
+            r#"  <rect width="{}" height="{}" {}/>"#,
+            size.width,
+            size.height,
+            svg_paint(&Paint::Fill(self.style.background))
+        );
+
+        for (shape, paint) in self.scene(size) {
+            let paint = svg_paint(&paint);
+            let _ = match shape {
+                Shape::Line(a, b) => writeln!(
+                    svg,
+                    r#"  <line x1="{}" y1="{}" x2="{}" y2="{}" {paint}/>"#,
+                    a.x, a.y, b.x, b.y
+                ),
+                Shape::Polyline(points) => {
+                    let d: Vec<String> = points
+                        .iter()
+                        .enumerate()
+                        .map(|(i, p)| format!("{} {:.2} {:.2}", if i == 0 { "M" } else { "L" }, p.x, p.y))
+                        .collect();
+                    writeln!(svg, r#"  <path d="{}" {paint}/>"#, d.join(" "))
+                }
+                Shape::Beziers(segments) => {
+                    let mut d = String::new();
+                    if let Some(first) = segments.first() {
+                        let _ = write!(d, "M {:.2} {:.2}", first[0].x, first[0].y);
+                    }
+                    for [_, a, b, end] in &segments {
+                        let _ = write!(
+                            d,
+                            " C {:.2} {:.2} {:.2} {:.2} {:.2} {:.2}",
+                            a.x, a.y, b.x, b.y, end.x, end.y
+                        );
+                    }
+                    writeln!(svg, r#"  <path d="{d}" {paint}/>"#)
+                }
+                Shape::Circle(center, radius) => writeln!(
+                    svg,
+                    r#"  <circle cx="{:.2}" cy="{:.2}" r="{radius}" {paint}/>"#,
+                    center.x, center.y
+                ),
+                Shape::Polygon(points) => {
+                    let points: Vec<String> = points.iter().map(|p| format!("{:.2},{:.2}", p.x, p.y)).collect();
+                    writeln!(svg, r#"  <polygon points="{}" {paint}/>"#, points.join(" "))
+                }
+                Shape::Border(size) => writeln!(
+                    svg,
+                    r#"  <rect width="{}" height="{}" {paint}/>"#,
+                    size.width, size.height
+                ),
+            };
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Rasterizes on the CPU, so no GPU is needed.
+    pub fn to_png(&self, width: u32, height: u32) -> Result<Vec<u8>, String> {
+        let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or("the image size must not be zero")?;
+        pixmap.fill(skia_color(self.style.background));
+
+        for (shape, paint) in self.scene(Size::new(width as f32, height as f32)) {
+            let mut builder = tiny_skia::PathBuilder::new();
+            match shape {
+                Shape::Line(a, b) => {
+                    builder.move_to(a.x, a.y);
+                    builder.line_to(b.x, b.y);
+                }
+                Shape::Polyline(points) => {
+                    for (i, p) in points.iter().enumerate() {
+                        if i == 0 {
+                            builder.move_to(p.x, p.y);
+                        } else {
+                            builder.line_to(p.x, p.y);
+                        }
+                    }
+                }
+                Shape::Beziers(segments) => {
+                    if let Some(first) = segments.first() {
+                        builder.move_to(first[0].x, first[0].y);
+                    }
+                    for [_, a, b, end] in &segments {
+                        builder.cubic_to(a.x, a.y, b.x, b.y, end.x, end.y);
+                    }
+                }
+                Shape::Circle(center, radius) => builder.push_circle(center.x, center.y, radius),
+                Shape::Polygon(points) => {
+                    for (i, p) in points.iter().enumerate() {
+                        if i == 0 {
+                            builder.move_to(p.x, p.y);
+                        } else {
+                            builder.line_to(p.x, p.y);
+                        }
+                    }
+                    builder.close();
+                }
+                Shape::Border(size) => {
+                    if let Some(rect) = tiny_skia::Rect::from_xywh(0.0, 0.0, size.width, size.height) {
+                        builder.push_rect(rect);
+                    }
+                }
+            }
+            let Some(path) = builder.finish() else {
+                continue;
+            };
+
+            let mut skia_paint = tiny_skia::Paint {
+                anti_alias: true,
+                ..Default::default()
+            };
+            match paint {
+                Paint::Stroke(color, width) | Paint::Dashed(color, width, _) => {
+                    skia_paint.set_color(skia_color(color));
+                    let dash = match paint {
+                        Paint::Dashed(_, _, dashes) => tiny_skia::StrokeDash::new(dashes.to_vec(), 0.0),
+                        _ => None,
+                    };
+                    let stroke = tiny_skia::Stroke {
+                        width,
+                        dash,
+                        ..Default::default()
+                    };
+                    pixmap.stroke_path(&path, &skia_paint, &stroke, tiny_skia::Transform::identity(), None);
+                }
+                Paint::Fill(color) => {
+                    skia_paint.set_color(skia_color(color));
+                    pixmap.fill_path(
+                        &path,
+                        &skia_paint,
+                        tiny_skia::FillRule::Winding,
+                        tiny_skia::Transform::identity(),
+                        None,
+                    );
+                }
+            }
+        }
+
+        pixmap.encode_png().map_err(|error| error.to_string())
+    }
+}
+
+fn svg_paint(paint: &Paint) -> String {
+    let hex = |color: &Color| {
+        let [r, g, b, _] = color.into_rgba8();
+        format!("#{r:02x}{g:02x}{b:02x}")
+    };
+    match paint {
+        Paint::Stroke(color, width) => format!(
+            r#"fill="none" stroke="{}" stroke-opacity="{}" stroke-width="{width}""#,
+            hex(color),
+            color.a
+        ),
+        Paint::Dashed(color, width, dashes) => {
+            let dashes: Vec<String> = dashes.iter().map(f32::to_string).collect();
+            format!(
+                r#"{} stroke-dasharray="{}""#,
+                svg_paint(&Paint::Stroke(*color, *width)),
+                dashes.join(" ")
+            )
+        }
+        Paint::Fill(color) => format!(r#"fill="{}" fill-opacity="{}""#, hex(color), color.a),
+    }
+}
+
+fn skia_color(color: Color) -> tiny_skia::Color {
+    tiny_skia::Color::from_rgba(color.r, color.g, color.b, color.a).unwrap_or(tiny_skia::Color::BLACK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::DotShape;
+
+    fn dots(points: &[(f32, f32)]) -> Vec<Dot> {
+        points.iter().map(|&(x, y)| Dot::new(Point::new(x, y))).collect()
+    }
+
+    fn drawing(dots: &[Dot], curve_mode: Option<CurveAlgorithm>) -> Drawing<'_> {
+        Drawing {
+            dots,
+            kind: CurveKind::Function,
+            straight_mode: false,
+            curve_mode,
+            spline_settings: SplineSettings::default(),
+            style: CanvasStyle::from_theme(&crate::style::EDITOR_THEME),
+        }
+    }
+
+    #[test]
+    fn svg_has_the_grid_keys_and_curve() {
+        let dots = dots(&[(0.1, 0.2), (0.5, 0.9), (0.8, 0.4)]);
+        let svg = drawing(&dots, Some(CurveAlgorithm::Pchip)).to_svg(Size::new(400.0, 200.0));
+        // Every 50 pixels across and down
+        assert_eq!(svg.matches("<line ").count(), 8 + 4);
+        assert_eq!(svg.matches("<circle ").count(), 3);
+        let curve = svg.lines().find(|line| line.contains("<path ")).unwrap();
+        assert!(curve.contains(r#"d="M 0.00 160.00 C "#), "{curve}");
+    }
+
+    #[test]
+    fn control_polygons_and_handles_are_drawn() {
+        let dots = dots(&[(0.1, 0.2), (0.5, 0.9), (0.8, 0.4)]);
+        let svg = drawing(&dots, Some(CurveAlgorithm::BSpline)).to_svg(Size::new(400.0, 200.0));
+        assert_eq!(svg.matches("stroke-dasharray").count(), 1);
+
+        let mut dots = dots;
+        dots[1].in_handle = Some(iced::Vector::new(-0.1, 0.0));
+        dots[1].out_handle = Some(iced::Vector::new(0.1, 0.0));
+        let svg = drawing(&dots, Some(CurveAlgorithm::Bezier)).to_svg(Size::new(400.0, 200.0));
+        assert_eq!(svg.matches("<line ").count(), 12 + 2);
+        assert_eq!(svg.matches("<circle ").count(), 3 + 2);
+    }
+
+    #[test]
+    fn a_curve_that_cannot_be_built_is_left_out() {
+        let dots = dots(&[(0.5, 0.2), (0.5, 0.9)]);
+        let svg = drawing(&dots, Some(CurveAlgorithm::Pchip)).to_svg(Size::new(400.0, 200.0));
+        assert_eq!(svg.matches("<circle ").count(), 2);
+        assert!(!svg.contains("<path "));
+    }
+
+    #[test]
+    fn keys_take_the_dot_shape() {
+        let dots = dots(&[(0.1, 0.2), (0.5, 0.9), (0.8, 0.4)]);
+        let mut drawing = drawing(&dots, None);
+        drawing.style.dot_shape = DotShape::Diamond;
+        let svg = drawing.to_svg(Size::new(400.0, 200.0));
+        assert_eq!(svg.matches("<circle ").count(), 0);
+        // The first key, at (40, 160), reaching 5 pixels up, right, down and left
+        assert!(
+            svg.contains(r#"<polygon points="40.00,155.00 45.00,160.00 40.00,165.00 35.00,160.00""#),
+            "{svg}"
+        );
+    }
+
+    #[test]
+    fn png_has_the_requested_size() {
+        let dots = dots(&[(0.1, 0.2), (0.5, 0.9), (0.8, 0.4)]);
+        let png = drawing(&dots, Some(CurveAlgorithm::CatmullRom))
+            .to_png(320, 180)
+            .unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!((image.width(), image.height()), (320, 180));
+        assert!(drawing(&dots, None).to_png(0, 180).is_err());
+    }
+}
@@ -0,0 +1,424 @@
+// Reading curve data produced by other tools
+use std::fmt;
+
+use iced::{Point, Vector};
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(std::io::Error),
+    InvalidLine(usize), // 1-based line number of a data row that isn't two numbers
+    TooFewSamples,
+    InvalidPathData(usize), // Byte offset in SVG path data where a command or number was expected
+    UnsupportedPathCommand(char),
+    MissingMoveTo,
+    MultipleSubpaths,
+    EmptyPath,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(error) => write!(f, "could not read file: {error}"),
+            ImportError::InvalidLine(line) => write!(f, "line {line} is not an x, y pair"),
+            ImportError::TooFewSamples => write!(f, "at least two samples are needed"),
+            ImportError::InvalidPathData(offset) => write!(f, "invalid path data at character {offset}"),
+            ImportError::UnsupportedPathCommand('A' | 'a') => {
+                write!(f, "arcs (A) are not supported, convert them to curves first")
+            }
+            ImportError::UnsupportedPathCommand(command) => write!(f, "unsupported path command {command}"),
+            ImportError::MissingMoveTo => write!(f, "the path must start with M"),
+            ImportError::MultipleSubpaths => write!(f, "the path must be a single subpath"),
+            ImportError::EmptyPath => write!(f, "the path has no segments"),
+        }
+    }
+}
+
+impl From<std::io::Error> for ImportError {
+    fn from(error: std::io::Error) -> Self {
+        ImportError::Io(error)
+    }
+}
+
+/// Parses `x, y` rows separated by commas, semicolons, tabs or spaces. Blank lines, `#` comments
+/// and a header on the first line are skipped. Samples are returned sorted by x.
+pub fn parse_csv_samples(text: &str) -> Result<Vec<Point>, ImportError> {
+    let mut samples = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line
+            .split([',', ';', '\t', ' '])
+            .filter(|field| !field.is_empty())
+            .map(str::parse::<f32>);
+        match (fields.next(), fields.next()) {
+            (Some(Ok(x)), Some(Ok(y))) if x.is_finite() && y.is_finite() => {
+                samples.push(Point::new(x, y))
+            }
+            _ if index == 0 => continue, // Column names
+            _ => return Err(ImportError::InvalidLine(index + 1)),
+        }
+    }
+
+    if samples.len() < 2 {
+        return Err(ImportError::TooFewSamples);
+    }
+    samples.sort_by(|a, b| a.x.total_cmp(&b.x));
+    Ok(samples)
+}
+
+pub fn read_csv_samples(path: &str) -> Result<Vec<Point>, ImportError> {
+    parse_csv_samples(&std::fs::read_to_string(path)?)
+}
+
+/// Parses SVG path data (the `d` attribute) into cubic Bézier segments in SVG coordinates, so y
+/// points down. Supports `M`, `L`, `H`, `V`, `C`, `S`, `Q`, `T` and `Z` in absolute and relative
+/// form. Lines become cubics with handles at thirds and quadratics are elevated exactly.
+pub fn parse_svg_path(d: &str) -> Result<Vec<[Point; 4]>, ImportError> {
+    let mut lexer = PathLexer { data: d, offset: 0 };
+    let mut segments: Vec<[Point; 4]> = Vec::new();
+    let mut current = Point::ORIGIN;
+    let mut subpath_start = Point::ORIGIN;
+    let mut started = false;
+    // Control point reflected by S (cubic) or T (quadratic), only after a command of the same kind
+    let mut last_cubic_control: Option<Point> = None;
+    let mut last_quadratic_control: Option<Point> = None;
+    let mut command = None;
+
+    while let Some(next) = lexer.next_command(command)? {
+        command = Some(next);
+        let relative = next.is_ascii_lowercase();
+        let origin = if relative { current } else { Point::ORIGIN };
+        let point = |lexer: &mut PathLexer| -> Result<Point, ImportError> {
+            Ok(Point::new(origin.x + lexer.number()?, origin.y + lexer.number()?))
+        };
+
+        if !started && !matches!(next, 'M' | 'm') {
+            return Err(ImportError::MissingMoveTo);
+        }
+        let (mut cubic_control, mut quadratic_control) = (None, None);
+        match next.to_ascii_uppercase() {
+            'M' => {
+                if started {
+                    return Err(ImportError::MultipleSubpaths);
+                }
+                current = point(&mut lexer)?;
+                subpath_start = current;
+                started = true;
+                // Further coordinate pairs after a moveto are implicit linetos
+                command = Some(if relative { 'l' } else { 'L' });
+            }
+            'L' | 'H' | 'V' | 'Z' => {
+                let end = match next.to_ascii_uppercase() {
+                    'L' => point(&mut lexer)?,
+                    'H' => Point::new(origin.x + lexer.number()?, current.y),
+                    'V' => Point::new(current.x, origin.y + lexer.number()?),
+                    _ => subpath_start,
+                };
+                let chord = end - current;
+                segments.push([current, current + chord * (1.0 / 3.0), current + chord * (2.0 / 3.0), end]);
+                current = end;
+            }
+            'C' | 'S' => {
+                let control_a = if next.eq_ignore_ascii_case(&'C') {
+                    point(&mut lexer)?
+                } else {
+                    reflect(last_cubic_control, current)
+                };
+                let control_b = point(&mut lexer)?;
+                let end = point(&mut lexer)?;
+                segments.push([current, control_a, control_b, end]);
+                cubic_control = Some(control_b);
+                current = end;
+            }
+            'Q' | 'T' => {
+                let control = if next.eq_ignore_ascii_case(&'Q') {
+                    point(&mut lexer)?
+                } else {
+                    reflect(last_quadratic_control, current)
+                };
+                let end = point(&mut lexer)?;
+                segments.push([
+                    current,
+                    current + (control - current) * (2.0 / 3.0),
+                    end + (control - end) * (2.0 / 3.0),
+                    end,
+                ]);
+                quadratic_control = Some(control);
+                current = end;
+            }
+            _ => return Err(ImportError::UnsupportedPathCommand(next)),
+        }
+        last_cubic_control = cubic_control;
+        last_quadratic_control = quadratic_control;
+    }
+
+    if segments.is_empty() {
+        return Err(ImportError::EmptyPath);
+    }
+    Ok(segments)
+}
+
+// Mirror image of the previous control point around the current point, or the current point itself
+fn reflect(control: Option<Point>, current: Point) -> Point {
+    control.map_or(current, |control| current + (current - control))
+}
+
+struct PathLexer<'a> {
+    data: &'a str,
+    offset: usize,
+}
+
+impl PathLexer<'_> {
+    fn rest(&self) -> &str {
+        &self.data[self.offset..]
+    }
+
+    fn skip_separators(&mut self) {
+        let rest = self.rest();
+        self.offset += rest.len() - rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',').len();
+    }
+
+    // The next command letter, or the previous command repeated when more numbers follow it
+    fn next_command(&mut self, previous: Option<char>) -> Result<Option<char>, ImportError> {
+        self.skip_separators();
+        match self.rest().chars().next() {
+            None => Ok(None),
+            Some(c) if c.is_ascii_alphabetic() && !matches!(c, 'e' | 'E') => {
+                self.offset += 1;
+                Ok(Some(c))
+            }
+            Some(_) => match previous {
+                Some(command) if !matches!(command, 'Z' | 'z') => Ok(Some(command)),
+                _ => Err(ImportError::InvalidPathData(self.offset)),
+            },
+        }
+    }
+
+    // Numbers may run together, as in "1.5.5" or "10-20"
+    fn number(&mut self) -> Result<f32, ImportError> {
+        self.skip_separators();
+        let bytes = self.rest().as_bytes();
+        let mut end = 0;
+        if matches!(bytes.first(), Some(b'+' | b'-')) {
+            end += 1;
+        }
+        let mut seen_dot = false;
+        while let Some(&b) = bytes.get(end) {
+            match b {
+                b'0'..=b'9' => end += 1,
+                b'.' if !seen_dot => {
+                    seen_dot = true;
+                    end += 1;
+                }
+                _ => break,
+            }
+        }
+        if matches!(bytes.get(end), Some(b'e' | b'E')) {
+            let mut exponent_end = end + 1;
+            if matches!(bytes.get(exponent_end), Some(b'+' | b'-')) {
+                exponent_end += 1;
+            }
+            if bytes.get(exponent_end).is_some_and(u8::is_ascii_digit) {
+                end = exponent_end;
+                while bytes.get(end).is_some_and(u8::is_ascii_digit) {
+                    end += 1;
+                }
+            }
+        }
+
+        match self.rest()[..end].parse::<f32>() {
+            Ok(value) if value.is_finite() => {
+                self.offset += end;
+                Ok(value)
+            }
+            _ => Err(ImportError::InvalidPathData(self.offset)),
+        }
+    }
+}
+
+// Imported data is fitted into the unit square with this much room left on every side
+pub const IMPORT_MARGIN: f32 = 0.05;
+
+/// Scales Bézier segments so their control points fill the unit square less the import margin.
+/// `flip_y` is for sources where y points down, such as SVG.
+pub fn fit_into_unit_square(segments: &[[Point; 4]], flip_y: bool) -> Vec<[Point; 4]> {
+    let (min, max) = segments.iter().flatten().fold(
+        (Point::new(f32::MAX, f32::MAX), Point::new(f32::MIN, f32::MIN)),
+        |(min, max), p| {
+            (
+                Point::new(min.x.min(p.x), min.y.min(p.y)),
+                Point::new(max.x.max(p.x), max.y.max(p.y)),
+            )
+        },
+    );
+    let scale = Vector::new(
+        (1.0 - 2.0 * IMPORT_MARGIN) / (max.x - min.x).max(f32::EPSILON),
+        (1.0 - 2.0 * IMPORT_MARGIN) / (max.y - min.y).max(f32::EPSILON),
+    );
+    let to_curve = |p: Point| {
+        let y = if flip_y { max.y - p.y } else { p.y - min.y };
+        Point::new(IMPORT_MARGIN + (p.x - min.x) * scale.x, IMPORT_MARGIN + y * scale.y)
+    };
+    segments.iter().map(|segment| segment.map(to_curve)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve;
+
+    fn p(x: f32, y: f32) -> Point {
+        Point::new(x, y)
+    }
+
+    fn line(a: Point, b: Point) -> [Point; 4] {
+        let chord = b - a;
+        [a, a + chord * (1.0 / 3.0), a + chord * (2.0 / 3.0), b]
+    }
+
+    fn parses_to(d: &str, expected: &[[Point; 4]]) {
+        let segments = parse_svg_path(d).unwrap_or_else(|error| panic!("{d:?}: {error}"));
+        assert_eq!(segments.len(), expected.len(), "{d:?}");
+        for (segment, expected) in segments.iter().zip(expected) {
+            for (a, b) in segment.iter().zip(expected) {
+                assert!(
+                    (*a - *b).x.abs() < 1e-5 && (*a - *b).y.abs() < 1e-5,
+                    "{d:?}: {segment:?} != {expected:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn relative_commands_start_from_the_current_point() {
+        let expected = [
+            line(p(10.0, 10.0), p(20.0, 10.0)),
+            line(p(20.0, 10.0), p(20.0, 30.0)),
+            [p(20.0, 30.0), p(25.0, 30.0), p(30.0, 35.0), p(30.0, 40.0)],
+            line(p(30.0, 40.0), p(10.0, 10.0)),
+        ];
+        parses_to("M 10 10 L 20 10 V 30 C 25 30 30 35 30 40 Z", &expected);
+        parses_to("m 10 10 l 10 0 v 20 c 5 0 10 5 10 10 z", &expected);
+        parses_to("M10,10 H20 v20 c5,0 10,5 10,10 L10,10", &expected);
+    }
+
+    #[test]
+    fn numbers_after_a_command_repeat_it() {
+        parses_to(
+            "M 0 0 L 1 0 2 0 h 1 1",
+            &[
+                line(p(0.0, 0.0), p(1.0, 0.0)),
+                line(p(1.0, 0.0), p(2.0, 0.0)),
+                line(p(2.0, 0.0), p(3.0, 0.0)),
+                line(p(3.0, 0.0), p(4.0, 0.0)),
+            ],
+        );
+        // Pairs after a moveto are linetos, relative after a relative moveto
+        parses_to(
+            "M 1 1 2 2 3 3",
+            &[line(p(1.0, 1.0), p(2.0, 2.0)), line(p(2.0, 2.0), p(3.0, 3.0))],
+        );
+        parses_to(
+            "m 1 1 1 1 1 1",
+            &[line(p(1.0, 1.0), p(2.0, 2.0)), line(p(2.0, 2.0), p(3.0, 3.0))],
+        );
+    }
+
+    #[test]
+    fn smooth_commands_reflect_the_previous_control_point() {
+        parses_to(
+            "M 0 0 C 0 1 1 1 1 0 S 2 -1 2 0",
+            &[
+                [p(0.0, 0.0), p(0.0, 1.0), p(1.0, 1.0), p(1.0, 0.0)],
+                [p(1.0, 0.0), p(1.0, -1.0), p(2.0, -1.0), p(2.0, 0.0)],
+            ],
+        );
+        // Quadratics are elevated, so the reflected control point is the quadratic one
+        parses_to(
+            "M 0 0 Q 3 3 6 0 T 12 0",
+            &[
+                [p(0.0, 0.0), p(2.0, 2.0), p(4.0, 2.0), p(6.0, 0.0)],
+                [p(6.0, 0.0), p(8.0, -2.0), p(10.0, -2.0), p(12.0, 0.0)],
+            ],
+        );
+    }
+
+    #[test]
+    fn smooth_commands_after_other_kinds_start_at_the_current_point() {
+        // After a line, or after a curve of the other kind, there's nothing to reflect
+        parses_to(
+            "M 0 0 L 3 0 S 4 1 6 0",
+            &[
+                line(p(0.0, 0.0), p(3.0, 0.0)),
+                [p(3.0, 0.0), p(3.0, 0.0), p(4.0, 1.0), p(6.0, 0.0)],
+            ],
+        );
+        parses_to(
+            "M 0 0 Q 3 3 6 0 S 7 1 9 0",
+            &[
+                [p(0.0, 0.0), p(2.0, 2.0), p(4.0, 2.0), p(6.0, 0.0)],
+                [p(6.0, 0.0), p(6.0, 0.0), p(7.0, 1.0), p(9.0, 0.0)],
+            ],
+        );
+        parses_to(
+            "M 0 0 C 1 1 2 1 3 0 T 6 0",
+            &[
+                [p(0.0, 0.0), p(1.0, 1.0), p(2.0, 1.0), p(3.0, 0.0)],
+                [p(3.0, 0.0), p(3.0, 0.0), p(4.0, 0.0), p(6.0, 0.0)],
+            ],
+        );
+    }
+
+    #[test]
+    fn numbers_may_run_together() {
+        parses_to("M1.5.5L10-20", &[line(p(1.5, 0.5), p(10.0, -20.0))]);
+        parses_to("M-1-1l+2,.5", &[line(p(-1.0, -1.0), p(1.0, -0.5))]);
+        parses_to("M1e1 2E-1L1.5e+1-3e0", &[line(p(10.0, 0.2), p(15.0, -3.0))]);
+    }
+
+    #[test]
+    fn unsupported_and_malformed_paths_are_errors() {
+        assert!(matches!(
+            parse_svg_path("M 0 0 A 5 5 0 0 1 10 0"),
+            Err(ImportError::UnsupportedPathCommand('A'))
+        ));
+        assert!(matches!(
+            parse_svg_path("M 0 0 a 5 5 0 0 1 10 0"),
+            Err(ImportError::UnsupportedPathCommand('a'))
+        ));
+        assert!(matches!(parse_svg_path("L 1 1"), Err(ImportError::MissingMoveTo)));
+        assert!(matches!(parse_svg_path("10 10"), Err(ImportError::InvalidPathData(0))));
+        assert!(matches!(
+            parse_svg_path("M 0 0 L 1 1 M 2 2 L 3 3"),
+            Err(ImportError::MultipleSubpaths)
+        ));
+        assert!(matches!(parse_svg_path("M 0 0"), Err(ImportError::EmptyPath)));
+        assert!(matches!(parse_svg_path(""), Err(ImportError::EmptyPath)));
+        assert!(matches!(
+            parse_svg_path("M 0 0 L 1"),
+            Err(ImportError::InvalidPathData(9))
+        ));
+        assert!(matches!(
+            parse_svg_path("M 0 0 Z 1 1"),
+            Err(ImportError::InvalidPathData(8))
+        ));
+    }
+
+    #[test]
+    fn segments_survive_the_round_trip_through_keys() {
+        let segments = parse_svg_path("M 0 0 C 0 1 1 1 1 0 S 2 -1 2 0 L 3 0 Q 4 1 5 0").unwrap();
+        let dots = curve::dots_from_beziers(&segments);
+        assert_eq!(dots.len(), segments.len() + 1);
+        let rebuilt: Vec<[Point; 4]> = dots
+            .windows(2)
+            .map(|pair| curve::bezier_segment(&pair[0], &pair[1]))
+            .collect();
+        for (a, b) in rebuilt.iter().flatten().zip(segments.iter().flatten()) {
+            assert!((*a - *b).x.abs() < 1e-5 && (*a - *b).y.abs() < 1e-5);
+        }
+    }
+}
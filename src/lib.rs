@@ -0,0 +1,24 @@
+// Curve engine behind the editor: algorithms, import, editing operations and export.
+// Everything here works without a window, so it can be used from build scripts and tests.
+pub mod bake;
+pub mod bounds;
+pub mod bspline;
+pub mod derivative;
+pub mod difference;
+pub mod curve;
+pub mod document;
+pub mod easing;
+pub mod export;
+pub mod fit;
+pub mod gradient;
+pub mod hermite;
+pub mod image_export;
+pub mod import;
+pub mod path;
+pub mod preview;
+pub mod query;
+pub mod simplify;
+pub mod spatial;
+pub mod style;
+pub mod timeline;
+pub mod tone;
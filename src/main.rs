@@ -1,8 +1,28 @@
 // IMPLEMENTS ALGORITHM MANUALLY
-use iced::widget::canvas::{Path, Stroke};
-use iced::widget::{button, canvas, container, horizontal_space, hover, Canvas};
-use iced::{mouse, Element, Fill, Point, Rectangle, Theme};
-use splines::{Interpolation, Key, Spline};
+use iced::widget::canvas::{LineDash, Path, Stroke};
+use iced::widget::{button, canvas, column, container, hover, image, pick_list, row, slider, text, text_input, Canvas};
+use iced_curve_editor::bake::{self, BakeSpacing, LookupTable};
+use iced_curve_editor::bounds::{self, BoundsPolicy, ValueBounds};
+use iced_curve_editor::bspline::KnotVector;
+use iced_curve_editor::derivative;
+use iced_curve_editor::difference::{self, CurveDifference};
+use iced_curve_editor::document::{self, CurveDocument};
+use iced_curve_editor::easing::{CssEasing, Preset};
+use iced_curve_editor::curve::{
+    self, canvas_to_curve, curve_to_canvas, CurveAlgorithm, CurveError, CurveKind, Dot, SampleCache,
+    SampleWindow, SplineSettings,
+};
+use iced_curve_editor::export::{self, ExportFormat, ExportSettings};
+use iced_curve_editor::gradient::{self, ColorSpace, GradientStrip};
+use iced_curve_editor::image_export::Drawing;
+use iced_curve_editor::preview::{Playback, Preview, PreviewObject};
+use iced_curve_editor::spatial::DotIndex;
+use iced_curve_editor::style::{self, CanvasStyle, DotShape, Legend};
+use iced_curve_editor::timeline::Timeline;
+use iced_curve_editor::tone::{self, Histogram, ToneChannel, ToneCurves};
+use ::image::RgbaImage;
+use iced_curve_editor::{fit, import, path, simplify};
+use iced::{mouse, window, Color, Element, Fill, Point, Rectangle, Size, Subscription, Task, Theme, Vector};
 
 pub fn main() -> iced::Result {
     // Entry point of the application. This initializes and runs the application.
@@ -12,8 +32,16 @@ pub fn main() -> iced::Result {
         ExampleCanvas::update,
         ExampleCanvas::view,
     )
-    .theme(|_| Theme::CatppuccinMocha)
-    .run() // Start the application.
+    .subscription(ExampleCanvas::subscription)
+    .theme(ExampleCanvas::theme)
+    // Start the application with the theme picked last time
+    .run_with(|| {
+        let editor = ExampleCanvas {
+            theme: style::load_theme(),
+            ..ExampleCanvas::default()
+        };
+        (editor, Task::none())
+    })
 }
 
 #[derive(Default)]
@@ -23,30 +51,290 @@ struct ExampleCanvas {
     straight_mode: bool,
     curve_mode: Option<CurveAlgorithm>,
     delete_mode: bool,
+    function_mode: bool, // Keep keys sorted and x-unique so the curve is a function of x
+    curve_kind: CurveKind,
+    spline_settings: SplineSettings,
+    document_path: String,
+    csv_path: String,
+    svg_path: String, // Path data as in the d attribute, not a file
+    css_easing: String,
+    fit_tolerance: String, // Kept as typed so the input can hold partial numbers like "0."
+    simplify_tolerance: String,
+    simplified_from: Option<Vec<Dot>>, // Dots before the last simplify, drawn as an overlay until the next edit
+    bake_spacing: String,
+    lookup_table: Option<LookupTable>,
+    export: ExportDialog,
+    status: Option<String>,
+    curve_error: Option<CurveError>, // Why the current curve can't be drawn, if it can't
+    curve_samples: SampleCache,
+    simplified_samples: SampleCache, // Samples of simplified_from
+    canvas_size: Option<Size>,       // Curves are sampled for this width once the canvas reports it
+    preview_open: bool,
+    preview_object: PreviewObject,
+    preview_duration: String, // Seconds, kept as typed
+    playback: Playback,
+    timeline: Option<Timeline>, // Some when the x axis reads as frames
+    timeline_fps: String,
+    timeline_range: String,
+    gradient_mode: bool, // Keys carry colors, shown as a gradient strip under the canvas
+    color_space: ColorSpace,
+    gradient: Vec<Color>, // Sampled gradient, empty when it can't be built
+    selected: Option<usize>, // Last dot pressed, whose color the gradient panel edits
+    key_color: String,       // Hex color as typed
+    gradient_path: String,
+    tone_mode: bool, // Grading an image, with a curve per color channel
+    tone_channel: ToneChannel,
+    tone_keys: [Vec<Dot>; 4], // Keys of the channels not being edited, by ToneChannel::index
+    tone_curves: ToneCurves,
+    tone_image: Option<ToneImage>,
+    image_path: String,
+    graded_path: String, // Where the adjusted image or LUT is exported
+    derivatives: DerivativeOverlays,
+    bounds: Option<ValueBounds>, // Range the curve's values must stay in, if limited
+    bounds_range: String,        // "min..max" as typed
+    reference: Option<CurveDocument>, // Frozen copy of the curve, drawn as a ghost to compare edits against
+    reference_samples: SampleCache,
+    reference_difference: Option<CurveDifference>, // Current curve against the reference, for function curves
+    compare_mode: bool,                // Every algorithm drawn over the same keys, with a legend
+    compare_samples: Vec<SampleCache>, // By CurveAlgorithm::index, filled in compare mode
+    theme: Theme, // Everything on the canvas takes its colors from it, saved when changed
+}
+
+// Smoothness overlays, each toggled on its own
+#[derive(Debug, Clone, Copy, Default)]
+struct DerivativeOverlays {
+    slope: bool,  // dy/dx on a secondary axis
+    comb: bool,   // Curvature comb along the curve
+    breaks: bool, // Markers where the second derivative jumps
 }
 
-#[derive(Debug, Clone, Copy)]
-enum CurveAlgorithm {
-    CatmullRom,
-    MonotonicSpline,
-    NaturalCubicSpline,
+// An image opened for grading, with what's derived from it once
+struct ToneImage {
+    full: RgbaImage,
+    thumbnail: RgbaImage,
+    histogram: Histogram,
+    preview: image::Handle, // The thumbnail with the curves applied
 }
 
-#[derive(Debug, Clone, Copy)]
+const MAX_SPLINE_DEGREE: usize = 5;
+const MIN_WEIGHT: f32 = 0.1;
+const MAX_WEIGHT: f32 = 10.0;
+// Fields of the export panel, kept as typed until "Export" is pressed
+#[derive(Default)]
+struct ExportDialog {
+    open: bool,
+    format: ExportFormat,
+    resolution: String,
+    start: String,
+    end: String,
+    path: String,
+    from_table: bool, // Export the baked lookup table instead of sampling the curve
+    image_width: String,
+    image_height: String,
+}
+
+const DEFAULT_IMAGE_SIZE: (u32, u32) = (1024, 768);
+
+// Pixels from a path segment within which a new key is inserted into it rather than appended
+const INSERT_REACH: f32 = 8.0;
+
+// Resolution of the gradient strip and exported gradient LUTs
+const GRADIENT_SAMPLES: usize = 256;
+
+// Longest side of the graded preview, small enough to regrade on every edit
+const TONE_PREVIEW_SIZE: u32 = 256;
+// Entries per side of exported 3D LUTs, a common size for grading tools
+const TONE_LUT_SIZE: usize = 33;
+
+fn image_handle(rgba: &RgbaImage) -> image::Handle {
+    image::Handle::from_rgba(rgba.width(), rgba.height(), rgba.as_raw().clone())
+}
+
+impl ExportDialog {
+    // Empty fields fall back to the defaults shown as placeholders
+    fn settings(&self) -> Result<ExportSettings, String> {
+        let defaults = ExportSettings::default();
+        let parse = |field: &str, default: f32| {
+            if field.trim().is_empty() {
+                Ok(default)
+            } else {
+                field.trim().parse::<f32>().map_err(|_| format!("\"{field}\" is not a number"))
+            }
+        };
+        let resolution = if self.resolution.trim().is_empty() {
+            defaults.resolution
+        } else {
+            self.resolution
+                .trim()
+                .parse()
+                .map_err(|_| format!("\"{}\" is not a whole number", self.resolution))?
+        };
+        let settings = ExportSettings {
+            resolution,
+            start: parse(&self.start, defaults.start)?,
+            end: parse(&self.end, defaults.end)?,
+        };
+
+        if settings.resolution < 2 {
+            return Err("the resolution must be at least 2".into());
+        }
+        let in_range = |x: f32| (0.0..=1.0).contains(&x);
+        if !in_range(settings.start) || !in_range(settings.end) || settings.start >= settings.end {
+            return Err("the range must satisfy 0 <= start < end <= 1".into());
+        }
+        Ok(settings)
+    }
+
+    // Pixel size for SVG and PNG export, empty fields fall back to the default
+    fn image_size(&self) -> Result<(u32, u32), String> {
+        let parse = |field: &str, default: u32| {
+            if field.trim().is_empty() {
+                return Ok(default);
+            }
+            match field.trim().parse::<u32>() {
+                Ok(pixels) if (1..=16384).contains(&pixels) => Ok(pixels),
+                _ => Err(format!("\"{field}\" is not an image size between 1 and 16384")),
+            }
+        };
+        Ok((
+            parse(&self.image_width, DEFAULT_IMAGE_SIZE.0)?,
+            parse(&self.image_height, DEFAULT_IMAGE_SIZE.1)?,
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
 enum Message {
     AddDot(Dot), // Message to add a new point.
+    PathMode,    // Cycle between a function curve and open and closed paths
+    MoveKeyBack, // Swap the selected key with the one before it in the path
+    MoveKeyForward,
+    ToggleSlope,
+    ToggleComb,
+    ToggleBreaks,
+    ToggleBounds,
+    BoundsRangeChanged(String),
+    BoundsPolicySelected(BoundsPolicy),
+    SetReference, // Freeze a copy of the curve to compare against
+    ClearReference,
+    CompareMode,
+    CompareSelected(CurveAlgorithm), // Picked from the compare legend
+    ThemeSelected(Theme),
+    DotShapeSelected(DotShape),
     Clear,       // Message to clear all points.
     Straight,    // Toggle straight line connector mode on and off.
     Curve, // Toggle curve line connector mode between catmull rom splines and off, can add more in future if needed
     DeleteMode,
-    DeleteDot(Point), //not Dot for easier proximity matching
+    DeleteDot(usize),        // Index of the dot under the cursor, hit tested by the canvas
+    FunctionMode,            // Toggle keeping the curve a function of x
+    MoveDot(usize, Point),   // Drag a dot to a curve space position
+    CanvasResized(Size),
+    Degree,                  // Cycle the B-spline/NURBS degree
+    Knots,                   // Toggle between uniform and clamped knot vectors
+    ScaleWeight(usize, f32), // Multiply the NURBS weight of a dot
+    DocumentPathChanged(String),
+    OpenDocument,
+    SaveDocument,
+    CsvPathChanged(String),
+    FitToleranceChanged(String),
+    FitCsv, // Fit Bézier keys to the samples in the CSV file
+    SvgPathChanged(String),
+    ImportSvgPath,
+    SimplifyToleranceChanged(String),
+    Simplify, // Remove keys while the curve stays within the tolerance
+    BakeSpacingChanged(String),
+    BakeToKeys,  // Replace the dots with linear keys sampled from the curve
+    BakeToTable, // Sample the curve into a lookup table, leaving the dots alone
+    DiscardTable,
+    ToggleExport, // Show or hide the export panel
+    TogglePreview,
+    PlayPause,
+    ToggleLoop,
+    PreviewDurationChanged(String),
+    PreviewObjectSelected(PreviewObject),
+    Tick(std::time::Instant), // Frame timestamp while the preview plays
+    ToggleTimeline,
+    TimelineFpsChanged(String),
+    TimelineRangeChanged(String),
+    SnapKeys,   // Move every key to its nearest frame
+    Scrub(f32), // Move the current time to a curve space x
+    SelectDot(usize),
+    GradientMode,
+    ColorSpaceSelected(ColorSpace),
+    KeyColorChanged(String),
+    KeyChannelChanged(usize, f32), // sRGB channel index and value of the selected key's color
+    GradientPathChanged(String),
+    ExportGradient,
+    ToneMode,
+    ToneChannelSelected(ToneChannel),
+    ImagePathChanged(String),
+    OpenImage,
+    GradedPathChanged(String),
+    ExportGradedImage,
+    ExportToneLut,
+    ExportFormatSelected(ExportFormat),
+    ExportResolutionChanged(String),
+    ExportStartChanged(String),
+    ExportEndChanged(String),
+    ExportPathChanged(String),
+    ExportSource, // Toggle between exporting the curve and the lookup table
+    Export,
+    ImageWidthChanged(String),
+    ImageHeightChanged(String),
+    ExportSvg, // Render the canvas to an SVG file
+    ExportPng, // Rasterize the canvas to a PNG file
+    PresetSelected(Preset),
+    CssEasingChanged(String),
+    ApplyCss, // Load the typed CSS timing function as keys
+    CopyCss,  // Write the curve to the clipboard as a CSS timing function
 }
 
 impl ExampleCanvas {
-    fn update(&mut self, message: Message) {
+    fn update(&mut self, message: Message) -> Task<Message> {
+        // A drag keeps the key between its neighbours, so the dots stay sorted without a full pass
+        let keeps_order = matches!(message, Message::MoveDot(..));
         match message {
-            Message::AddDot(dot) => {
-                self.dots.push(dot);
+            Message::AddDot(mut dot) => {
+                if let Some(timeline) = self.timeline {
+                    dot.position.x = timeline.snap(dot.position.x);
+                }
+                if self.gradient_mode && dot.color.is_none() {
+                    // New keys take the color the gradient already has there, so adding one changes nothing
+                    let last = self.gradient.len().saturating_sub(1) as f32;
+                    dot.color = self.gradient.get((dot.position.x.clamp(0.0, 1.0) * last).round() as usize).copied();
+                }
+                if self.function_mode
+                    && self
+                        .dots
+                        .iter()
+                        .any(|other| (other.position.x - dot.position.x).abs() < curve::MIN_KEY_SPACING)
+                {
+                    self.status = Some(format!("A key already exists at x = {:.3}", dot.position.x));
+                    return Task::none();
+                }
+                if let Some(bounds) = self.bounds {
+                    if bounds.policy == BoundsPolicy::Reject && !bounds.contains(dot.position.y) {
+                        self.status = Some(format!(
+                            "y = {:.3} is outside the bounds {}..{}",
+                            dot.position.y, bounds.min, bounds.max
+                        ));
+                        return Task::none();
+                    }
+                    bounds.clamp_keys(std::slice::from_mut(&mut dot));
+                }
+                if let CurveKind::Path { closed } = self.curve_kind {
+                    // Placed on a segment, the key goes between that segment's keys
+                    let reach = self.canvas_size.map_or(Vector::new(0.02, 0.02), |size| {
+                        Vector::new(INSERT_REACH / size.width, INSERT_REACH / size.height)
+                    });
+                    let points: Vec<Point> = self.dots.iter().map(|dot| dot.position).collect();
+                    let index = path::insertion_index(&points, closed, dot.position, reach);
+                    self.dots.insert(index, dot);
+                    self.selected = Some(index);
+                } else {
+                    self.dots.push(dot);
+                }
+                self.simplified_from = None;
                 self.dotstate.request_redraw();
                 if self.straight_mode {
                     self.dotstate.request_redraw(); // Ensure lines are regenerated
@@ -55,6 +343,9 @@ impl ExampleCanvas {
             Message::Clear => {
                 self.dotstate = DotState::default();
                 self.dots.clear();
+                self.simplified_from = None;
+                self.selected = None;
+                self.lookup_table = None;
                 self.straight_mode = false; // Reset the mode
                 self.curve_mode = None;
             }
@@ -62,97 +353,1268 @@ impl ExampleCanvas {
                 self.straight_mode = !self.straight_mode; // Toggle the mode
                 self.dotstate.request_redraw(); // Redraw to show/hide lines
             }
+            Message::ToggleSlope => {
+                self.derivatives.slope = !self.derivatives.slope;
+                self.dotstate.request_redraw();
+            }
+            Message::ToggleComb => {
+                self.derivatives.comb = !self.derivatives.comb;
+                self.dotstate.request_redraw();
+            }
+            Message::ToggleBreaks => {
+                self.derivatives.breaks = !self.derivatives.breaks;
+                self.dotstate.request_redraw();
+            }
+            Message::ToggleBounds => {
+                self.bounds = match self.bounds {
+                    Some(_) => None,
+                    None => {
+                        let (min, max) = bounds::parse_range(&self.bounds_range).unwrap_or((0.0, 1.0));
+                        Some(ValueBounds { min, max, policy: BoundsPolicy::default() })
+                    }
+                };
+                self.clamp_to_bounds();
+            }
+            Message::BoundsRangeChanged(range) => {
+                // Applied once it reads as a range, so partial input like "0.." leaves the bounds alone
+                if let (Some(bounds), Some((min, max))) = (&mut self.bounds, bounds::parse_range(&range)) {
+                    bounds.min = min;
+                    bounds.max = max;
+                }
+                self.bounds_range = range;
+                self.clamp_to_bounds();
+            }
+            Message::BoundsPolicySelected(policy) => {
+                if let Some(bounds) = &mut self.bounds {
+                    bounds.policy = policy;
+                }
+                self.clamp_to_bounds();
+            }
+            Message::SetReference => {
+                self.reference = Some(self.document());
+                self.status = Some("Set the current curve as the reference".into());
+            }
+            Message::ClearReference => {
+                self.reference = None;
+                self.reference_samples = SampleCache::default();
+                self.reference_difference = None;
+                self.dotstate.request_redraw();
+            }
+            Message::CompareMode => {
+                self.compare_mode = !self.compare_mode;
+                if !self.compare_mode {
+                    self.compare_samples.clear();
+                }
+                self.dotstate.request_redraw();
+            }
+            Message::CompareSelected(algorithm) => {
+                self.curve_mode = Some(algorithm);
+                self.dotstate.request_redraw();
+            }
+            Message::ThemeSelected(theme) => {
+                if let Err(error) = style::save_theme(&theme) {
+                    self.status = Some(format!("Warning: the theme won't be remembered, {error}"));
+                }
+                self.theme = theme;
+                self.dotstate.request_full_redraw();
+            }
+            Message::DotShapeSelected(shape) => {
+                self.dotstate.dot_shape = shape;
+                self.dotstate.request_redraw();
+            }
+            Message::PathMode => {
+                self.curve_kind = match self.curve_kind {
+                    CurveKind::Function => CurveKind::Path { closed: false },
+                    CurveKind::Path { closed: false } => CurveKind::Path { closed: true },
+                    CurveKind::Path { closed: true } => CurveKind::Function,
+                };
+                // Sorting by x would undo the path order
+                if self.curve_kind != CurveKind::Function {
+                    self.function_mode = false;
+                }
+            }
+            Message::MoveKeyBack | Message::MoveKeyForward => {
+                if let Some(index) = self.selected.filter(|&index| index < self.dots.len()) {
+                    let other = if matches!(message, Message::MoveKeyBack) {
+                        index.checked_sub(1)
+                    } else {
+                        Some(index + 1).filter(|&other| other < self.dots.len())
+                    };
+                    if let Some(other) = other {
+                        self.dots.swap(index, other);
+                        self.selected = Some(other);
+                        self.simplified_from = None;
+                    }
+                }
+            }
             Message::Curve => {
-                // Cycle through curve modes: Off -> Catmull-Rom -> Off
+                // Cycle through curve modes: Off -> Catmull-Rom -> ... -> NURBS -> Off
                 self.curve_mode = match self.curve_mode {
                     None => Some(CurveAlgorithm::CatmullRom), // 1st press
                     Some(CurveAlgorithm::CatmullRom) => Some(CurveAlgorithm::MonotonicSpline),
                     Some(CurveAlgorithm::MonotonicSpline) => Some(CurveAlgorithm::NaturalCubicSpline),
-                    Some(CurveAlgorithm::NaturalCubicSpline) => None, // 2nd press
-
-                                                             // Some(CurveAlgorithm::CatmullRom) => None, // 2nd press
+                    Some(CurveAlgorithm::NaturalCubicSpline) => Some(CurveAlgorithm::Pchip),
+                    Some(CurveAlgorithm::Pchip) => Some(CurveAlgorithm::Steffen),
+                    Some(CurveAlgorithm::Steffen) => Some(CurveAlgorithm::Akima),
+                    Some(CurveAlgorithm::Akima) => Some(CurveAlgorithm::Bezier),
+                    Some(CurveAlgorithm::Bezier) => Some(CurveAlgorithm::BSpline),
+                    Some(CurveAlgorithm::BSpline) => Some(CurveAlgorithm::Nurbs),
+                    Some(CurveAlgorithm::Nurbs) => None, // last press
                 };
                 self.dotstate.request_redraw();
             }
-            Message::DeleteDot(position) => {
-                if self.delete_mode {
-                    // Find the nearest dot and remove it
-                    if let Some(index) = self
-                        .dots
-                        .iter()
-                        .position(|dot| (dot.position.x - position.x).abs() < 10.0 && (dot.position.y - position.y).abs() < 10.0)
-                    {
-                        self.dots.remove(index);
-                        self.dotstate.request_redraw();
-                    }
+            Message::DeleteDot(index) => {
+                if self.delete_mode && index < self.dots.len() {
+                    self.dots.remove(index);
+                    self.selected = match self.selected {
+                        Some(selected) if selected == index => None,
+                        Some(selected) if selected > index => Some(selected - 1),
+                        selected => selected,
+                    };
+                    self.simplified_from = None;
+                    self.dotstate.request_redraw();
                 }
             }
             
+            Message::CanvasResized(size) => {
+                self.canvas_size = Some(size);
+            }
             Message::DeleteMode => {
                 self.delete_mode = !self.delete_mode; // Toggle delete mode
             }
+            Message::FunctionMode => {
+                self.function_mode = !self.function_mode;
+                if self.function_mode {
+                    self.curve_kind = CurveKind::Function;
+                }
+                self.dotstate.request_redraw();
+            }
+            Message::MoveDot(index, position) => {
+                let mut x = position.x.clamp(0.0, 1.0);
+                if let Some(timeline) = self.timeline {
+                    x = timeline.snap(x);
+                }
+                if self.function_mode {
+                    // Dots are sorted in function mode, so the neighbours in the list are the ones in x
+                    let (low, high) = curve::key_x_range(&self.dots, index);
+                    x = x.clamp(low, high);
+                }
+                let y = position.y.clamp(0.0, 1.0);
+                if self
+                    .bounds
+                    .is_some_and(|bounds| bounds.policy == BoundsPolicy::Reject && !bounds.contains(y))
+                {
+                    // The dot stays at the last position inside
+                    return Task::none();
+                }
+                if let Some(dot) = self.dots.get_mut(index) {
+                    dot.position = Point::new(x, y);
+                    if let Some(bounds) = self.bounds {
+                        bounds.clamp_keys(std::slice::from_mut(dot));
+                    }
+                    if self.function_mode {
+                        // Only the moved key and its neighbours have handles that can now reach too far
+                        curve::clamp_handles(&mut self.dots, index.saturating_sub(1)..index + 2);
+                    }
+                    self.simplified_from = None;
+                    self.dotstate.request_redraw();
+                }
+            }
+            Message::Degree => {
+                // Cycle 1 (linear) -> 2 -> ... -> MAX_SPLINE_DEGREE -> 1
+                self.spline_settings.degree = self.spline_settings.degree % MAX_SPLINE_DEGREE + 1;
+                self.dotstate.request_redraw();
+            }
+            Message::Knots => {
+                self.spline_settings.knots = self.spline_settings.knots.toggle();
+                self.dotstate.request_redraw();
+            }
+            Message::ScaleWeight(index, factor) => {
+                if let Some(dot) = self.dots.get_mut(index) {
+                    dot.weight = (dot.weight * factor).clamp(MIN_WEIGHT, MAX_WEIGHT);
+                    self.simplified_from = None;
+                    self.dotstate.request_redraw();
+                }
+            }
+            Message::DocumentPathChanged(path) => {
+                self.document_path = path;
+            }
+            Message::OpenDocument => match CurveDocument::read(&self.document_path()) {
+                Ok(document) => {
+                    self.dots = document.dots;
+                    self.curve_kind = document.kind;
+                    if self.curve_kind != CurveKind::Function {
+                        self.function_mode = false;
+                    }
+                    self.curve_mode = document.curve_mode;
+                    self.spline_settings = document.spline_settings;
+                    self.bounds = document.bounds;
+                    if let Some(bounds) = self.bounds {
+                        self.bounds_range = format!("{}..{}", bounds.min, bounds.max);
+                    }
+                    self.simplified_from = None;
+                    self.dotstate.request_redraw();
+                    self.status = Some(format!("Opened {} keys", self.dots.len()));
+                }
+                Err(error) => self.status = Some(format!("Open failed: {error}")),
+            },
+            Message::SaveDocument => {
+                let document = self.document();
+                let path = self.document_path();
+                self.status = Some(match document.write(&path) {
+                    Ok(()) => format!("Saved {path}"),
+                    Err(error) => format!("Save failed: {error}"),
+                });
+            }
+            Message::CsvPathChanged(path) => {
+                self.csv_path = path;
+            }
+            Message::FitToleranceChanged(tolerance) => {
+                self.fit_tolerance = tolerance;
+            }
+            Message::FitCsv => {
+                self.status = Some(match self.fit_csv() {
+                    Ok(count) => format!("Fitted {count} keys"),
+                    Err(error) => format!("Import failed: {error}"),
+                });
+            }
+            Message::SvgPathChanged(path) => {
+                self.svg_path = path;
+            }
+            Message::ImportSvgPath => {
+                self.status = Some(match self.import_svg_path() {
+                    Ok(count) => format!("Imported {count} keys from the SVG path"),
+                    Err(error) => format!("SVG import failed: {error}"),
+                });
+            }
+            Message::SimplifyToleranceChanged(tolerance) => {
+                self.simplify_tolerance = tolerance;
+            }
+            Message::Simplify => {
+                match self.simplify_tolerance.trim().parse::<f32>() {
+                    Ok(tolerance) if tolerance >= 0.0 => {
+                        let before = self.dots.len();
+                        match simplify::simplify(
+                            &self.dots,
+                            self.curve_kind,
+                            self.curve_mode,
+                            self.spline_settings,
+                            tolerance,
+                        ) {
+                            Ok(simplified) => {
+                                self.status =
+                                    Some(format!("Removed {} of {before} keys", before - simplified.len()));
+                                self.simplified_from = Some(std::mem::replace(&mut self.dots, simplified));
+                                self.dotstate.request_redraw();
+                            }
+                            Err(error) => self.status = Some(format!("Simplify failed: {error}")),
+                        }
+                    }
+                    _ => self.status = Some("The simplify tolerance must be a number of at least 0".into()),
+                }
+            }
+            Message::BakeSpacingChanged(spacing) => {
+                self.bake_spacing = spacing;
+            }
+            Message::BakeToKeys => match self.bake() {
+                Ok(table) => {
+                    self.status = Some(format!("Baked {} linear keys", table.samples.len()));
+                    self.dots = table.samples.into_iter().map(Dot::new).collect();
+                    self.simplified_from = None;
+                    // Linear keys are joined with straight lines
+                    self.curve_mode = None;
+                    self.straight_mode = true;
+                    self.dotstate.request_redraw();
+                }
+                Err(error) => self.status = Some(format!("Bake failed: {error}")),
+            },
+            Message::BakeToTable => match self.bake() {
+                Ok(table) => {
+                    self.status = Some(match table.spacing {
+                        BakeSpacing::Count(n) => format!("Baked {n} samples to the lookup table"),
+                        BakeSpacing::Frames { start, end } => {
+                            format!("Baked frames {start} to {end} to the lookup table")
+                        }
+                    });
+                    self.lookup_table = Some(table);
+                    self.dotstate.request_redraw();
+                }
+                Err(error) => self.status = Some(format!("Bake failed: {error}")),
+            },
+            Message::DiscardTable => {
+                self.lookup_table = None;
+                self.export.from_table = false;
+                self.dotstate.request_redraw();
+            }
+            Message::ToggleExport => {
+                self.export.open = !self.export.open;
+            }
+            Message::TogglePreview => {
+                self.preview_open = !self.preview_open;
+                if !self.preview_open && self.playback.playing {
+                    self.playback.toggle();
+                }
+            }
+            Message::PlayPause => self.playback.toggle(),
+            Message::ToggleLoop => self.playback.looping = !self.playback.looping,
+            Message::PreviewDurationChanged(duration) => {
+                if let Some(seconds) = duration.trim().parse::<f32>().ok().filter(|seconds| *seconds > 0.0) {
+                    self.playback.duration = std::time::Duration::from_secs_f32(seconds.min(3600.0));
+                }
+                self.preview_duration = duration;
+            }
+            Message::PreviewObjectSelected(object) => self.preview_object = object,
+            Message::Tick(now) => self.playback.tick(now),
+            Message::ToggleTimeline => {
+                self.timeline = match self.timeline {
+                    Some(_) => None,
+                    None => Some(self.timeline_settings().unwrap_or_default()),
+                };
+                if let Some(timeline) = self.timeline {
+                    self.playback.duration = timeline.duration();
+                }
+                self.dotstate.request_full_redraw();
+            }
+            Message::TimelineFpsChanged(fps) => {
+                self.timeline_fps = fps;
+                self.apply_timeline_settings();
+            }
+            Message::TimelineRangeChanged(range) => {
+                self.timeline_range = range;
+                self.apply_timeline_settings();
+            }
+            Message::SnapKeys => {
+                if let Some(timeline) = self.timeline {
+                    let skipped = timeline.snap_keys(&mut self.dots, self.function_mode);
+                    if skipped > 0 {
+                        self.status = Some(format!("Left {skipped} keys off their frames, another key was already there"));
+                    }
+                    self.simplified_from = None;
+                    self.dotstate.request_redraw();
+                }
+            }
+            Message::Scrub(x) => self.playback.seek(x),
+            Message::SelectDot(index) => {
+                self.selected = Some(index);
+                if let Some(dot) = self.dots.get(index) {
+                    self.key_color = gradient::to_hex(gradient::key_color(dot));
+                }
+            }
+            Message::GradientMode => {
+                self.gradient_mode = !self.gradient_mode;
+                self.update_gradient();
+                self.dotstate.request_redraw();
+            }
+            Message::ColorSpaceSelected(space) => {
+                self.color_space = space;
+                self.update_gradient();
+                self.dotstate.request_redraw();
+            }
+            Message::KeyColorChanged(hex) => {
+                if let (Some(color), Some(dot)) = (gradient::parse_hex(&hex), self.selected_dot_mut()) {
+                    dot.color = Some(color);
+                }
+                self.key_color = hex;
+            }
+            Message::KeyChannelChanged(channel, value) => {
+                if let Some(dot) = self.selected_dot_mut() {
+                    let mut color = gradient::key_color(dot);
+                    match channel {
+                        0 => color.r = value,
+                        1 => color.g = value,
+                        _ => color.b = value,
+                    }
+                    dot.color = Some(color);
+                    self.key_color = gradient::to_hex(color);
+                }
+            }
+            Message::ToneMode => {
+                self.tone_mode = !self.tone_mode;
+                self.update_tone();
+                self.dotstate.request_full_redraw();
+            }
+            Message::ToneChannelSelected(channel) => {
+                // The edited channel's keys live in dots like any other curve, the rest wait here
+                self.tone_keys[self.tone_channel.index()] = std::mem::take(&mut self.dots);
+                self.dots = std::mem::take(&mut self.tone_keys[channel.index()]);
+                self.tone_channel = channel;
+                self.selected = None;
+                self.dotstate.request_full_redraw();
+            }
+            Message::ImagePathChanged(path) => {
+                self.image_path = path;
+            }
+            Message::OpenImage => match tone::load(self.image_path.trim()) {
+                Ok(full) => {
+                    let thumbnail = tone::thumbnail(&full, TONE_PREVIEW_SIZE);
+                    self.tone_image = Some(ToneImage {
+                        histogram: Histogram::new(&full),
+                        preview: image_handle(&thumbnail),
+                        full,
+                        thumbnail,
+                    });
+                    self.update_tone();
+                    self.dotstate.request_full_redraw();
+                    self.status = Some(format!("Opened {}", self.image_path.trim()));
+                }
+                Err(error) => self.status = Some(format!("Could not open the image: {error}")),
+            },
+            Message::GradedPathChanged(path) => {
+                self.graded_path = path;
+            }
+            Message::ExportGradedImage => {
+                self.status = Some(match &self.tone_image {
+                    None => "Open an image to export it graded".into(),
+                    Some(tone_image) => {
+                        let path = self.graded_path("graded.png");
+                        match tone::save(&self.tone_curves.apply_to_image(&tone_image.full), &path) {
+                            Ok(()) => format!("Exported the graded image to {path}"),
+                            Err(error) => format!("Export failed: {error}"),
+                        }
+                    }
+                });
+            }
+            Message::ExportToneLut => {
+                let path = self.graded_path("tone.cube");
+                self.status = Some(match std::fs::write(&path, self.tone_curves.format_cube(TONE_LUT_SIZE)) {
+                    Ok(()) => format!("Exported the tone curves to {path}"),
+                    Err(error) => format!("Export failed: {error}"),
+                });
+            }
+            Message::GradientPathChanged(path) => {
+                self.gradient_path = path;
+            }
+            Message::ExportGradient => {
+                self.status = Some(match self.export_gradient() {
+                    Ok(path) => format!("Exported the gradient to {path}"),
+                    Err(error) => format!("Export failed: {error}"),
+                });
+            }
+            Message::ExportFormatSelected(format) => {
+                self.export.format = format;
+            }
+            Message::ExportResolutionChanged(resolution) => {
+                self.export.resolution = resolution;
+            }
+            Message::ExportStartChanged(start) => {
+                self.export.start = start;
+            }
+            Message::ExportEndChanged(end) => {
+                self.export.end = end;
+            }
+            Message::ExportPathChanged(path) => {
+                self.export.path = path;
+            }
+            Message::ExportSource => {
+                self.export.from_table = !self.export.from_table && self.lookup_table.is_some();
+            }
+            Message::Export => {
+                self.status = Some(match self.export_to_file() {
+                    Ok((count, path)) => format!("Exported {count} samples to {path}"),
+                    Err(error) => format!("Export failed: {error}"),
+                });
+            }
+            Message::ImageWidthChanged(width) => {
+                self.export.image_width = width;
+            }
+            Message::ImageHeightChanged(height) => {
+                self.export.image_height = height;
+            }
+            Message::ExportSvg => {
+                self.status = Some(match self.export_image("svg") {
+                    Ok(path) => format!("Saved the canvas to {path}"),
+                    Err(error) => format!("SVG export failed: {error}"),
+                });
+            }
+            Message::ExportPng => {
+                self.status = Some(match self.export_image("png") {
+                    Ok(path) => format!("Saved the canvas to {path}"),
+                    Err(error) => format!("PNG export failed: {error}"),
+                });
+            }
+            Message::PresetSelected(preset) => {
+                self.apply_easing(preset.dots());
+                self.status = Some(format!("Loaded {preset}"));
+            }
+            Message::CssEasingChanged(css) => {
+                self.css_easing = css;
+            }
+            Message::ApplyCss => match self.css_easing.parse::<CssEasing>() {
+                Ok(easing) => {
+                    self.apply_easing(Preset::Css(easing).dots());
+                    self.status = Some(format!("Loaded {easing}"));
+                }
+                Err(error) => self.status = Some(format!("CSS import failed: {error}")),
+            },
+            Message::CopyCss => match self.function_keys() {
+                Err(reason) => self.status = Some(format!("Warning: not copied, {reason}")),
+                Ok(dots) => match CssEasing::from_curve(dots, self.curve_mode) {
+                    Ok(easing) => {
+                        self.css_easing = easing.to_string();
+                        self.status = Some(format!("Copied {easing}"));
+                        return iced::clipboard::write(self.css_easing.clone());
+                    }
+                    Err(reason) => self.status = Some(format!("Warning: not copied, {reason}")),
+                },
+            },
+        }
+
+        if self.function_mode && !keeps_order {
+            let moved = curve::make_function_curve(&mut self.dots);
+            // Sorting may have moved the selected key, and with it the one being dragged
+            self.selected = self.selected.and_then(|index| moved.get(index).copied().flatten());
+            let removed = moved.iter().filter(|index| index.is_none()).count();
+            if removed > 0 {
+                self.status = Some(format!("Removed {removed} keys that shared an x position"));
+                self.dotstate.request_redraw();
+            }
+        }
+        self.dotstate.selected = self.selected;
+
+        // Resampled only when the curve's own inputs changed, so other messages and redraws stay cheap
+        let window = self
+            .canvas_size
+            .map_or(SampleWindow::FULL, |size| SampleWindow::screen(size.width));
+        let curve_changed = self.curve_samples.update(
+            &self.dots,
+            self.curve_kind,
+            self.curve_mode,
+            self.spline_settings,
+            self.bounds,
+            window,
+        );
+        if curve_changed {
+            self.dotstate.update_index(&self.dots);
+            self.update_gradient();
+            self.update_tone();
+            self.dotstate.request_redraw();
+        }
+        if self.compare_mode {
+            self.compare_samples.resize_with(CurveAlgorithm::ALL.len(), SampleCache::default);
+            let mut compare_changed = false;
+            for (samples, algorithm) in self.compare_samples.iter_mut().zip(CurveAlgorithm::ALL) {
+                compare_changed |= samples.update(
+                    &self.dots,
+                    self.curve_kind,
+                    Some(algorithm),
+                    self.spline_settings,
+                    self.bounds,
+                    window,
+                );
+            }
+            if compare_changed {
+                self.dotstate.request_redraw();
+            }
+        }
+        if let Some(reference) = &self.reference {
+            let reference_changed = self.reference_samples.update(
+                &reference.dots,
+                reference.kind,
+                reference.curve_mode,
+                reference.spline_settings,
+                reference.bounds,
+                window,
+            );
+            if curve_changed || reference_changed {
+                // Either curve failing to build leaves no readout rather than a stale one
+                self.reference_difference = difference::difference(reference, &self.document()).ok().flatten();
+                self.dotstate.request_redraw();
+            }
+        }
+        if let Some(original) = &self.simplified_from {
+            if self.simplified_samples.update(
+                original,
+                self.curve_kind,
+                self.curve_mode,
+                self.spline_settings,
+                self.bounds,
+                window,
+            ) {
+                self.dotstate.request_redraw();
+            }
+        }
+
+        // A curve that can't be built is explained, not just missing
+        self.curve_error = if self.dots.is_empty() {
+            None
+        } else {
+            self.curve_samples.samples().err()
+        };
+        Task::none()
+    }
+
+    // Frame rate and range as typed, empty fields fall back to the defaults shown as placeholders
+    fn timeline_settings(&self) -> Result<Timeline, String> {
+        let defaults = Timeline::default();
+        let fps = match self.timeline_fps.trim() {
+            "" => defaults.fps,
+            fps => fps
+                .parse::<f32>()
+                .ok()
+                .filter(|fps| *fps > 0.0 && fps.is_finite())
+                .ok_or("the frame rate must be a positive number")?,
+        };
+        let (start, end) = match self.timeline_range.trim() {
+            "" => (defaults.start, defaults.end),
+            range => match range.parse::<BakeSpacing>() {
+                Ok(BakeSpacing::Frames { start, end }) => (start, end),
+                _ => return Err("the frame range must look like 0..120".into()),
+            },
+        };
+        Ok(Timeline { fps, start, end })
+    }
+
+    // Fields that don't parse yet are left alone until they do, as with the preview duration
+    fn apply_timeline_settings(&mut self) {
+        if let (Some(_), Ok(timeline)) = (self.timeline, self.timeline_settings()) {
+            self.timeline = Some(timeline);
+            self.playback.duration = timeline.duration();
+            self.dotstate.request_full_redraw();
+        }
+    }
+
+    // Lookup tables, exports, CSS, gradients and tone curves read one y per x, which paths don't have
+    fn function_keys(&self) -> Result<&[Dot], String> {
+        match self.curve_kind {
+            CurveKind::Function => Ok(&self.dots),
+            CurveKind::Path { .. } => Err("the curve is a free-form path, which has no single y per x".into()),
+        }
+    }
+
+    fn selected_dot_mut(&mut self) -> Option<&mut Dot> {
+        self.selected.and_then(|index| self.dots.get_mut(index))
+    }
+
+    // Rebuilds the channel curves and regrades the thumbnail. A curve that can't be built keeps the
+    // last grade until it can
+    fn update_tone(&mut self) {
+        if !self.tone_mode {
+            return;
+        }
+        let Ok(dots) = self.function_keys() else {
+            return;
+        };
+        let mut keys: [&[Dot]; 4] = std::array::from_fn(|channel| self.tone_keys[channel].as_slice());
+        keys[self.tone_channel.index()] = dots;
+        if let Ok(curves) = ToneCurves::new(keys, self.curve_mode, self.spline_settings) {
+            self.tone_curves = curves;
+        }
+        if let Some(tone_image) = &mut self.tone_image {
+            tone_image.preview = image_handle(&self.tone_curves.apply_to_image(&tone_image.thumbnail));
+        }
+    }
+
+    fn graded_path(&self, default: &str) -> String {
+        match self.graded_path.trim() {
+            "" => default.to_string(),
+            path => path.to_string(),
+        }
+    }
+
+    fn update_gradient(&mut self) {
+        self.gradient = match (self.gradient_mode, self.function_keys()) {
+            (true, Ok(dots)) => gradient::sample_gradient(
+                dots,
+                self.curve_mode,
+                self.spline_settings,
+                self.color_space,
+                GRADIENT_SAMPLES,
+            )
+            .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+    }
+
+    // Writes the gradient as a .cube LUT, or CSV when the path ends in .csv, returning the path
+    fn export_gradient(&self) -> Result<String, String> {
+        self.function_keys()?;
+        if self.gradient.is_empty() {
+            return Err("the gradient needs at least one key that can be built into a curve".into());
+        }
+        let path = match self.gradient_path.trim() {
+            "" => "gradient.cube".to_string(),
+            path => path.to_string(),
+        };
+        let contents = if path.to_lowercase().ends_with(".csv") {
+            gradient::format_csv(&self.gradient)
+        } else {
+            gradient::format_cube(&self.gradient)
+        };
+        std::fs::write(&path, contents).map_err(|error| error.to_string())?;
+        Ok(path)
+    }
+
+    fn bake(&self) -> Result<LookupTable, String> {
+        let spacing = self.bake_spacing.parse::<BakeSpacing>().map_err(|error| error.to_string())?;
+        let mut table = bake::bake(self.function_keys()?, self.curve_mode, self.spline_settings, spacing)
+            .map_err(|error| error.to_string())?;
+        if let Some(bounds) = self.bounds {
+            bounds.apply(&mut table.samples);
+        }
+        Ok(table)
+    }
+
+    // The curve and its settings, as saved
+    fn document(&self) -> CurveDocument {
+        CurveDocument {
+            dots: self.dots.clone(),
+            kind: self.curve_kind,
+            curve_mode: self.curve_mode,
+            spline_settings: self.spline_settings,
+            bounds: self.bounds,
+        }
+    }
+
+    // Moves the keys into the bounds when the policy clamps, saying how many moved
+    fn clamp_to_bounds(&mut self) {
+        if let Some(bounds) = self.bounds {
+            let moved = bounds.clamp_keys(&mut self.dots);
+            if moved > 0 {
+                self.simplified_from = None;
+                self.status = Some(format!("Clamped {moved} keys into {}..{}", bounds.min, bounds.max));
+            }
+        }
+        self.dotstate.request_redraw();
+    }
+
+    // Replaces the dots with an easing curve, which is always made of Bézier keys
+    fn apply_easing(&mut self, dots: Vec<Dot>) {
+        self.dots = dots;
+        self.curve_mode = Some(CurveAlgorithm::Bezier);
+        self.simplified_from = None;
+        self.dotstate.request_redraw();
+    }
+
+    // Renders the canvas as "svg" or "png" next to the export path, returning the file written
+    fn export_image(&self, extension: &str) -> Result<String, String> {
+        let (width, height) = self.export.image_size()?;
+        let drawing = Drawing {
+            dots: &self.dots,
+            kind: self.curve_kind,
+            straight_mode: self.straight_mode,
+            curve_mode: self.curve_mode,
+            spline_settings: self.spline_settings,
+            style: CanvasStyle {
+                dot_shape: self.dotstate.dot_shape,
+                ..CanvasStyle::from_theme(&self.theme)
+            },
+        };
+        let path = match self.export.path.trim() {
+            "" => std::path::PathBuf::from("curve"),
+            path => std::path::PathBuf::from(path),
         }
+        .with_extension(extension);
+
+        let contents = match extension {
+            "svg" => drawing.to_svg(Size::new(width as f32, height as f32)).into_bytes(),
+            _ => drawing.to_png(width, height)?,
+        };
+        std::fs::write(&path, contents).map_err(|error| error.to_string())?;
+        Ok(path.display().to_string())
+    }
+
+    // Writes the curve or lookup table in the chosen format, returning the sample count and path
+    fn export_to_file(&self) -> Result<(usize, String), String> {
+        let samples = match (&self.lookup_table, self.export.from_table) {
+            (Some(table), true) => table.samples.clone(),
+            _ => {
+                let mut samples = export::sample(
+                    self.function_keys()?,
+                    self.curve_mode,
+                    self.spline_settings,
+                    self.export.settings()?,
+                )
+                .map_err(|error| error.to_string())?;
+                if let Some(bounds) = self.bounds {
+                    bounds.apply(&mut samples);
+                }
+                samples
+            }
+        };
+
+        let path = match self.export.path.trim() {
+            "" => format!("curve.{}", self.export.format.extension()),
+            path => path.to_string(),
+        };
+        let contents = export::format_samples(self.export.format, &samples).map_err(|error| error.to_string())?;
+        std::fs::write(&path, contents).map_err(|error| error.to_string())?;
+        Ok((samples.len(), path))
+    }
+
+    // The typed document path, or curve.curve when empty
+    fn document_path(&self) -> String {
+        match self.document_path.trim() {
+            "" => format!("curve.{}", document::EXTENSION),
+            path => path.to_string(),
+        }
+    }
+
+    // Replaces the dots with a Bézier fit of the CSV samples, returning how many keys it used
+    fn fit_csv(&mut self) -> Result<usize, String> {
+        let tolerance: f32 = self
+            .fit_tolerance
+            .trim()
+            .parse()
+            .ok()
+            .filter(|tolerance: &f32| *tolerance > 0.0)
+            .ok_or("the tolerance must be a positive number")?;
+        let samples = import::read_csv_samples(self.csv_path.trim()).map_err(|error| error.to_string())?;
+
+        // Fit in data units so the tolerance means what the user typed, then map into curve space
+        // An error here leaves the current dots alone
+        let segments = fit::fit_cubic_beziers(&samples, tolerance).map_err(|error| error.to_string())?;
+        let segments = import::fit_into_unit_square(&segments, false);
+
+        self.dots = curve::dots_from_beziers(&segments);
+        self.simplified_from = None;
+        self.curve_mode = Some(CurveAlgorithm::Bezier);
+        self.dotstate.request_redraw();
+        Ok(self.dots.len())
+    }
+
+    // Replaces the dots with the segments of the SVG path data, returning how many keys it made
+    fn import_svg_path(&mut self) -> Result<usize, String> {
+        let segments = import::parse_svg_path(&self.svg_path).map_err(|error| error.to_string())?;
+        // SVG y points down, curve space y points up
+        self.dots = curve::dots_from_beziers(&import::fit_into_unit_square(&segments, true));
+        self.simplified_from = None;
+        self.curve_mode = Some(CurveAlgorithm::Bezier);
+        self.dotstate.request_redraw();
+        Ok(self.dots.len())
+    }
+
+    /// The theme picked in the editor, which also colors the canvas.
+    fn theme(&self) -> Theme {
+        self.theme.clone()
     }
 
     /// Builds the user interface (UI) for the application.
-    fn view(&self) -> Element<Message> {
-        container(hover(
-            self.dotstate
-                .view(&self.dots, self.straight_mode, self.curve_mode, self.delete_mode)
-                .map(|dot| {
-                    if self.delete_mode {
-                        Message::DeleteDot(dot.position)
-                    } else {
-                        Message::AddDot(dot)
-                    }
-                }),
-            if self.dots.is_empty() {
-                container(horizontal_space())
-            } else {
-                container(
-                    iced::widget::column![
-                        button("Clear")
-                            .style(button::danger)
-                            .on_press(Message::Clear),
-                        button(if self.straight_mode {
-                            "Straight: On"
-                        } else {
-                            "Straight: Off"
-                        })
-                        .on_press(Message::Straight),
-                        if self.dots.len() >= 2 {
-                            // Only enable curve button if there are >2 points
-                            button(match self.curve_mode {
-                                None => "Curve: Off",
-                                Some(CurveAlgorithm::CatmullRom) => "Curve: Catmull-Rom",
-                                Some(CurveAlgorithm::MonotonicSpline) => "Curve: Monotonic",
-                                Some(CurveAlgorithm::NaturalCubicSpline) => "Curve: Natural Cubic",
-                            })
-                            .on_press(Message::Curve) // Button is active
-                        } else {
-                            button("Curve: Disabled") // Button is disabled (no `on_press`)
-                        },
-                        button(if self.delete_mode {
-                            "Delete Mode: On"
-                        } else {
-                            "Delete Mode: Off"
-                        })
-                        .on_press(Message::DeleteMode),
-                    ]
+    fn view(&self) -> Element<'_, Message> {
+        let canvas = hover(
+            self.dotstate.view(
+                &self.dots,
+                self.straight_mode,
+                self.curve_mode,
+                self.delete_mode,
+                &self.curve_samples,
+                Overlays {
+                    simplified_from: self
+                        .simplified_from
+                        .as_deref()
+                        .map(|original| (original, self.simplified_samples.samples().unwrap_or_default())),
+                    lookup_table: self.lookup_table.as_ref(),
+                    playhead: (self.preview_open || self.timeline.is_some()).then(|| self.playback.progress()),
+                    timeline: self.timeline,
+                    gradient: self.gradient_mode.then_some((self.gradient.as_slice(), self.color_space)),
+                    histogram: self
+                        .tone_image
+                        .as_ref()
+                        .filter(|_| self.tone_mode)
+                        .map(|tone_image| (&tone_image.histogram, self.tone_channel)),
+                    derivatives: self.derivatives,
+                    bounds: self.bounds,
+                    compare: self.compare_mode.then_some(self.compare_samples.as_slice()),
+                    reference: self.reference.as_ref().map(|_| {
+                        (self.reference_samples.samples().unwrap_or_default(), self.reference_difference)
+                    }),
+                },
+            ),
+            container(
+                column![
+                    self.curve_controls(),
+                    self.timeline_controls(),
+                    self.gradient_controls(),
+                    button(if self.tone_mode { "Tone curves: On" } else { "Tone curves: Off" })
+                        .on_press(Message::ToneMode),
+                    pick_list(Theme::ALL, Some(&self.theme), Message::ThemeSelected),
+                    pick_list(DotShape::ALL, Some(self.dotstate.dot_shape), Message::DotShapeSelected),
+                    self.import_controls()
+                ]
+                    .push_maybe(self.export.open.then(|| self.export_controls()))
+                    .push_maybe(self.status.as_deref().map(text))
+                    .push_maybe(
+                        self.curve_error
+                            .map(|error| text(format!("Can't draw the curve: {error}")).style(text::danger)),
+                    )
                     .spacing(10),
-                )
-                .padding(10)
-                .align_right(Fill)
+            )
+            .padding(10)
+            .align_right(Fill),
+        );
+        let canvas = column![canvas]
+            .push_maybe(
+                self.gradient_mode
+                    .then(|| Canvas::new(GradientStrip { colors: &self.gradient }).width(Fill).height(32)),
+            )
+            .spacing(10);
+        container(
+            row![canvas]
+                .push_maybe(self.preview_open.then(|| self.preview_panel()))
+                .push_maybe(self.tone_mode.then(|| self.tone_panel()))
+                .spacing(20),
+        )
+            .padding(20)
+            .into()
+    }
+
+    // Animation frames are only requested while something is playing
+    fn subscription(&self) -> Subscription<Message> {
+        if self.playback.playing {
+            window::frames().map(Message::Tick)
+        } else {
+            Subscription::none()
+        }
+    }
+
+    // The curve applied to an object over time, beside the canvas
+    fn preview_panel(&self) -> Element<'_, Message> {
+        column![
+            Canvas::new(Preview {
+                object: self.preview_object,
+                samples: self
+                    .curve_samples
+                    .samples()
+                    .ok()
+                    .filter(|_| !self.dots.is_empty() && self.curve_kind == CurveKind::Function),
+                progress: self.playback.progress(),
+            })
+            .width(Fill)
+            .height(200),
+            pick_list(PreviewObject::ALL, Some(self.preview_object), Message::PreviewObjectSelected).width(Fill),
+            row![
+                button(if self.playback.playing { "Pause" } else { "Play" }).on_press(Message::PlayPause),
+                button(if self.playback.looping { "Loop: On" } else { "Loop: Off" }).on_press(Message::ToggleLoop),
+            ]
+            .spacing(10),
+            text_input("Duration (s): 1", &self.preview_duration)
+                .on_input(Message::PreviewDurationChanged)
+                .width(Fill),
+            text(format!("x = {:.3}", self.playback.progress())),
+        ]
+        .spacing(10)
+        .width(220)
+        .into()
+    }
+
+    // The image being graded and where to send the result, beside the canvas
+    fn tone_panel(&self) -> Element<'_, Message> {
+        column![
+            pick_list(ToneChannel::ALL, Some(self.tone_channel), Message::ToneChannelSelected).width(Fill),
+            text_input("Image (PNG or JPEG)", &self.image_path)
+                .on_input(Message::ImagePathChanged)
+                .on_submit(Message::OpenImage)
+                .width(Fill),
+            button("Open image").on_press(Message::OpenImage),
+        ]
+        .push_maybe(
+            self.tone_image
+                .as_ref()
+                .map(|tone_image| image(tone_image.preview.clone()).width(Fill)),
+        )
+        .push(
+            text_input("graded.png or tone.cube", &self.graded_path)
+                .on_input(Message::GradedPathChanged)
+                .width(Fill),
+        )
+        .push(
+            row![
+                button("Export image").on_press_maybe(self.tone_image.as_ref().map(|_| Message::ExportGradedImage)),
+                button("Export LUT").on_press(Message::ExportToneLut),
+            ]
+            .spacing(10),
+        )
+        .spacing(10)
+        .width(260)
+        .into()
+    }
+
+    // Buttons for editing the dots, hidden until there is something to edit
+    fn curve_controls(&self) -> Element<'_, Message> {
+        if self.dots.is_empty() {
+            return column![].into();
+        }
+        // Baking reads one y per x, see function_keys
+        let function = self.curve_kind == CurveKind::Function;
+
+        column![
+            button("Clear")
+                .style(button::danger)
+                .on_press(Message::Clear),
+            button(if self.straight_mode {
+                "Straight: On"
+            } else {
+                "Straight: Off"
+            })
+            .on_press(Message::Straight),
+            if self.dots.len() >= 2 {
+                // Only enable curve button if there are >2 points
+                button(text(match self.curve_mode {
+                    None => "Curve: Off".to_string(),
+                    Some(algorithm) => format!("Curve: {algorithm}"),
+                }))
+                .on_press(Message::Curve) // Button is active
+            } else {
+                button("Curve: Disabled") // Button is disabled (no `on_press`)
             },
+            // Degree and knot controls only apply to the approximating splines
+            button(iced::widget::text(format!("Degree: {}", self.spline_settings.degree)))
+                .on_press_maybe(self.curve_mode.filter(|mode| mode.has_control_polygon()).map(|_| Message::Degree)),
+            button(match self.spline_settings.knots {
+                KnotVector::Uniform => "Knots: Uniform",
+                KnotVector::Clamped => "Knots: Clamped",
+            })
+            .on_press_maybe(self.curve_mode.filter(|mode| mode.has_control_polygon()).map(|_| Message::Knots)),
+            button(if self.delete_mode {
+                "Delete Mode: On"
+            } else {
+                "Delete Mode: Off"
+            })
+            .on_press(Message::DeleteMode),
+            button(if self.function_mode {
+                "Function curve: On"
+            } else {
+                "Function curve: Off"
+            })
+            .on_press(Message::FunctionMode),
+            button(match self.curve_kind {
+                CurveKind::Function => "Path: Off",
+                CurveKind::Path { closed: false } => "Path: Open",
+                CurveKind::Path { closed: true } => "Path: Closed",
+            })
+            .on_press(Message::PathMode),
+            row![
+                button(if self.derivatives.slope { "Slope: On" } else { "Slope: Off" })
+                    .on_press(Message::ToggleSlope),
+                button(if self.derivatives.comb { "Comb: On" } else { "Comb: Off" }).on_press(Message::ToggleComb),
+                button(if self.derivatives.breaks { "Breaks: On" } else { "Breaks: Off" })
+                    .on_press(Message::ToggleBreaks),
+            ]
+            .spacing(10),
+            button(if self.bounds.is_some() { "Bounds: On" } else { "Bounds: Off" }).on_press(Message::ToggleBounds),
+            button(if self.compare_mode { "Compare: On" } else { "Compare: Off" }).on_press(Message::CompareMode),
+            row![
+                button("Set as reference").on_press(Message::SetReference),
+                button("Clear reference").on_press_maybe(self.reference.as_ref().map(|_| Message::ClearReference)),
+            ]
+            .spacing(10),
+            text_input("Simplify tolerance", &self.simplify_tolerance)
+                .on_input(Message::SimplifyToleranceChanged)
+                .on_submit(Message::Simplify)
+                .width(200),
+            button("Simplify").on_press(Message::Simplify),
+            text_input("Bake: 64 or 0..120", &self.bake_spacing)
+                .on_input(Message::BakeSpacingChanged)
+                .on_submit(Message::BakeToTable)
+                .width(200),
+            button("Bake to keys").on_press_maybe(function.then_some(Message::BakeToKeys)),
+            button("Bake to table").on_press_maybe(function.then_some(Message::BakeToTable)),
+            button(if self.export.open { "Export: Hide" } else { "Export..." })
+                .on_press(Message::ToggleExport),
+            button(if self.preview_open { "Preview: Hide" } else { "Preview..." })
+                .on_press(Message::TogglePreview),
+        ]
+        .push_maybe(self.bounds.map(|bounds| {
+            row![
+                text_input("Bounds: 0..1", &self.bounds_range)
+                    .on_input(Message::BoundsRangeChanged)
+                    .width(100),
+                pick_list(BoundsPolicy::ALL, Some(bounds.policy), Message::BoundsPolicySelected),
+            ]
+            .spacing(10)
+        }))
+        .push_maybe(
+            self.lookup_table
+                .as_ref()
+                .map(|_| button("Discard table").on_press(Message::DiscardTable)),
+        )
+        .push_maybe(
+            // Path order is the drawing order, so keys can be moved along it
+            (self.curve_kind != CurveKind::Function && self.selected.is_some()).then(|| {
+                row![
+                    button("Move key back").on_press(Message::MoveKeyBack),
+                    button("Move key forward").on_press(Message::MoveKeyForward),
+                ]
+                .spacing(10)
+            }),
+        )
+        .spacing(10)
+        .into()
+    }
+
+    fn export_controls(&self) -> Element<'_, Message> {
+        let defaults = ExportSettings::default();
+        column![
+            pick_list(
+                ExportFormat::ALL,
+                Some(self.export.format),
+                Message::ExportFormatSelected
+            )
+            .width(200),
+            row![
+                text_input(&format!("From {}", defaults.start), &self.export.start)
+                    .on_input(Message::ExportStartChanged)
+                    .width(95),
+                text_input(&format!("To {}", defaults.end), &self.export.end)
+                    .on_input(Message::ExportEndChanged)
+                    .width(95),
+            ]
+            .spacing(10),
+            text_input(&format!("Resolution {}", defaults.resolution), &self.export.resolution)
+                .on_input(Message::ExportResolutionChanged)
+                .width(200),
+            text_input(&format!("curve.{}", self.export.format.extension()), &self.export.path)
+                .on_input(Message::ExportPathChanged)
+                .on_submit(Message::Export)
+                .width(200),
+        ]
+        .push_maybe(self.lookup_table.as_ref().map(|_| {
+            button(if self.export.from_table {
+                "Source: Lookup table"
+            } else {
+                "Source: Curve"
+            })
+            .on_press(Message::ExportSource)
+        }))
+        .push(button("Export").on_press_maybe(
+            // A lookup table can be exported whatever the curve is now
+            (self.export.from_table || self.curve_kind == CurveKind::Function).then_some(Message::Export),
         ))
-        .padding(20)
+        .push(
+            row![
+                text_input(&format!("Width {}", DEFAULT_IMAGE_SIZE.0), &self.export.image_width)
+                    .on_input(Message::ImageWidthChanged)
+                    .width(95),
+                text_input(&format!("Height {}", DEFAULT_IMAGE_SIZE.1), &self.export.image_height)
+                    .on_input(Message::ImageHeightChanged)
+                    .width(95),
+            ]
+            .spacing(10),
+        )
+        .push(
+            row![
+                button("Export SVG").on_press(Message::ExportSvg),
+                button("Export PNG").on_press(Message::ExportPng),
+            ]
+            .spacing(10),
+        )
+        .spacing(10)
+        .into()
+    }
+
+    fn timeline_controls(&self) -> Element<'_, Message> {
+        let toggle = button(if self.timeline.is_some() { "Timeline: On" } else { "Timeline: Off" })
+            .on_press(Message::ToggleTimeline);
+        if self.timeline.is_none() {
+            return toggle.into();
+        }
+        let defaults = Timeline::default();
+        column![
+            toggle,
+            row![
+                text_input(&format!("{} fps", defaults.fps), &self.timeline_fps)
+                    .on_input(Message::TimelineFpsChanged)
+                    .width(95),
+                text_input(&format!("{}..{}", defaults.start, defaults.end), &self.timeline_range)
+                    .on_input(Message::TimelineRangeChanged)
+                    .width(95),
+            ]
+            .spacing(10),
+            button("Snap keys to frames").on_press_maybe((!self.dots.is_empty()).then_some(Message::SnapKeys)),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    fn gradient_controls(&self) -> Element<'_, Message> {
+        let toggle = button(if self.gradient_mode { "Gradient: On" } else { "Gradient: Off" })
+            .on_press(Message::GradientMode);
+        if !self.gradient_mode {
+            return toggle.into();
+        }
+        let selected = self.selected.and_then(|index| self.dots.get(index));
+        let channels = selected.map(|dot| {
+            let color = gradient::key_color(dot);
+            [color.r, color.g, color.b]
+        });
+        let channel_slider = |channel: usize| {
+            slider(0.0..=1.0, channels.map_or(0.0, |channels| channels[channel]), move |value| {
+                Message::KeyChannelChanged(channel, value)
+            })
+            .step(1.0 / 255.0)
+            .width(200)
+        };
+        column![
+            toggle,
+            pick_list(ColorSpace::ALL, Some(self.color_space), Message::ColorSpaceSelected).width(200),
+        ]
+        .push_maybe(selected.map(|_| {
+            column![
+                text_input("#ff8000", &self.key_color)
+                    .on_input(Message::KeyColorChanged)
+                    .width(200),
+                channel_slider(0),
+                channel_slider(1),
+                channel_slider(2),
+            ]
+            .spacing(5)
+        }))
+        .push(
+            text_input("gradient.cube", &self.gradient_path)
+                .on_input(Message::GradientPathChanged)
+                .on_submit(Message::ExportGradient)
+                .width(200),
+        )
+        .push(
+            button("Export gradient LUT")
+                .on_press_maybe((!self.gradient.is_empty()).then_some(Message::ExportGradient)),
+        )
+        .spacing(10)
         .into()
     }
-}
 
-#[derive(Debug, Clone, Copy)]
-struct Dot {
-    position: iced::Point,
+    fn import_controls(&self) -> Element<'_, Message> {
+        column![
+            text_input("curve.curve", &self.document_path)
+                .on_input(Message::DocumentPathChanged)
+                .on_submit(Message::OpenDocument)
+                .width(200),
+            row![
+                button("Open").on_press(Message::OpenDocument),
+                button("Save").on_press(Message::SaveDocument),
+            ]
+            .spacing(10),
+            text_input("samples.csv", &self.csv_path)
+                .on_input(Message::CsvPathChanged)
+                .on_submit(Message::FitCsv)
+                .width(200),
+            text_input("Tolerance", &self.fit_tolerance)
+                .on_input(Message::FitToleranceChanged)
+                .on_submit(Message::FitCsv)
+                .width(200),
+            button("Fit CSV").on_press(Message::FitCsv),
+            text_input("SVG path: M0,100 C40,100 60,0 100,0", &self.svg_path)
+                .on_input(Message::SvgPathChanged)
+                .on_submit(Message::ImportSvgPath)
+                .width(200),
+            button("Import SVG path").on_press(Message::ImportSvgPath),
+            pick_list(Preset::all(), None::<Preset>, Message::PresetSelected)
+                .placeholder("Easing preset")
+                .width(200),
+            text_input("cubic-bezier(0.25, 0.1, 0.25, 1)", &self.css_easing)
+                .on_input(Message::CssEasingChanged)
+                .on_submit(Message::ApplyCss)
+                .width(200),
+            row![
+                button("Apply CSS").on_press(Message::ApplyCss),
+                button("Copy as CSS").on_press_maybe(
+                    (!self.dots.is_empty() && self.curve_kind == CurveKind::Function).then_some(Message::CopyCss)
+                ),
+            ]
+            .spacing(10),
+        ]
+        .spacing(10)
+        .into()
+    }
 }
 
 // impl Dot{
@@ -160,9 +1622,21 @@ struct Dot {
 // }
 // }
 
+// Height of the timeline ruler along the top of the canvas, where frame numbers are drawn
+const RULER_HEIGHT: f32 = 20.0;
+
+// The canvas is drawn in three layers so that an edit only redraws what it touched:
+// the grid never changes, the curve changes with the dots and the overlay follows the mouse
 #[derive(Default)]
 struct DotState {
-    cache: canvas::Cache,
+    background: canvas::Cache,
+    curve: canvas::Cache,
+    index: DotIndex,
+    selected: Option<usize>, // The app's selected dot, which is the one a drag moves
+    dragging: bool,          // Holding the selected dot, only used by the canvas' own copy of the state
+    scrubbing: bool,         // Dragging the current time along the timeline ruler
+    size: Size,              // Last bounds seen by the canvas' own copy, to report resizes
+    dot_shape: DotShape,     // How keys are drawn, on the canvas and in image exports
 }
 
 impl DotState {
@@ -172,42 +1646,139 @@ impl DotState {
         straight_mode: bool,
         curve_mode: Option<CurveAlgorithm>,
         delete_mode: bool,
-    ) -> Element<'a, Dot> {
+        samples: &'a SampleCache,
+        overlays: Overlays<'a>,
+    ) -> Element<'a, Message> {
         Canvas::new(DrawDotsAndLines {
             state: self,
             dots,
+            kind: samples.kind(),
             straight_mode,
             curve_mode,
-            delete_mode
+            delete_mode,
+            samples,
+            overlays,
         }) //Pass straight_mode to DrawDotsandLines
         .width(Fill)
         .height(Fill)
         .into()
     }
 
+    pub fn update_index(&mut self, dots: &[Dot]) {
+        self.index = DotIndex::new(dots);
+    }
+
+    // The background only depends on the canvas size, which its cache already checks
     pub fn request_redraw(&mut self) {
-        self.cache.clear();
+        self.curve.clear();
+    }
+
+    // For changes to the background itself, such as the timeline ruler
+    pub fn request_full_redraw(&mut self) {
+        self.background.clear();
+        self.curve.clear();
     }
 }
 struct DrawDotsAndLines<'a> {
     state: &'a DotState,
     dots: &'a [Dot],
+    kind: CurveKind,
     straight_mode: bool,
     curve_mode: Option<CurveAlgorithm>,
     delete_mode: bool,
+    samples: &'a SampleCache,
+    overlays: Overlays<'a>,
+}
+
+// Optional layers drawn along with the curve
+#[derive(Clone, Copy)]
+struct Overlays<'a> {
+    simplified_from: Option<(&'a [Dot], &'a [Point])>, // Dots before the last simplify and their samples
+    lookup_table: Option<&'a LookupTable>,
+    playhead: Option<f32>, // Curve space x of the preview animation, scrubbed in timeline mode
+    timeline: Option<Timeline>,
+    gradient: Option<(&'a [Color], ColorSpace)>, // Gradient mode's samples, drawn as channel curves
+    histogram: Option<(&'a Histogram, ToneChannel)>, // The graded image's, behind the grid
+    derivatives: DerivativeOverlays,
+    bounds: Option<ValueBounds>, // Drawn as limit lines, with what's outside them in red
+    reference: Option<(&'a [Point], Option<CurveDifference>)>, // Reference samples and how far the curve is from them
+    compare: Option<&'a [SampleCache]>, // Every algorithm's samples, by CurveAlgorithm::index
+}
+
+impl DrawDotsAndLines<'_> {
+    // Index of the first dot within 10px of the canvas position, used for picking dots with the mouse
+    fn dot_at(&self, position: Point, size: Size) -> Option<usize> {
+        let reach = Vector::new(10.0 / size.width, 10.0 / size.height);
+        self.state.index.find(canvas_to_curve(position, size), reach)
+    }
 }
 
-impl canvas::Program<Dot> for DrawDotsAndLines<'_> {
+impl canvas::Program<Message> for DrawDotsAndLines<'_> {
     type State = DotState;
 
     /// Handles events on the canvas, such as mouse clicks.
     fn update(
         &self,
-        _state: &mut Self::State,
+        state: &mut Self::State,
         event: iced::widget::canvas::event::Event,
         bounds: Rectangle,
         cursor: mouse::Cursor,
-    ) -> (iced::widget::canvas::event::Status, Option<Dot>) {
+    ) -> (iced::widget::canvas::event::Status, Option<Message>) {
+        // Scrubbing keeps following the mouse outside the canvas like a drag does
+        if state.scrubbing {
+            match event {
+                iced::widget::canvas::event::Event::Mouse(iced::mouse::Event::CursorMoved { position }) => {
+                    let x = ((position.x - bounds.x) / bounds.width).clamp(0.0, 1.0);
+                    return (iced::widget::canvas::event::Status::Captured, Some(Message::Scrub(x)));
+                }
+                iced::widget::canvas::event::Event::Mouse(iced::mouse::Event::ButtonReleased(
+                    iced::mouse::Button::Left,
+                )) => {
+                    state.scrubbing = false;
+                    return (iced::widget::canvas::event::Status::Captured, None);
+                }
+                _ => {}
+            }
+        }
+
+        // A drag keeps following the mouse outside the canvas, clamped to its edges. It moves whichever
+        // dot the app has selected, which follows the dot when the keys are reordered
+        if state.dragging {
+            match event {
+                iced::widget::canvas::event::Event::Mouse(iced::mouse::Event::CursorMoved { position }) => {
+                    let Some(index) = self.state.selected else {
+                        return (iced::widget::canvas::event::Status::Captured, None);
+                    };
+                    let position = Point::new(
+                        (position.x - bounds.x).clamp(0.0, bounds.width),
+                        (position.y - bounds.y).clamp(0.0, bounds.height),
+                    );
+                    return (
+                        iced::widget::canvas::event::Status::Captured,
+                        Some(Message::MoveDot(index, canvas_to_curve(position, bounds.size()))),
+                    );
+                }
+                iced::widget::canvas::event::Event::Mouse(iced::mouse::Event::ButtonReleased(
+                    iced::mouse::Button::Left,
+                )) => {
+                    state.dragging = false;
+                    return (iced::widget::canvas::event::Status::Captured, None);
+                }
+                _ => {}
+            }
+        }
+
+        // Curves are sampled at screen resolution, so tell the app when that changes
+        if let iced::widget::canvas::event::Event::Mouse(iced::mouse::Event::CursorMoved { .. }) = event {
+            if state.size != bounds.size() {
+                state.size = bounds.size();
+                return (
+                    iced::widget::canvas::event::Status::Ignored,
+                    Some(Message::CanvasResized(bounds.size())),
+                );
+            }
+        }
+
         // Only handle events when the cursor is inside the canvas.
         let Some(cursor_position) = cursor.position_in(bounds) else {
             return (iced::widget::canvas::event::Status::Ignored, None);
@@ -231,23 +1802,54 @@ impl canvas::Program<Dot> for DrawDotsAndLines<'_> {
         iced::widget::canvas::event::Event::Mouse(iced::mouse::Event::ButtonPressed(
             iced::mouse::Button::Left,
         )) => {
-            if self.delete_mode {
-                // Directly return the position for deletion
-                return (
+            if self.overlays.timeline.is_some() && cursor_position.y < RULER_HEIGHT {
+                // The ruler is for scrubbing, keys can't be added under it
+                state.scrubbing = true;
+                (
                     iced::widget::canvas::event::Status::Captured,
-                    Some(Dot {
-                        position: cursor_position,
-                    }),
-                );
+                    Some(Message::Scrub(cursor_position.x / bounds.width)),
+                )
+            } else if let Some(algorithm) = self.legend_entry_at(cursor_position, bounds.size()) {
+                (
+                    iced::widget::canvas::event::Status::Captured,
+                    Some(Message::CompareSelected(algorithm)),
+                )
+            } else if self.delete_mode {
+                // Find the dot under the cursor and ask for it to be removed
+                match self.dot_at(cursor_position, bounds.size()) {
+                    Some(index) => (
+                        iced::widget::canvas::event::Status::Captured,
+                        Some(Message::DeleteDot(index)),
+                    ),
+                    None => (iced::widget::canvas::event::Status::Captured, None),
+                }
+            } else if let Some(index) = self.dot_at(cursor_position, bounds.size()) {
+                // Pressing on a dot picks it up instead of adding another one on top
+                state.dragging = true;
+                (iced::widget::canvas::event::Status::Captured, Some(Message::SelectDot(index)))
             } else {
                 // Handle adding a new dot
-                let dot = Dot {
-                    position: cursor_position,
-                };
-                return (
+                let dot = Dot::new(canvas_to_curve(cursor_position, bounds.size()));
+                (
                     iced::widget::canvas::event::Status::Captured,
-                    Some(dot),
-                );
+                    Some(Message::AddDot(dot)),
+                )
+            }
+        }
+        // Scrolling over a dot in NURBS mode pulls the curve towards it (up) or pushes it away (down)
+        iced::widget::canvas::event::Event::Mouse(iced::mouse::Event::WheelScrolled { delta })
+            if self.curve_mode == Some(CurveAlgorithm::Nurbs) =>
+        {
+            let y = match delta {
+                mouse::ScrollDelta::Lines { y, .. } => y,
+                mouse::ScrollDelta::Pixels { y, .. } => y / 20.0,
+            };
+            match self.dot_at(cursor_position, bounds.size()) {
+                Some(index) => (
+                    iced::widget::canvas::event::Status::Captured,
+                    Some(Message::ScaleWeight(index, 1.1_f32.powf(y))),
+                ),
+                None => (iced::widget::canvas::event::Status::Ignored, None),
             }
         }
         _ => (iced::widget::canvas::event::Status::Ignored, None),
@@ -259,33 +1861,89 @@ impl canvas::Program<Dot> for DrawDotsAndLines<'_> {
     /// Draws the canvas content.
     fn draw(
         &self,
-        _state: &Self::State,
+        state: &Self::State,
         renderer: &iced::Renderer,
         theme: &iced::Theme,
         bounds: iced::Rectangle,
-        _cursor: iced::mouse::Cursor,
+        cursor: iced::mouse::Cursor,
     ) -> Vec<iced::widget::canvas::Geometry> {
-        let content = self.state.cache.draw(renderer, bounds.size(), |frame| {
+        let style = CanvasStyle {
+            dot_shape: self.state.dot_shape,
+            ..CanvasStyle::from_theme(theme)
+        };
+        let background = self.state.background.draw(renderer, bounds.size(), |frame| {
+            // Histograms of the image being graded, scaled so the fullest bin reaches the top. The
+            // channel being edited is drawn stronger than the others
+            if let Some((histogram, channel)) = self.overlays.histogram {
+                let peak = histogram.peak().max(1) as f32;
+                let area = |bins: &[u32]| {
+                    let last = (bins.len() - 1) as f32;
+                    Path::new(|builder| {
+                        builder.move_to(Point::new(0.0, bounds.height));
+                        for (i, &count) in bins.iter().enumerate() {
+                            builder.line_to(curve_to_canvas(
+                                Point::new(i as f32 / last, count as f32 / peak),
+                                bounds.size(),
+                            ));
+                        }
+                        builder.line_to(Point::new(bounds.width, bounds.height));
+                        builder.close();
+                    })
+                };
+                let luminance = theme.extended_palette().background.strong.color;
+                frame.fill(&area(&histogram.luminance), Color { a: 0.5, ..luminance });
+                for (index, (bins, tint)) in histogram.channels.iter().zip(style.channels).enumerate() {
+                    let alpha = if channel.index() == index + 1 { 0.45 } else { 0.15 };
+                    frame.fill(&area(bins), Color { a: alpha, ..tint });
+                }
+            }
+
             // Draw grid lines
-            let grid_spacing = 50.0; // Spacing between grid lines in pixels
+            let grid_spacing = style.grid_spacing;
             let bounds_width = bounds.width;
             let bounds_height = bounds.height;
 
-            // Draw vertical grid lines
-            for x in (0..bounds_width as usize).step_by(grid_spacing as usize) {
-                let x = x as f32;
+            // Draw vertical grid lines, on labelled frames in timeline mode
+            if let Some(timeline) = self.overlays.timeline {
+                let step = timeline.ruler_step(bounds_width, grid_spacing);
+                for frame_number in timeline.ruler_frames(step) {
+                    let x = curve_to_canvas(Point::new(timeline.x_at(frame_number as f32), 0.0), bounds.size()).x;
+                    frame.stroke(
+                        &Path::line(Point { x, y: 0.0 }, Point { x, y: bounds_height }),
+                        Stroke::default()
+                            .with_width(1.0)
+                            .with_color(style.grid_vertical),
+                    );
+                    frame.fill_text(iced::widget::canvas::Text {
+                        content: frame_number.to_string(),
+                        position: Point { x: x + 3.0, y: 3.0 },
+                        color: theme.palette().text,
+                        size: iced::Pixels(12.0),
+                        ..Default::default()
+                    });
+                }
                 frame.stroke(
-                    &Path::line(
-                        Point { x, y: 0.0 },
-                        Point {
-                            x,
-                            y: bounds_height,
-                        },
-                    ),
+                    &Path::line(Point { x: 0.0, y: RULER_HEIGHT }, Point { x: bounds_width, y: RULER_HEIGHT }),
                     Stroke::default()
                         .with_width(1.0)
-                        .with_color(iced::Color::from_rgb(0.23, 0.25, 0.18)), // Light gray
+                        .with_color(style.grid_horizontal),
                 );
+            } else {
+                for x in (0..bounds_width as usize).step_by(grid_spacing as usize) {
+                    let x = x as f32;
+                    frame.stroke(
+                        &Path::line(
+                            Point { x, y: 0.0 },
+                            Point {
+                                x,
+                                y: bounds_height,
+                            },
+                        ),
+                        Stroke::default()
+                            .with_width(1.0)
+                            .with_color(style.grid_vertical),
+                    );
+                }
             }
 
             // Draw horizontal grid lines
@@ -295,7 +1953,7 @@ impl canvas::Program<Dot> for DrawDotsAndLines<'_> {
                     &Path::line(Point { x: 0.0, y }, Point { x: bounds_width, y }),
                     Stroke::default()
                         .with_width(1.0)
-                        .with_color(iced::Color::from_rgb(0.21, 0.23, 0.19)), // Light gray
+                        .with_color(style.grid_horizontal),
                 );
             }
 
@@ -303,356 +1961,567 @@ impl canvas::Program<Dot> for DrawDotsAndLines<'_> {
             frame.stroke(
                 &Path::rectangle(Point::ORIGIN, frame.size()),
                 Stroke::default()
-                    .with_width(4.0)
-                    .with_color(iced::Color::from_rgb(0.23, 0.37, 0.80)),
+                    .with_width(style.border_width)
+                    .with_color(style.border),
             );
+        });
+
+        let content = self.state.curve.draw(renderer, bounds.size(), |frame| {
+            // Everything below is drawn in canvas pixels
+            let canvas_dots: Vec<Dot> = self.dots.iter().map(|dot| dot.to_canvas(bounds.size())).collect();
+
+            // Only dots on the canvas are drawn, and of several on the same pixel only the first, so a
+            // curve with far more keys than the canvas has pixels costs what can be seen
+            let visible = Rectangle::new(Point::ORIGIN, bounds.size()).expand(style.dot_radius);
+            let mut covered = std::collections::HashSet::new();
+
             // Draw dots - iterate list and draw on the canvas.
-            for dot in self.dots {
+            for (index, (dot, original)) in canvas_dots.iter().zip(self.dots).enumerate() {
+                let pixel = (dot.position.x.round() as i32, dot.position.y.round() as i32);
+                if !visible.contains(dot.position) || !covered.insert(pixel) {
+                    continue;
+                }
                 // Use the x and y fields of the iced::Point to draw a circle at dot position.
-                frame.fill(
-                    &iced::widget::canvas::Path::circle(dot.position, 5.0), // Use *position to dereference the Point reference.
-                    iced::Color {
-                        r: 0.44, // 111 / 255
-                        g: 0.20, // 50 / 255
-                        b: 0.0,  // 0 / 255
-                        a: 1.0,  // Fully opaque
-                    }, // Use the theme's text color for the dot.
-                );
-            }
-
-            let mut sorted_dots: Vec<Dot> = Vec::new(); // Kim: Outside of if statement so it can be used below
-            dbg!(&sorted_dots);
+                frame.fill(&dot_path(style.dot_shape, dot.position, style.dot_radius), style.dot);
+                // Gradient keys show their color inside the usual dot
+                if self.overlays.gradient.is_some() {
+                    frame.fill(
+                        &dot_path(style.dot_shape, dot.position, style.dot_radius - 1.5),
+                        gradient::key_color(original),
+                    );
+                }
 
-            if !self.dots.is_empty() {
-                if self.straight_mode || self.curve_mode.is_some() {
-                    sorted_dots = self.dots.to_vec();
-                    sorted_dots.sort_by(|a, b| a.position.x.partial_cmp(&b.position.x).unwrap());
-                    let first_dot = sorted_dots.first().unwrap();
-                    let last_dot = sorted_dots.last().unwrap();
-                    let first_control_dot = Dot {
+                // Number path keys, since their order shapes the path
+                if self.kind != CurveKind::Function {
+                    frame.fill_text(iced::widget::canvas::Text {
+                        content: (index + 1).to_string(),
                         position: Point {
-                            x: 0.0,
-                            y: first_dot.position.y,
+                            x: dot.position.x - 14.0,
+                            y: dot.position.y - 18.0,
                         },
-                    };
-                    let last_control_dot = Dot {
+                        color: theme.palette().text,
+                        size: iced::Pixels(12.0),
+                        ..Default::default()
+                    });
+                }
+
+                // Label non-default weights so NURBS edits are visible
+                if self.curve_mode == Some(CurveAlgorithm::Nurbs) && dot.weight != 1.0 {
+                    frame.fill_text(iced::widget::canvas::Text {
+                        content: format!("w={:.2}", dot.weight),
                         position: Point {
-                            x: bounds_width,
-                            y: last_dot.position.y,
+                            x: dot.position.x + 8.0,
+                            y: dot.position.y - 18.0,
                         },
-                    };
-                    sorted_dots.push(first_control_dot);
-                    sorted_dots.push(last_control_dot);
-                    sorted_dots.sort_by(|a, b| a.position.x.partial_cmp(&b.position.x).unwrap());
-                    dbg!(&sorted_dots);
-
-                    // Prepare knots and knot spacing
-                    // let first_dot = sorted_dots.first().unwrap();
-                    // let last_dot = sorted_dots.last().unwrap();
-                    // let mut x_knots: Vec<f32> = vec![0.0, last_dot.position.x]; // BUG: knots break if less than 4
-                    // let mut y_knots: Vec<f32> = vec![first_dot.position.y, last_dot.position.y];
-                    // x_knots.extend(sorted_dots.iter().map(|dot| dot.position.x));
-                    // y_knots.extend(sorted_dots.iter().map(|dot| dot.position.y));
-                    // x_knots.push(1.0);
-                    // y_knots.push(last_dot.position.y);
+                        color: theme.palette().text,
+                        size: iced::Pixels(12.0),
+                        ..Default::default()
+                    });
                 }
             }
 
+            // The keys as the curve joins them (sorted with edge keys, or in path order), already
+            // built when the curve was sampled. A closed path joins back to its first key
+            let mut sorted_dots: Vec<Point> = self
+                .samples
+                .keys()
+                .iter()
+                .map(|dot| curve_to_canvas(dot.position, bounds.size()))
+                .collect();
+            if self.kind == (CurveKind::Path { closed: true }) && !sorted_dots.is_empty() {
+                sorted_dots.push(sorted_dots[0]);
+            }
+
             // Draw straight line connectors if "Straight" is active
             if self.straight_mode {
-                for i in 0..sorted_dots.len() - 1 {
-                    let start = sorted_dots[i].position;
-                    let end = sorted_dots[i + 1].position;
-
+                for pair in sorted_dots.windows(2) {
                     frame.stroke(
-                        &iced::widget::canvas::Path::line(start, end),
+                        &iced::widget::canvas::Path::line(pair[0], pair[1]),
                         iced::widget::canvas::Stroke::default()
-                            .with_width(2.0)
-                            .with_color(theme.palette().text),
+                            .with_width(style.line_width)
+                            .with_color(style.line),
                     );
                 }
             }
 
-            // Handle curve drawing
-            if let Some(curve_mode) = self.curve_mode {
-                let mut path = Path::new(|builder| {
-                    match curve_mode {
-                        CurveAlgorithm::CatmullRom => {
-                            let n_points = 200; // Number of interpolated points
-                            for i in 0..n_points {
-                                let t = i as f32 / (n_points - 1) as f32;
-                                let segment_index =
-                                    ((sorted_dots.len() - 1) as f32 * t).floor() as usize;
-
-                                // Clamp control points for safe interpolation
-                                let p0 = if segment_index == 0 {
-                                    &sorted_dots[segment_index]
-                                } else {
-                                    &sorted_dots[segment_index - 1]
-                                };
-                                let p1 = &sorted_dots[segment_index];
-                                let p2 = &sorted_dots
-                                    [std::cmp::min(segment_index + 1, sorted_dots.len() - 1)];
-                                let p3 = &sorted_dots
-                                    [std::cmp::min(segment_index + 2, sorted_dots.len() - 1)];
-
-                                let local_t = (t * (sorted_dots.len() - 1) as f32) % 1.0;
-
-                                let alpha = 0.3; // Adjust as needed, but 0.5 is typical for centripetal splines
-
-                                let (x, y) = (
-                                    catmull_rom_centripetal(
-                                        local_t,
-                                        p0.position.x,
-                                        p1.position.x,
-                                        p2.position.x,
-                                        p3.position.x,
-                                        alpha,
-                                    ),
-                                    catmull_rom_centripetal(
-                                        local_t,
-                                        p0.position.y,
-                                        p1.position.y,
-                                        p2.position.y,
-                                        p3.position.y,
-                                        alpha,
-                                    ),
-                                );
-
-                                if i == 0 {
-                                    builder.move_to(Point { x, y });
-                                } else {
-                                    builder.line_to(Point { x, y });
-                                }
-                            }
-                        }
-                        CurveAlgorithm::MonotonicSpline => {
-                            let xs: Vec<f32> =
-                                sorted_dots.iter().map(|dot| dot.position.x).collect();
-                            let ys: Vec<f32> =
-                                sorted_dots.iter().map(|dot| dot.position.y).collect();
-
-                            if xs.len() < 2 {
-                                // Skip drawing if not enough points
-                                return;
-                            }
-
-                            if let Some(interpolated_points) = monotonic_cubic_spline(&xs, &ys) {
-                                for (i, &(x, y)) in interpolated_points.iter().enumerate() {
-                                    // Ensure finite values for rendering
-                                    if !x.is_finite() || !y.is_finite() {
-                                        eprintln!("Invalid interpolated point: x={}, y={}", x, y);
-                                        continue; // Skip invalid points
-                                    }
-
-                                    if i == 0 {
-                                        builder.move_to(Point { x, y });
-                                    } else {
-                                        builder.line_to(Point { x, y });
-                                    }
-                                }
-                            } else {
-                                eprintln!("Monotonic cubic spline interpolation failed.");
-                            }
-                        }
-
-                        CurveAlgorithm::NaturalCubicSpline => {
-                            if sorted_dots.len() >= 2 {
-                                let mut x_points: Vec<f32> =
-                                    sorted_dots.iter().map(|dot| dot.position.x).collect();
-                                let mut y_points: Vec<f32> =
-                                    sorted_dots.iter().map(|dot| dot.position.y).collect();
-
-                                // Compute natural cubic spline coefficients
-                                let x_spline = compute_natural_cubic_spline(&x_points);
-                                let y_spline = compute_natural_cubic_spline(&y_points);
-
-                                let n_points_per_segment = 50; // Number of points per segment for smoothness
-                                for i in 0..sorted_dots.len() - 1 {
-                                    for j in 0..n_points_per_segment {
-                                        let t = j as f32 / (n_points_per_segment as f32);
-
-                                        let x = evaluate_cubic(&x_spline[i], t);
-                                        let y = evaluate_cubic(&y_spline[i], t);
-
-                                        if i == 0 && j == 0 {
-                                            builder.move_to(Point { x, y });
-                                        } else {
-                                            builder.line_to(Point { x, y });
-                                        }
-                                    }
-                                }
-                            }
+            // Draw the dashed control polygon for approximating curves. Paths always interpolate
+            if self.kind == CurveKind::Function && self.curve_mode.is_some_and(CurveAlgorithm::has_control_polygon) {
+                let polygon = Path::new(|builder| {
+                    for (i, &position) in sorted_dots.iter().enumerate() {
+                        if i == 0 {
+                            builder.move_to(position);
+                        } else {
+                            builder.line_to(position);
                         }
                     }
                 });
-
                 frame.stroke(
-                    &path,
-                    Stroke::default()
-                        .with_width(2.0)
-                        .with_color(theme.palette().text),
+                    &polygon,
+                    Stroke {
+                        line_dash: LineDash {
+                            segments: style.polygon_dash,
+                            offset: 0,
+                        },
+                        ..Stroke::default().with_width(1.0).with_color(style.polygon)
+                    },
                 );
             }
-        });
 
-        vec![content]
-    }
-}
+            // Draw Bézier handles so fitted and imported keys show their shape
+            if self.kind == CurveKind::Function && self.curve_mode == Some(CurveAlgorithm::Bezier) {
+                for dot in &canvas_dots {
+                    for handle in [dot.in_handle, dot.out_handle].into_iter().flatten() {
+                        let end = dot.position + handle;
+                        frame.stroke(
+                            &Path::line(dot.position, end),
+                            Stroke::default().with_width(1.0).with_color(style.polygon),
+                        );
+                        frame.fill(&Path::circle(end, style.handle_radius), style.dot);
+                    }
+                }
+            }
 
-fn safe_powf_distance(a: f32, b: f32, alpha: f32) -> f32 {
-    let dist = (a - b).abs();
-    if dist < 1e-9 {
-        // fallback to small epsilon
-        1e-9_f32.powf(alpha)
-    } else {
-        dist.powf(alpha)
-    }
-}
+            // Before/after overlay for the last simplify: the original curve and its removed keys
+            if let Some((original, samples)) = self.overlays.simplified_from {
+                let ghost = theme.extended_palette().danger.weak.color;
+                frame.stroke(
+                    &polyline(samples, bounds.size()),
+                    Stroke {
+                        line_dash: LineDash {
+                            segments: style.ghost_dash,
+                            offset: 0,
+                        },
+                        ..Stroke::default().with_width(1.0).with_color(ghost)
+                    },
+                );
+                for dot in original {
+                    frame.stroke(
+                        &Path::circle(curve_to_canvas(dot.position, bounds.size()), 4.0),
+                        Stroke::default().with_width(1.0).with_color(ghost),
+                    );
+                }
+            }
+
+            // Gradient channels as thin curves, in the space they're interpolated in. OKLab's a and b
+            // are centered on zero, so they're lifted to the middle of the square
+            if let Some((colors, space)) = self.overlays.gradient {
+                let last = colors.len().saturating_sub(1).max(1) as f32;
+                let offsets = match space {
+                    ColorSpace::Oklab => [0.0, 0.5, 0.5],
+                    ColorSpace::LinearRgb | ColorSpace::Srgb => [0.0; 3],
+                };
+                for (channel, (offset, tint)) in offsets.into_iter().zip(style.channels).enumerate() {
+                    let points: Vec<Point> = colors
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &color)| Point::new(i as f32 / last, space.to_channels(color)[channel] + offset))
+                        .collect();
+                    frame.stroke(
+                        &polyline(&points, bounds.size()),
+                        Stroke::default().with_width(1.0).with_color(Color { a: 0.6, ..tint }),
+                    );
+                }
+            }
 
-fn catmull_rom_centripetal(t: f32, p0: f32, p1: f32, p2: f32, p3: f32, alpha: f32) -> f32 {
-    let d01 = safe_powf_distance(p0, p1, alpha);
-    let d12 = safe_powf_distance(p1, p2, alpha);
-    let d23 = safe_powf_distance(p2, p3, alpha);
+            // Baked lookup table as stems, so its resolution can be judged against the curve
+            if let Some(table) = self.overlays.lookup_table {
+                let color = theme.extended_palette().success.base.color;
+                for sample in &table.samples {
+                    let top = curve_to_canvas(*sample, bounds.size());
+                    frame.stroke(
+                        &Path::line(Point::new(top.x, bounds.height), top),
+                        Stroke::default().with_width(1.0).with_color(Color { a: 0.4, ..color }),
+                    );
+                    frame.fill(&Path::rectangle(top - Vector::new(2.0, 2.0), Size::new(4.0, 4.0)), color);
+                }
+            }
 
-    let t0 = 0.0;
-    let t1 = t0 + d01;
-    let t2 = t1 + d12;
-    let t3 = t2 + d23;
+            self.draw_reference(frame, theme, &style, bounds.size());
+            self.draw_compare(frame, theme, &style, bounds.size());
 
-    // We only interpolate between p1 and p2, so we re-map t from [0..1] to [t1..t2].
-    let t = t1 + (t * (t2 - t1));
+            // Handle curve drawing. A curve that can't be built is left out, the panel says why
+            if let (Some(_), Ok(samples)) = (self.curve_mode, self.samples.samples()) {
+                frame.stroke(
+                    &polyline(samples, bounds.size()),
+                    Stroke::default()
+                        .with_width(style.line_width)
+                        .with_color(style.line),
+                );
+                self.draw_derivatives(frame, theme, &style, bounds.size(), samples);
+            }
 
-    // If t1==t0, t2==t1, or t3==t2 (which can happen if d01/d12/d23 = 0), we can early-return:
-    if (t1 - t0).abs() < 1e-12 || (t2 - t1).abs() < 1e-12 || (t3 - t2).abs() < 1e-12 {
-        // fallback: linear interpolation or just pick p1
-        return p1;
-    }
+            self.draw_bounds(frame, theme, &style, bounds.size());
+        });
 
-    // ... compute A1, A2, A3
-    let a1 = (t1 - t) / (t1 - t0) * p0 + (t - t0) / (t1 - t0) * p1;
-    let a2 = (t2 - t) / (t2 - t1) * p1 + (t - t1) / (t2 - t1) * p2;
-    let a3 = (t3 - t) / (t3 - t2) * p2 + (t - t2) / (t3 - t2) * p3;
+        // Hover and drag feedback is redrawn on every frame, it's only a ring or two
+        let mut overlay = canvas::Frame::new(renderer, bounds.size());
+        let hovered = cursor
+            .position_in(bounds)
+            .and_then(|position| self.dot_at(position, bounds.size()));
+        let ring_color = if self.delete_mode {
+            theme.extended_palette().danger.base.color
+        } else {
+            theme.extended_palette().primary.strong.color
+        };
+        let held = self.state.selected.filter(|_| state.dragging);
+        for index in held.into_iter().chain(hovered) {
+            if let Some(dot) = self.dots.get(index) {
+                overlay.stroke(
+                    &Path::circle(curve_to_canvas(dot.position, bounds.size()), style.dot_radius + style.ring_gap),
+                    Stroke::default().with_width(2.0).with_color(ring_color),
+                );
+            }
+        }
 
-    // ... then B1, B2, final
-    let b1 = (t2 - t) / (t2 - t0) * a1 + (t - t0) / (t2 - t0) * a2;
-    let b2 = (t3 - t) / (t3 - t1) * a2 + (t - t1) / (t3 - t1) * a3;
+        // The preview's playhead moves every frame while playing, so it lives in the overlay too
+        if let Some(x) = self.overlays.playhead {
+            let color = theme.extended_palette().success.strong.color;
+            let top = curve_to_canvas(Point::new(x, 1.0), bounds.size());
+            overlay.stroke(
+                &Path::line(top, Point::new(top.x, bounds.height)),
+                Stroke::default().with_width(1.0).with_color(color),
+            );
+            let label = |content: String, position: Point| iced::widget::canvas::Text {
+                content,
+                position: position + Vector::new(6.0, 4.0),
+                color,
+                size: iced::Pixels(12.0),
+                ..Default::default()
+            };
+            if let Some(timeline) = self.overlays.timeline {
+                overlay.fill_text(label(
+                    format!("{:.1} ({:.2} s)", timeline.frame_at(x), timeline.seconds_at(x)),
+                    Point::new(top.x, RULER_HEIGHT),
+                ));
+            }
 
-    (t2 - t) / (t2 - t1) * b1 + (t - t1) / (t2 - t1) * b2
-}
+            // Value of every curve on the canvas at the current time. Paths have no single value
+            let curves = [
+                self.samples
+                    .samples()
+                    .ok()
+                    .filter(|_| !self.dots.is_empty() && self.kind == CurveKind::Function),
+                self.overlays.simplified_from.map(|(_, samples)| samples),
+            ];
+            for samples in curves.into_iter().flatten() {
+                if let Some(y) = curve::polyline_y_at(samples, x) {
+                    let position = curve_to_canvas(Point::new(x, y), bounds.size());
+                    overlay.fill(&Path::circle(position, 4.0), color);
+                    if self.overlays.timeline.is_some() {
+                        overlay.fill_text(label(format!("{y:.3}"), position));
+                    }
+                }
+            }
+        }
 
-fn monotonic_cubic_spline(xs: &[f32], ys: &[f32]) -> Option<Vec<(f32, f32)>> {
-    if xs.len() != ys.len() || xs.len() < 2 {
-        return None; // Invalid input
+        vec![background, content, overlay.into_geometry()]
     }
+}
 
-    let n = xs.len();
-    let mut slopes = vec![0.0; n - 1];
-    let mut tangents = vec![0.0; n];
+// Evaluations per cubic segment for the slope curve and the comb
+const DERIVATIVE_STEPS: usize = 24;
 
-    // Step 1: Compute slopes between points
-    for i in 0..n - 1 {
-        slopes[i] = (ys[i + 1] - ys[i]) / (xs[i + 1] - xs[i]);
+impl DrawDotsAndLines<'_> {
+    // Compare mode's legend, which moves below the ruler in timeline mode
+    fn legend(&self, size: Size) -> Legend {
+        Legend::new(size, if self.overlays.timeline.is_some() { RULER_HEIGHT } else { 0.0 })
     }
 
-    // Step 2: Compute tangents
-    tangents[0] = slopes[0];
-    tangents[n - 1] = slopes[n - 2];
-    for i in 1..n - 1 {
-        tangents[i] = (slopes[i - 1] + slopes[i]) / 2.0;
+    // The algorithm whose legend row is under a canvas position
+    fn legend_entry_at(&self, position: Point, size: Size) -> Option<CurveAlgorithm> {
+        self.overlays.compare?;
+        self.legend(size).entry_at(position)
     }
 
-    // Step 3: Adjust tangents to ensure monotonicity
-    for i in 0..n - 1 {
-        if slopes[i] == 0.0 {
-            tangents[i] = 0.0;
-            tangents[i + 1] = 0.0;
-        } else {
-            let alpha = tangents[i] / slopes[i];
-            let beta = tangents[i + 1] / slopes[i];
-            let s = alpha * alpha + beta * beta;
-            if s > 9.0 {
-                let tau = 3.0 / s.sqrt();
-                tangents[i] = tau * alpha * slopes[i];
-                tangents[i + 1] = tau * beta * slopes[i];
+    // Every algorithm's curve in its own color under the current one, and a legend naming them.
+    // Algorithms that can't be built from the keys are listed but not drawn
+    fn draw_compare(&self, frame: &mut canvas::Frame, theme: &iced::Theme, style: &CanvasStyle, size: Size) {
+        let Some(compare) = self.overlays.compare else {
+            return;
+        };
+        for (samples, algorithm) in compare.iter().zip(CurveAlgorithm::ALL) {
+            if let Ok(samples) = samples.samples() {
+                frame.stroke(
+                    &polyline(samples, size),
+                    Stroke::default().with_width(style.compare_width).with_color(style.algorithm(algorithm)),
+                );
             }
         }
-    }
-
-    // Step 4: Interpolate
-    let mut result = Vec::new();
-    let num_points = 100; // Number of points to sample for rendering
-    for i in 0..n - 1 {
-        let x0 = xs[i];
-        let x1 = xs[i + 1];
-        let y0 = ys[i];
-        let y1 = ys[i + 1];
-        let t0 = tangents[i];
-        let t1 = tangents[i + 1];
-
-        for j in 0..=num_points {
-            let t = j as f32 / num_points as f32;
-            let h00 = (1.0 + 2.0 * t) * (1.0 - t) * (1.0 - t);
-            let h10 = t * (1.0 - t) * (1.0 - t);
-            let h01 = t * t * (3.0 - 2.0 * t);
-            let h11 = t * t * (t - 1.0);
 
-            let x = x0 + t * (x1 - x0);
-            let y = h00 * y0 + h10 * (x1 - x0) * t0 + h01 * y1 + h11 * (x1 - x0) * t1;
-
-            result.push((x, y));
+        let palette = theme.extended_palette();
+        let legend = self.legend(size);
+        frame.fill_rectangle(
+            legend.origin,
+            legend.size(),
+            Color { a: 0.85, ..palette.background.weak.color },
+        );
+        for (samples, algorithm) in compare.iter().zip(CurveAlgorithm::ALL) {
+            let top = legend.row(algorithm);
+            if self.curve_mode == Some(algorithm) {
+                frame.fill_rectangle(top, Size::new(Legend::WIDTH, Legend::ROW), palette.primary.weak.color);
+            }
+            let middle = top.y + Legend::ROW / 2.0;
+            frame.stroke(
+                &Path::line(Point::new(top.x + 6.0, middle), Point::new(top.x + 24.0, middle)),
+                Stroke::default().with_width(3.0).with_color(style.algorithm(algorithm)),
+            );
+            let color = if samples.samples().is_ok() {
+                palette.background.base.text
+            } else {
+                palette.background.strong.color
+            };
+            frame.fill_text(iced::widget::canvas::Text {
+                content: algorithm.to_string(),
+                position: Point::new(top.x + 30.0, middle),
+                color,
+                size: iced::Pixels(12.0),
+                vertical_alignment: iced::alignment::Vertical::Center,
+                ..Default::default()
+            });
         }
     }
 
-    Some(result)
-}
-fn quadratic_bezier(t: f32, p0: f32, p1: f32, p2: f32) -> f32 {
-    let u = 1.0 - t;
-    (u * u * p0) + (2.0 * u * t * p1) + (t * t * p2)
-}
-
-fn compute_natural_cubic_spline(points: &[f32]) -> Vec<[f32; 4]> {
-    let n = points.len() - 1;
-    let mut a = points.to_vec();
-    let mut b = vec![0.0; n];
-    let mut d = vec![0.0; n];
-    let mut h = vec![0.0; n];
-    let mut alpha = vec![0.0; n];
-
-    for i in 0..n {
-        // h[i] = 1.0; // Assuming equal spacing between points
-        h[i] = 1.0;
+    // The reference curve as a ghost under the current one, with the largest and RMS differences in
+    // the corner and the largest marked where it happens
+    fn draw_reference(&self, frame: &mut canvas::Frame, theme: &iced::Theme, style: &CanvasStyle, size: Size) {
+        let Some((samples, difference)) = self.overlays.reference else {
+            return;
+        };
+        let palette = theme.extended_palette();
+        let ghost = Color { a: 0.7, ..palette.secondary.base.color };
+        frame.stroke(
+            &polyline(samples, size),
+            Stroke {
+                line_dash: LineDash {
+                    segments: style.ghost_dash,
+                    offset: 0,
+                },
+                ..Stroke::default().with_width(2.0).with_color(ghost)
+            },
+        );
 
+        let Some(difference) = difference else {
+            return;
+        };
+        let current = self.samples.samples().ok().and_then(|current| curve::polyline_y_at(current, difference.max_at));
+        if let (Some(before), Some(after)) = (curve::polyline_y_at(samples, difference.max_at), current) {
+            frame.stroke(
+                &Path::line(
+                    curve_to_canvas(Point::new(difference.max_at, before), size),
+                    curve_to_canvas(Point::new(difference.max_at, after), size),
+                ),
+                Stroke::default().with_width(1.0).with_color(palette.background.base.text),
+            );
+        }
+        let top = if self.overlays.timeline.is_some() { RULER_HEIGHT } else { 0.0 };
+        frame.fill_text(iced::widget::canvas::Text {
+            content: format!(
+                "Reference: max {:.4} at x = {:.3}, RMS {:.4}",
+                difference.max, difference.max_at, difference.rms
+            ),
+            position: Point::new(8.0, top + 8.0),
+            color: palette.background.base.text,
+            size: iced::Pixels(13.0),
+            ..Default::default()
+        });
     }
 
-    for i in 1..n {
-        alpha[i] = (3.0 / h[i]) * (a[i + 1] - a[i]) - (3.0 / h[i - 1]) * (a[i] - a[i - 1]);
+    // The limit lines, with the region beyond them shaded and the parts of the curve and the keys that
+    // fall there in red
+    fn draw_bounds(&self, frame: &mut canvas::Frame, theme: &iced::Theme, style: &CanvasStyle, size: Size) {
+        let Some(limits) = self.overlays.bounds else {
+            return;
+        };
+        let danger = theme.extended_palette().danger.base.color;
+        let top = curve_to_canvas(Point::new(0.0, limits.max), size).y;
+        let bottom = curve_to_canvas(Point::new(0.0, limits.min), size).y;
+        let shade = Color { a: 0.08, ..danger };
+        if top > 0.0 {
+            frame.fill_rectangle(Point::ORIGIN, Size::new(size.width, top), shade);
+        }
+        if bottom < size.height {
+            frame.fill_rectangle(Point::new(0.0, bottom), Size::new(size.width, size.height - bottom), shade);
+        }
+        for y in [top, bottom] {
+            frame.stroke(
+                &Path::line(Point::new(0.0, y), Point::new(size.width, y)),
+                Stroke {
+                    line_dash: LineDash {
+                        segments: style.guide_dash,
+                        offset: 0,
+                    },
+                    ..Stroke::default().with_width(1.0).with_color(Color { a: 0.6, ..danger })
+                },
+            );
+        }
+
+        // Every sample pair with an end outside, so a run of them reads as one red stretch
+        let shown = self.curve_mode.is_some() || self.straight_mode;
+        if let (true, Ok(samples)) = (shown, self.samples.samples()) {
+            let outside = Path::new(|builder| {
+                for pair in samples.windows(2) {
+                    if !(limits.contains(pair[0].y) && limits.contains(pair[1].y)) {
+                        builder.move_to(curve_to_canvas(pair[0], size));
+                        builder.line_to(curve_to_canvas(pair[1], size));
+                    }
+                }
+            });
+            frame.stroke(&outside, Stroke::default().with_width(3.0).with_color(danger));
+        }
+        for dot in self.dots.iter().filter(|dot| !limits.contains(dot.position.y)) {
+            frame.stroke(
+                &Path::circle(curve_to_canvas(dot.position, size), style.marker_radius),
+                Stroke::default().with_width(2.0).with_color(danger),
+            );
+        }
     }
 
-    let mut c = vec![0.0; points.len()];
-    let mut l = vec![1.0; points.len()];
-    let mut mu = vec![0.0; points.len()];
-    let mut z = vec![0.0; points.len()];
+    // Slope, curvature comb and second derivative breaks of the curve, in closed form for cubic
+    // segments and from the samples otherwise
+    fn draw_derivatives(
+        &self,
+        frame: &mut canvas::Frame,
+        theme: &iced::Theme,
+        style: &CanvasStyle,
+        size: Size,
+        samples: &[Point],
+    ) {
+        let overlays = self.overlays.derivatives;
+        if !(overlays.slope || overlays.comb || overlays.breaks) {
+            return;
+        }
+        let palette = theme.extended_palette();
+        let segments = curve::cubic_segments(self.kind, self.curve_mode, self.samples.keys())
+            .ok()
+            .flatten();
+
+        // The slope on its own axis, centered on zero and scaled to the steepest part shown
+        if overlays.slope && self.kind == CurveKind::Function {
+            let slopes = match &segments {
+                Some(segments) => derivative::slope_curve(segments, DERIVATIVE_STEPS),
+                None => derivative::sampled_slope_curve(samples),
+            };
+            let range = slopes.iter().map(|point| point.y.abs()).fold(0.0, f32::max).max(1e-3);
+            let to_canvas = |slope: f32| size.height * (0.5 - 0.45 * slope / range);
+            let color = palette.secondary.strong.color;
+            frame.stroke(
+                &Path::new(|builder| {
+                    for (i, point) in slopes.iter().enumerate() {
+                        let position = Point::new(point.x * size.width, to_canvas(point.y));
+                        if i == 0 {
+                            builder.move_to(position);
+                        } else {
+                            builder.line_to(position);
+                        }
+                    }
+                }),
+                Stroke::default().with_width(1.5).with_color(color),
+            );
+            frame.stroke(
+                &Path::line(Point::new(0.0, size.height / 2.0), Point::new(size.width, size.height / 2.0)),
+                Stroke {
+                    line_dash: LineDash {
+                        segments: style.guide_dash,
+                        offset: 0,
+                    },
+                    ..Stroke::default().with_width(1.0).with_color(color)
+                },
+            );
+            for (label, slope) in [(format!("dy/dx {range:+.2}"), range), ("0".into(), 0.0), (format!("{:+.2}", -range), -range)] {
+                frame.fill_text(iced::widget::canvas::Text {
+                    content: label,
+                    position: Point::new(size.width - 6.0, to_canvas(slope)),
+                    color,
+                    size: iced::Pixels(12.0),
+                    horizontal_alignment: iced::alignment::Horizontal::Right,
+                    vertical_alignment: iced::alignment::Vertical::Center,
+                    ..Default::default()
+                });
+            }
+        }
 
-    for i in 1..n {
-        l[i] = 2.0 * (points[i + 1] - points[i - 1]) - h[i - 1] * mu[i - 1];
-        mu[i] = h[i] / l[i];
-        z[i] = (alpha[i] - h[i - 1] * z[i - 1]) / l[i];
-    }
+        // Combs are measured in pixels, so they curve the way the curve looks on screen
+        if overlays.comb {
+            let teeth = match &segments {
+                Some(segments) => {
+                    let on_canvas: Vec<[Point; 4]> = segments
+                        .iter()
+                        .map(|segment| segment.map(|point| curve_to_canvas(point, size)))
+                        .collect();
+                    derivative::comb(&on_canvas, DERIVATIVE_STEPS)
+                }
+                None => {
+                    let on_canvas: Vec<Point> = samples.iter().map(|&point| curve_to_canvas(point, size)).collect();
+                    derivative::sampled_comb(&on_canvas)
+                }
+            };
+            let longest = teeth
+                .iter()
+                .map(|(_, tooth)| (tooth.x * tooth.x + tooth.y * tooth.y).sqrt())
+                .fold(0.0, f32::max);
+            if longest > 0.0 {
+                let scale = style.comb_length / longest;
+                let color = Color { a: 0.6, ..palette.primary.weak.color };
+                for &(point, tooth) in &teeth {
+                    frame.stroke(
+                        &Path::line(point, point + tooth * scale),
+                        Stroke::default().with_width(1.0).with_color(color),
+                    );
+                }
+                frame.stroke(
+                    &Path::new(|builder| {
+                        for (i, &(point, tooth)) in teeth.iter().enumerate() {
+                            if i == 0 {
+                                builder.move_to(point + tooth * scale);
+                            } else {
+                                builder.line_to(point + tooth * scale);
+                            }
+                        }
+                    }),
+                    Stroke::default().with_width(1.0).with_color(color),
+                );
+            }
+        }
 
-    for i in (0..n).rev() {
-        c[i] = z[i] - mu[i] * c[i + 1];
-        b[i] = (a[i + 1] - a[i]) / h[i] - h[i] * (c[i + 1] + 2.0 * c[i]) / 3.0;
-        d[i] = (c[i + 1] - c[i]) / (3.0 * h[i]);
+        // Only cubic segments have joins to compare. Paths have no y'', so their curvature is compared
+        if let (true, Some(segments)) = (overlays.breaks, &segments) {
+            let breaks = match self.kind {
+                CurveKind::Function => derivative::discontinuities(segments, false, derivative::second_derivative_at),
+                CurveKind::Path { closed } => derivative::discontinuities(segments, closed, derivative::curvature),
+            };
+            for point in breaks {
+                let center = curve_to_canvas(point, size);
+                let diamond = dot_path(DotShape::Diamond, center, style.break_size);
+                frame.stroke(&diamond, Stroke::default().with_width(2.0).with_color(palette.danger.base.color));
+            }
+        }
     }
+}
 
-    let mut coefficients = Vec::new();
-    for i in 0..n {
-        coefficients.push([a[i], b[i], c[i], d[i]]);
+// A dot of the given shape around a canvas position, such as a key or a break marker
+fn dot_path(shape: DotShape, center: Point, radius: f32) -> Path {
+    match shape.corners(center, radius) {
+        Some(corners) => Path::new(|builder| {
+            builder.move_to(corners[0]);
+            for &corner in &corners[1..] {
+                builder.line_to(corner);
+            }
+            builder.close();
+        }),
+        None => Path::circle(center, radius),
     }
-
-    coefficients
 }
-fn evaluate_cubic(coefficients: &[f32; 4], t: f32) -> f32 {
-    coefficients[0] + coefficients[1] * t + coefficients[2] * t * t + coefficients[3] * t * t * t
+
+// Polyline through curve space points, mapped onto a canvas of the given size
+fn polyline(points: &[Point], size: Size) -> Path {
+    Path::new(|builder| {
+        for (i, &point) in points.iter().enumerate() {
+            if i == 0 {
+                builder.move_to(curve_to_canvas(point, size));
+            } else {
+                builder.line_to(curve_to_canvas(point, size));
+            }
+        }
+    })
 }
+
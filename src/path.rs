@@ -0,0 +1,128 @@
+// Free-form paths: keys joined in the order they were placed rather than by x, so curves can loop,
+// double back and close on themselves
+use iced::{Point, Vector};
+
+use crate::curve::{self, CurveError, Dot, SampleWindow, SegmentSampler};
+
+// Centripetal parameterization, the one that never cusps or loops inside a segment
+pub(crate) const CENTRIPETAL_ALPHA: f32 = 0.5;
+
+/// Samples the path through `keys` in order, as a centripetal Catmull-Rom spline when `smooth` and
+/// as straight lines otherwise. A closed path ends back at the first key.
+pub fn sample_path(keys: &[Dot], closed: bool, smooth: bool, window: SampleWindow) -> Result<Vec<Point>, CurveError> {
+    let xs: Vec<f32> = keys.iter().map(|dot| dot.position.x).collect();
+    let ys: Vec<f32> = keys.iter().map(|dot| dot.position.y).collect();
+    curve::check_points(&xs, &ys, 2)?;
+    let points: Vec<Point> = keys.iter().map(|dot| dot.position).collect();
+
+    if !smooth {
+        let mut samples = points.clone();
+        if closed {
+            samples.push(points[0]);
+        }
+        return Ok(samples);
+    }
+
+    let mut sampler = SegmentSampler::new(window);
+    for hull in segment_points(&points, closed) {
+        sampler.segment(&hull, |t| curve::catmull_rom_centripetal_2d(t, hull, CENTRIPETAL_ALPHA));
+    }
+    let mut samples = sampler.finish();
+    samples.retain(|point| point.x.is_finite() && point.y.is_finite());
+    Ok(samples)
+}
+
+// The four points around each segment of the path, the segment running between the middle two.
+// Open ends get a phantom neighbour mirrored through the end key, so the first and last segments
+// leave their keys heading towards the next one
+pub(crate) fn segment_points(points: &[Point], closed: bool) -> Vec<[Point; 4]> {
+    let n = points.len();
+    let point = |i: isize| -> Point {
+        if closed {
+            points[i.rem_euclid(n as isize) as usize]
+        } else if i < 0 {
+            points[0] + (points[0] - points[1])
+        } else if i as usize >= n {
+            points[n - 1] + (points[n - 1] - points[n - 2])
+        } else {
+            points[i as usize]
+        }
+    };
+    let segments = match n {
+        0 | 1 => 0,
+        _ if closed => n,
+        _ => n - 1,
+    };
+    (0..segments as isize)
+        .map(|i| [point(i - 1), point(i), point(i + 1), point(i + 2)])
+        .collect()
+}
+
+/// Where a key placed at `position` goes in the path: right after the first key of the segment it
+/// was placed on, within `reach` of the straight line between the segment's keys, or at the end.
+pub fn insertion_index(points: &[Point], closed: bool, position: Point, reach: Vector) -> usize {
+    let n = points.len();
+    let segments = match n {
+        0 | 1 => 0,
+        _ if closed => n,
+        _ => n - 1,
+    };
+    // Measured in units of reach, so an editor can pass a reach that's a few pixels on either axis
+    let scaled = |point: Point| Point::new(point.x / reach.x, point.y / reach.y);
+    let position = scaled(position);
+    (0..segments)
+        .map(|i| {
+            let (a, b) = (scaled(points[i]), scaled(points[(i + 1) % n]));
+            (i, distance_to_segment(position, a, b))
+        })
+        .filter(|&(_, distance)| distance <= 1.0)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(n, |(i, _)| i + 1)
+}
+
+pub(crate) fn distance_to_segment(point: Point, a: Point, b: Point) -> f32 {
+    let ab = b - a;
+    let length_squared = ab.x * ab.x + ab.y * ab.y;
+    let t = if length_squared > 0.0 {
+        (((point.x - a.x) * ab.x + (point.y - a.y) * ab.y) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    point.distance(a + ab * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> Vec<Dot> {
+        [(0.2, 0.2), (0.8, 0.2), (0.8, 0.8), (0.2, 0.8)]
+            .map(|(x, y)| Dot::new(Point::new(x, y)))
+            .to_vec()
+    }
+
+    #[test]
+    fn passes_through_the_keys_in_order() {
+        let keys = square();
+        for closed in [false, true] {
+            let samples = sample_path(&keys, closed, true, SampleWindow::FULL).unwrap();
+            for key in &keys {
+                assert!(samples.iter().any(|sample| sample.distance(key.position) < 1e-5));
+            }
+            assert_eq!(samples[0], keys[0].position);
+            let end = samples[samples.len() - 1];
+            let expected = if closed { keys[0].position } else { keys[3].position };
+            assert!(end.distance(expected) < 1e-5, "{end:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn inserts_on_the_nearest_segment() {
+        let points: Vec<Point> = square().iter().map(|dot| dot.position).collect();
+        let reach = Vector::new(0.05, 0.05);
+        assert_eq!(insertion_index(&points, false, Point::new(0.5, 0.21), reach), 1);
+        assert_eq!(insertion_index(&points, false, Point::new(0.81, 0.5), reach), 2);
+        // Away from every segment, the key is appended
+        assert_eq!(insertion_index(&points, true, Point::new(0.5, 0.5), reach), 4);
+    }
+}
@@ -0,0 +1,224 @@
+// Animating an object with the curve, so an easing can be judged by how it moves
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use iced::widget::canvas::{self, Path, Stroke};
+use iced::{mouse, Color, Point, Rectangle, Renderer, Size, Theme};
+
+use crate::curve;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PreviewObject {
+    #[default]
+    Marker, // Rises and falls with the curve, like the plot
+    Square, // Slides from left to right
+    Swatch, // Blends between two colors
+}
+
+impl PreviewObject {
+    pub const ALL: [PreviewObject; 3] = [PreviewObject::Marker, PreviewObject::Square, PreviewObject::Swatch];
+}
+
+impl fmt::Display for PreviewObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PreviewObject::Marker => "Marker",
+            PreviewObject::Square => "Moving square",
+            PreviewObject::Swatch => "Color swatch",
+        })
+    }
+}
+
+/// Time through the animation, advanced by frame timestamps while playing.
+#[derive(Debug, Clone, Copy)]
+pub struct Playback {
+    pub playing: bool,
+    pub looping: bool,
+    pub duration: Duration,
+    elapsed: Duration,
+    last_tick: Option<Instant>,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            playing: false,
+            looping: true,
+            duration: Duration::from_secs(1),
+            elapsed: Duration::ZERO,
+            last_tick: None,
+        }
+    }
+}
+
+impl Playback {
+    /// Plays from the start once a non-looping animation has finished.
+    pub fn toggle(&mut self) {
+        self.playing = !self.playing;
+        self.last_tick = None;
+        if self.playing && self.elapsed >= self.duration {
+            self.elapsed = Duration::ZERO;
+        }
+    }
+
+    /// Advances by the time since the previous tick. The first tick after pressing play only
+    /// records the time, so a pause doesn't count as time played.
+    pub fn tick(&mut self, now: Instant) {
+        if !self.playing {
+            return;
+        }
+        if let Some(last_tick) = self.last_tick {
+            self.elapsed += now.saturating_duration_since(last_tick);
+        }
+        self.last_tick = Some(now);
+
+        if self.elapsed >= self.duration {
+            if self.looping && !self.duration.is_zero() {
+                self.elapsed = Duration::from_secs_f64(self.elapsed.as_secs_f64() % self.duration.as_secs_f64());
+            } else {
+                self.elapsed = self.duration;
+                self.playing = false;
+            }
+        }
+    }
+
+    /// Jumps to a fraction of the duration, as when scrubbing.
+    pub fn seek(&mut self, progress: f32) {
+        self.elapsed = self.duration.mul_f32(progress.clamp(0.0, 1.0));
+        self.last_tick = None;
+    }
+
+    /// Fraction of the duration played, which is the curve space x being previewed.
+    pub fn progress(&self) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        (self.elapsed.as_secs_f64() / self.duration.as_secs_f64()).min(1.0) as f32
+    }
+}
+
+/// Canvas program drawing the preview object at the current progress through the curve samples.
+pub struct Preview<'a> {
+    pub object: PreviewObject,
+    pub samples: Option<&'a [iced::Point]>, // None when the curve can't be built
+    pub progress: f32,
+}
+
+impl Preview<'_> {
+    /// Height of the curve at the current progress, which places the object.
+    pub fn value(&self) -> Option<f32> {
+        self.samples.and_then(|samples| curve::polyline_y_at(samples, self.progress))
+    }
+}
+
+impl<Message> canvas::Program<Message> for Preview<'_> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<canvas::Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        let palette = theme.extended_palette();
+        frame.stroke(
+            &Path::rectangle(Point::ORIGIN, frame.size()),
+            Stroke::default().with_width(1.0).with_color(palette.background.strong.color),
+        );
+
+        let Some(value) = self.value() else {
+            return vec![frame.into_geometry()];
+        };
+        // Room around the track so overshooting curves stay visible
+        let margin = 0.2 * bounds.width.min(bounds.height);
+        let track = Size::new(bounds.width - 2.0 * margin, bounds.height - 2.0 * margin);
+        let center = Point::new(bounds.width / 2.0, bounds.height / 2.0);
+
+        match self.object {
+            PreviewObject::Marker => {
+                let y = margin + (1.0 - value) * track.height;
+                frame.stroke(
+                    &Path::line(Point::new(center.x, margin), Point::new(center.x, margin + track.height)),
+                    Stroke::default().with_width(1.0).with_color(palette.background.strong.color),
+                );
+                frame.fill(&Path::circle(Point::new(center.x, y), 8.0), palette.primary.strong.color);
+            }
+            PreviewObject::Square => {
+                let side = 24.0;
+                let x = margin + value * track.width;
+                frame.stroke(
+                    &Path::line(Point::new(margin, center.y), Point::new(margin + track.width, center.y)),
+                    Stroke::default().with_width(1.0).with_color(palette.background.strong.color),
+                );
+                frame.fill(
+                    &Path::rectangle(Point::new(x - side / 2.0, center.y - side / 2.0), Size::new(side, side)),
+                    palette.primary.strong.color,
+                );
+            }
+            PreviewObject::Swatch => {
+                let (from, to) = (palette.primary.strong.color, palette.success.strong.color);
+                let t = value.clamp(0.0, 1.0);
+                let color = Color::from_rgb(
+                    from.r + (to.r - from.r) * t,
+                    from.g + (to.g - from.g) * t,
+                    from.b + (to.b - from.b) * t,
+                );
+                frame.fill(&Path::rectangle(Point::new(margin, margin), track), color);
+            }
+        }
+        vec![frame.into_geometry()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{Dot, SplineSettings};
+
+    #[test]
+    fn the_preview_follows_the_curve() {
+        let dots = [(0.0, 0.0), (0.5, 0.8), (1.0, 1.0)].map(|(x, y)| Dot::new(Point::new(x, y)));
+        let samples = curve::sample_curve(None, &curve::with_edge_keys(&dots), SplineSettings::default()).unwrap();
+        let mut playback = Playback::default();
+        for (progress, value) in [(0.0, 0.0), (0.5, 0.8), (1.0, 1.0), (0.25, 0.4)] {
+            playback.seek(progress);
+            let preview = Preview {
+                object: PreviewObject::Marker,
+                samples: Some(&samples),
+                progress: playback.progress(),
+            };
+            assert!((preview.value().unwrap() - value).abs() < 1e-5, "{progress}");
+        }
+        let unbuilt = Preview {
+            object: PreviewObject::Marker,
+            samples: None,
+            progress: 0.5,
+        };
+        assert_eq!(unbuilt.value(), None);
+    }
+
+    #[test]
+    fn playback_loops_or_stops_at_the_end() {
+        let start = Instant::now();
+        let mut playback = Playback::default();
+        playback.toggle();
+        playback.tick(start);
+        assert_eq!(playback.progress(), 0.0);
+        playback.tick(start + Duration::from_millis(250));
+        assert!((playback.progress() - 0.25).abs() < 1e-6);
+        playback.tick(start + Duration::from_millis(1500));
+        assert!((playback.progress() - 0.5).abs() < 1e-6);
+        assert!(playback.playing);
+
+        playback.looping = false;
+        playback.tick(start + Duration::from_millis(2500));
+        assert_eq!(playback.progress(), 1.0);
+        assert!(!playback.playing);
+        // Playing again starts over
+        playback.toggle();
+        assert_eq!(playback.progress(), 0.0);
+    }
+}
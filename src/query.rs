@@ -0,0 +1,442 @@
+// Questions a runtime asks of a function curve besides y at x: the area under it, the x for a y,
+// its extremes and where it crosses a value. Answered segment by segment on cubic Béziers, in
+// closed form where there is one
+use iced::Point;
+
+use crate::curve::{self, CurveAlgorithm, CurveError, CurveKind, Dot, SplineSettings};
+use crate::fit;
+
+/// Largest error in x of `inverse`, `solve` and `roots`, in curve space. Integrals and extremes of
+/// cubic segments are exact up to float rounding.
+pub const TOLERANCE: f32 = 1e-5;
+
+// Newton steps polishing a bracketed root, each at least halving the bracket
+const MAX_ROOT_STEPS: usize = 64;
+
+// Gauss-Legendre nodes and weights on [0, 1]. Three nodes integrate polynomials up to degree 5
+// exactly, and y(t) x'(t) on a cubic segment is degree 5
+const GAUSS_NODES: [f32; 3] = [0.112_701_67, 0.5, 0.887_298_3];
+const GAUSS_WEIGHTS: [f32; 3] = [5.0 / 18.0, 8.0 / 18.0, 5.0 / 18.0];
+
+/// A curve y = f(x) as cubic Bézier segments running left to right, held level past its ends as
+/// the editor evaluates it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCurve {
+    segments: Vec<[Point; 4]>,
+}
+
+impl FunctionCurve {
+    /// The curve through `dots` as the editor draws it. Curves made of cubic segments keep them
+    /// exactly; B-splines and NURBS, and Bézier keys whose handles fold back in x, are queried on
+    /// their samples as straight segments, so their answers are only as close as the samples.
+    pub fn new(
+        curve_mode: Option<CurveAlgorithm>,
+        dots: &[Dot],
+        spline_settings: SplineSettings,
+    ) -> Result<Self, CurveError> {
+        let keys = curve::with_edge_keys(dots);
+        if let Some(segments) = curve::cubic_segments(CurveKind::Function, curve_mode, &keys)? {
+            // x must only move right along every segment for the curve to be a function
+            if segments
+                .iter()
+                .all(|segment| segment.windows(2).all(|pair| pair[0].x <= pair[1].x))
+            {
+                return Ok(Self { segments });
+            }
+        }
+        let samples = curve::sample_curve(curve_mode, &keys, spline_settings)?;
+        Ok(Self::from_polyline(&samples))
+    }
+
+    /// Straight segments between the points, which should be sorted by x.
+    pub fn from_polyline(points: &[Point]) -> Self {
+        let segments = points
+            .windows(2)
+            .map(|pair| {
+                let (a, b) = (pair[0], pair[1]);
+                [a, a + (b - a) * (1.0 / 3.0), a + (b - a) * (2.0 / 3.0), b]
+            })
+            .collect();
+        Self { segments }
+    }
+
+    /// The curve with everything outside `min..=max` cut off and run level along the edge instead,
+    /// as `BoundsPolicy::Bounded` draws it. The parts inside stay exact cubic segments, split where
+    /// the curve crosses the edges.
+    pub fn clipped(&self, min: f32, max: f32) -> Self {
+        let mut segments = Vec::with_capacity(self.segments.len());
+        for segment in &self.segments {
+            let ys = segment.map(|point| point.y);
+            let mut ts = vec![0.0, 1.0];
+            for edge in [min, max] {
+                ts.extend(cubic_roots(&ys.map(|y| y - edge)));
+            }
+            ts.sort_by(f32::total_cmp);
+            for pair in ts.windows(2).filter(|pair| pair[1] > pair[0]) {
+                let piece = sub_segment(segment, pair[0], pair[1]);
+                let middle = fit::evaluate_bezier(&piece, 0.5).y;
+                if (min..=max).contains(&middle) {
+                    segments.push(piece);
+                } else {
+                    let level = |point: Point| Point::new(point.x, middle.clamp(min, max));
+                    let (start, end) = (level(piece[0]), level(piece[3]));
+                    segments.extend(Self::from_polyline(&[start, end]).segments);
+                }
+            }
+        }
+        Self { segments }
+    }
+
+    /// y at `x`, level with the end keys outside them.
+    pub fn evaluate(&self, x: f32) -> f32 {
+        let (Some(first), Some(last)) = (self.segments.first(), self.segments.last()) else {
+            return 0.0;
+        };
+        if x <= first[0].x {
+            return first[0].y;
+        }
+        if x >= last[3].x {
+            return last[3].y;
+        }
+        let segment = &self.segments[self.segment_index(x)];
+        fit::evaluate_bezier(segment, self.t_at(segment, x)).y
+    }
+
+    /// Signed area under the curve from x = `a` to `b`, negative when `b < a`. Past the end keys the
+    /// curve is level, so the area there is a rectangle.
+    pub fn integral(&self, a: f32, b: f32) -> f32 {
+        if b < a {
+            return -self.integral(b, a);
+        }
+        let (Some(first), Some(last)) = (self.segments.first(), self.segments.last()) else {
+            return 0.0;
+        };
+        let (start, end) = (first[0], last[3]);
+        let mut area = 0.0;
+        // Level parts before the first key and after the last
+        area += (b.min(start.x) - a.min(start.x)) * start.y;
+        area += (b.max(end.x) - a.max(end.x)) * end.y;
+
+        let (a, b) = (a.max(start.x), b.min(end.x));
+        if a >= b {
+            return area;
+        }
+        for segment in &self.segments {
+            let (x0, x1) = (segment[0].x, segment[3].x);
+            if x1 <= a || x0 >= b {
+                continue;
+            }
+            let t0 = if x0 < a { self.t_at(segment, a) } else { 0.0 };
+            let t1 = if x1 > b { self.t_at(segment, b) } else { 1.0 };
+            area += segment_area(segment, t0, t1);
+        }
+        area
+    }
+
+    /// The x where the curve reaches `y`, if the curve is monotone and reaches it. Within
+    /// `TOLERANCE` of the true x.
+    pub fn inverse(&self, y: f32) -> Option<f32> {
+        if !self.is_monotone() {
+            return None;
+        }
+        self.solve(y).first().copied()
+    }
+
+    /// Whether y only rises or only falls, level stretches allowed.
+    pub fn is_monotone(&self) -> bool {
+        let mut values = Vec::new();
+        for segment in &self.segments {
+            let ys = segment.map(|point| point.y);
+            values.push(segment[0].y);
+            values.extend(
+                critical_points(&ys)
+                    .into_iter()
+                    .map(|t| evaluate_power(&power_basis(&ys), t)),
+            );
+        }
+        values.extend(self.segments.last().map(|segment| segment[3].y));
+        let rising = values.windows(2).all(|pair| pair[1] >= pair[0] - TOLERANCE);
+        let falling = values.windows(2).all(|pair| pair[1] <= pair[0] + TOLERANCE);
+        rising || falling
+    }
+
+    /// The lowest and highest points of the curve between x = `a` and `b`. Exact: they're at the
+    /// range ends, at keys or where a segment's slope is zero, found with the quadratic formula.
+    pub fn extrema(&self, a: f32, b: f32) -> (Point, Point) {
+        let (a, b) = (a.min(b), a.max(b));
+        let mut candidates = vec![Point::new(a, self.evaluate(a)), Point::new(b, self.evaluate(b))];
+        for segment in &self.segments {
+            let ys = segment.map(|point| point.y);
+            let points = [0.0, 1.0]
+                .into_iter()
+                .chain(critical_points(&ys))
+                .map(|t| fit::evaluate_bezier(segment, t));
+            candidates.extend(points.filter(|point| (a..=b).contains(&point.x)));
+        }
+        let lowest = candidates.iter().copied().min_by(|p, q| p.y.total_cmp(&q.y));
+        let highest = candidates.iter().copied().max_by(|p, q| p.y.total_cmp(&q.y));
+        (lowest.unwrap_or(candidates[0]), highest.unwrap_or(candidates[0]))
+    }
+
+    /// Every x between the end keys where the curve crosses or touches `y`, in increasing order and
+    /// within `TOLERANCE`. A segment lying along `y` reports its ends.
+    pub fn solve(&self, y: f32) -> Vec<f32> {
+        let mut xs: Vec<f32> = Vec::new();
+        for segment in &self.segments {
+            let ys = segment.map(|point| point.y - y);
+            for t in cubic_roots(&ys) {
+                let x = fit::evaluate_bezier(segment, t).x;
+                // Neighbouring segments share their end point, count it once
+                if xs.last().is_none_or(|&last| x - last > TOLERANCE) {
+                    xs.push(x);
+                }
+            }
+        }
+        xs
+    }
+
+    /// Where the curve crosses zero, see `solve`.
+    pub fn roots(&self) -> Vec<f32> {
+        self.solve(0.0)
+    }
+
+    // Segment containing x, for x between the end keys
+    fn segment_index(&self, x: f32) -> usize {
+        self.segments
+            .partition_point(|segment| segment[3].x < x)
+            .min(self.segments.len() - 1)
+    }
+
+    // Parameter where the segment reaches x, which it does once since x only moves right
+    fn t_at(&self, segment: &[Point; 4], x: f32) -> f32 {
+        let xs = segment.map(|point| point.x - x);
+        cubic_roots(&xs).first().copied().unwrap_or(0.0)
+    }
+}
+
+// c0 + c1 t + c2 t² + c3 t³ of a cubic Bézier coordinate
+fn power_basis(&[p0, p1, p2, p3]: &[f32; 4]) -> [f32; 4] {
+    [
+        p0,
+        3.0 * (p1 - p0),
+        3.0 * (p0 - 2.0 * p1 + p2),
+        p3 - p0 + 3.0 * (p1 - p2),
+    ]
+}
+
+fn evaluate_power(&[c0, c1, c2, c3]: &[f32; 4], t: f32) -> f32 {
+    ((c3 * t + c2) * t + c1) * t + c0
+}
+
+// The part of a segment between t0 and t1 as a Bézier of its own, by de Casteljau's construction
+fn sub_segment(segment: &[Point; 4], t0: f32, t1: f32) -> [Point; 4] {
+    let split = |[p0, p1, p2, p3]: [Point; 4], t: f32| {
+        let lerp = |a: Point, b: Point| a + (b - a) * t;
+        let (q0, q1, q2) = (lerp(p0, p1), lerp(p1, p2), lerp(p2, p3));
+        let (r0, r1) = (lerp(q0, q1), lerp(q1, q2));
+        let s = lerp(r0, r1);
+        ([p0, q0, r0, s], [s, r1, q2, p3])
+    };
+    let (_, tail) = split(*segment, t0);
+    // t1 measured along the tail, which starts at t0
+    let (piece, _) = split(tail, if t0 < 1.0 { (t1 - t0) / (1.0 - t0) } else { 0.0 });
+    piece
+}
+
+// ∫ y dx over t0..t1 of a segment, exactly, since y(t) x'(t) is a quintic
+fn segment_area(segment: &[Point; 4], t0: f32, t1: f32) -> f32 {
+    let span = t1 - t0;
+    GAUSS_NODES
+        .iter()
+        .zip(GAUSS_WEIGHTS)
+        .map(|(&node, weight)| {
+            let t = t0 + span * node;
+            weight * fit::evaluate_bezier(segment, t).y * fit::first_derivative(segment, t).x
+        })
+        .sum::<f32>()
+        * span
+}
+
+// Parameters in (0, 1) where the coordinate's derivative is zero
+fn critical_points(coordinate: &[f32; 4]) -> Vec<f32> {
+    let [_, c1, c2, c3] = power_basis(coordinate);
+    quadratic_roots(3.0 * c3, 2.0 * c2, c1)
+        .into_iter()
+        .filter(|t| *t > 0.0 && *t < 1.0)
+        .collect()
+}
+
+// Real roots of a t² + b t + c, without the cancellation of the textbook formula
+fn quadratic_roots(a: f32, b: f32, c: f32) -> Vec<f32> {
+    let scale = a.abs().max(b.abs()).max(c.abs());
+    if scale == 0.0 {
+        return Vec::new();
+    }
+    if a.abs() <= 1e-7 * scale {
+        return if b == 0.0 { Vec::new() } else { vec![-c / b] };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return Vec::new();
+    }
+    let q = -0.5 * (b + discriminant.sqrt().copysign(b));
+    let mut roots = vec![q / a];
+    if q != 0.0 {
+        roots.push(c / q);
+    }
+    roots.sort_by(f32::total_cmp);
+    roots
+}
+
+// Parameters in [0, 1] where the coordinate is zero, in increasing order. The critical points split
+// [0, 1] into stretches where it's monotone, and each stretch that changes sign holds one root,
+// found by Newton steps kept inside the bracket
+fn cubic_roots(coordinate: &[f32; 4]) -> Vec<f32> {
+    let power = power_basis(coordinate);
+    let f = |t: f32| evaluate_power(&power, t);
+    let derivative = |t: f32| (3.0 * power[3] * t + 2.0 * power[2]) * t + power[1];
+    // The power basis rounds differently from evaluate_bezier, so a value found through one can miss
+    // zero by a few ulps in the other at the ends of a stretch
+    let near_zero = |value: f32| value.abs() <= 4.0 * f32::EPSILON;
+
+    let mut bounds = vec![0.0];
+    bounds.extend(critical_points(coordinate));
+    bounds.push(1.0);
+    let mut roots: Vec<f32> = Vec::new();
+    let mut push = |t: f32| {
+        if roots.last().is_none_or(|&last| t - last > TOLERANCE * 1e-2) {
+            roots.push(t);
+        }
+    };
+    for pair in bounds.windows(2) {
+        let (mut low, mut high) = (pair[0], pair[1]);
+        let (f_low, f_high) = (f(low), f(high));
+        if near_zero(f_low) {
+            push(low);
+        }
+        if f_low * f_high >= 0.0 || near_zero(f_high) {
+            if near_zero(f_high) {
+                push(high);
+            }
+            continue;
+        }
+        let rising = f_high > f_low;
+        let mut t = 0.5 * (low + high);
+        for _ in 0..MAX_ROOT_STEPS {
+            let value = f(t);
+            if value == 0.0 {
+                break;
+            }
+            if (value > 0.0) == rising {
+                high = t;
+            } else {
+                low = t;
+            }
+            let step = t - value / derivative(t);
+            // Newton where it stays in the bracket, bisection where it doesn't
+            let next = if step > low && step < high {
+                step
+            } else {
+                0.5 * (low + high)
+            };
+            let converged = (next - t).abs() <= f32::EPSILON || high - low <= f32::EPSILON;
+            t = next;
+            if converged {
+                break;
+            }
+        }
+        push(t);
+    }
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn dots(points: &[(f32, f32)]) -> Vec<Dot> {
+        points.iter().map(|&(x, y)| Dot::new(Point::new(x, y))).collect()
+    }
+
+    #[test]
+    fn answers_exactly_on_straight_lines() {
+        let curve = FunctionCurve::new(None, &dots(&[(0.0, 0.0), (1.0, 1.0)]), SplineSettings::default()).unwrap();
+        assert!((curve.integral(0.0, 1.0) - 0.5).abs() < 1e-6);
+        assert!((curve.integral(0.5, -1.0) + 0.125).abs() < 1e-6);
+        assert!((curve.inverse(0.25).unwrap() - 0.25).abs() < TOLERANCE);
+        assert_eq!(curve.roots(), [0.0]);
+        let (lowest, highest) = curve.extrema(0.2, 0.6);
+        assert_eq!((lowest.x, highest.x), (0.2, 0.6));
+    }
+
+    #[test]
+    fn finds_every_crossing_and_the_peak() {
+        let curve = FunctionCurve::new(
+            Some(CurveAlgorithm::Pchip),
+            &dots(&[(0.0, 0.0), (0.5, 1.0), (1.0, 0.0)]),
+            SplineSettings::default(),
+        )
+        .unwrap();
+        assert!(!curve.is_monotone());
+        assert_eq!(curve.inverse(0.5), None);
+        let crossings = curve.solve(0.5);
+        assert_eq!(crossings.len(), 2);
+        for x in crossings {
+            assert!((curve.evaluate(x) - 0.5).abs() < 1e-4);
+        }
+        let (_, highest) = curve.extrema(0.0, 1.0);
+        assert!((highest.x - 0.5).abs() < TOLERANCE && (highest.y - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn clipping_cuts_the_overshoot_level() {
+        let mode = Some(CurveAlgorithm::NaturalCubicSpline);
+        let keys = dots(&[(0.0, 0.0), (0.3, 1.0), (0.35, 1.0), (0.7, 0.0), (1.0, 0.1)]);
+        let curve = FunctionCurve::new(mode, &keys, SplineSettings::default()).unwrap();
+        assert!(curve.extrema(0.0, 1.0).1.y > 1.0);
+
+        let clipped = curve.clipped(0.0, 1.0);
+        let (lowest, highest) = clipped.extrema(0.0, 1.0);
+        assert!(lowest.y >= -1e-6 && highest.y <= 1.0 + 1e-6, "{lowest:?} {highest:?}");
+        let mut samples = curve::sample_curve(mode, &curve::with_edge_keys(&keys), SplineSettings::default()).unwrap();
+        for sample in &mut samples {
+            sample.y = sample.y.clamp(0.0, 1.0);
+            assert!((clipped.evaluate(sample.x) - sample.y).abs() < 1e-4);
+        }
+        let trapezoids: f32 = samples
+            .windows(2)
+            .map(|pair| (pair[1].x - pair[0].x) * (pair[0].y + pair[1].y) / 2.0)
+            .sum();
+        assert!((clipped.integral(0.0, 1.0) - trapezoids).abs() < 1e-3);
+    }
+
+    proptest! {
+        #[test]
+        fn inverse_undoes_evaluate(
+            ys in proptest::collection::vec(0.0f32..1.0, 3..8),
+            x in 0.0f32..1.0,
+        ) {
+            // Sorted heights over evenly spaced keys make a monotone curve
+            let mut ys = ys;
+            ys.sort_by(f32::total_cmp);
+            let last = (ys.len() - 1) as f32;
+            let points: Vec<(f32, f32)> = ys.iter().enumerate().map(|(i, &y)| (i as f32 / last, y)).collect();
+            let curve = FunctionCurve::new(Some(CurveAlgorithm::Pchip), &dots(&points), SplineSettings::default()).unwrap();
+            let y = curve.evaluate(x);
+            let inverse = curve.inverse(y).unwrap();
+            prop_assert!((curve.evaluate(inverse) - y).abs() < 1e-4);
+        }
+
+        #[test]
+        fn integral_matches_the_samples(ys in proptest::collection::vec(0.0f32..1.0, 2..8)) {
+            let last = (ys.len() - 1) as f32;
+            let points: Vec<(f32, f32)> = ys.iter().enumerate().map(|(i, &y)| (i as f32 / last, y)).collect();
+            let mode = Some(CurveAlgorithm::NaturalCubicSpline);
+            let curve = FunctionCurve::new(mode, &dots(&points), SplineSettings::default()).unwrap();
+            let samples = curve::sample_curve(mode, &curve::with_edge_keys(&dots(&points)), SplineSettings::default()).unwrap();
+            let trapezoids: f32 = samples.windows(2).map(|pair| (pair[1].x - pair[0].x) * (pair[0].y + pair[1].y) / 2.0).sum();
+            prop_assert!((curve.integral(0.0, 1.0) - trapezoids).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,316 @@
+// Key reduction that measures error on the evaluated curve rather than on the keys themselves
+use iced::Point;
+
+use crate::curve::{self, CurveAlgorithm, CurveError, CurveKind, Dot, SplineSettings};
+use crate::fit;
+use crate::path;
+
+// Each path segment is compared as a polyline of this many steps
+const PATH_STEPS: usize = 32;
+
+/// Returns the smallest set of keys found by refinement whose curve stays within `tolerance` of
+/// the original curve everywhere it was sampled. Function keys come back sorted by x and are
+/// measured by height; path keys keep their order and are measured by distance, as paths can
+/// double back.
+///
+/// Starts from the first and last keys and repeatedly puts back the removed key closest to where
+/// the simplified curve strays furthest, so the result follows the real interpolant.
+pub fn simplify(
+    dots: &[Dot],
+    kind: CurveKind,
+    curve_mode: Option<CurveAlgorithm>,
+    spline_settings: SplineSettings,
+    tolerance: f32,
+) -> Result<Vec<Dot>, CurveError> {
+    if let CurveKind::Path { .. } = kind {
+        return simplify_path(dots, kind, curve_mode, tolerance);
+    }
+    let mut sorted = dots.to_vec();
+    sorted.sort_by(|a, b| a.position.x.total_cmp(&b.position.x));
+    if sorted.len() <= 2 {
+        return Ok(sorted);
+    }
+
+    let original = curve::sample_curve(curve_mode, &curve::with_edge_keys(&sorted), spline_settings)?;
+    let mut kept = vec![false; sorted.len()];
+    kept[0] = true;
+    kept[sorted.len() - 1] = true;
+
+    loop {
+        let candidate: Vec<Dot> = sorted
+            .iter()
+            .zip(&kept)
+            .filter_map(|(dot, &keep)| keep.then_some(*dot))
+            .collect();
+        let simplified = curve::sample_curve(curve_mode, &curve::with_edge_keys(&candidate), spline_settings)?;
+
+        let Some((worst_x, error)) = worst_error(&original, &simplified) else {
+            return Ok(candidate);
+        };
+        if error <= tolerance {
+            return Ok(candidate);
+        }
+
+        let restore = sorted
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !kept[*i])
+            .min_by(|(_, a), (_, b)| {
+                (a.position.x - worst_x)
+                    .abs()
+                    .total_cmp(&(b.position.x - worst_x).abs())
+            })
+            .map(|(i, _)| i);
+        match restore {
+            Some(i) => kept[i] = true,
+            None => return Ok(candidate), // Every key is back, nothing left to remove
+        }
+    }
+}
+
+fn simplify_path(
+    dots: &[Dot],
+    kind: CurveKind,
+    curve_mode: Option<CurveAlgorithm>,
+    tolerance: f32,
+) -> Result<Vec<Dot>, CurveError> {
+    if dots.len() <= 2 {
+        return Ok(dots.to_vec());
+    }
+
+    let original = path_polylines(kind, curve_mode, dots)?;
+    let mut kept = vec![false; dots.len()];
+    kept[0] = true;
+    kept[dots.len() - 1] = true;
+
+    loop {
+        let indices: Vec<usize> = (0..dots.len()).filter(|&i| kept[i]).collect();
+        let candidate: Vec<Dot> = indices.iter().map(|&i| dots[i]).collect();
+        let simplified = path_polylines(kind, curve_mode, &candidate)?;
+
+        // Original segment k lies along the simplified segment from the last kept key up to k. The
+        // worst point is found as a position along the path in keys, segment k spanning k..k + 1
+        let mut worst: Option<(f32, f32)> = None;
+        let mut span = 0;
+        for (k, samples) in original.iter().enumerate() {
+            while span + 1 < indices.len() && indices[span + 1] <= k {
+                span += 1;
+            }
+            for (step, &point) in samples.iter().enumerate() {
+                let error = simplified[span]
+                    .windows(2)
+                    .map(|pair| path::distance_to_segment(point, pair[0], pair[1]))
+                    .fold(f32::MAX, f32::min);
+                if worst.is_none_or(|(_, worst_error)| error > worst_error) {
+                    worst = Some((k as f32 + step as f32 / PATH_STEPS as f32, error));
+                }
+            }
+        }
+
+        let Some((worst_at, error)) = worst else {
+            return Ok(candidate);
+        };
+        if error <= tolerance {
+            return Ok(candidate);
+        }
+
+        let restore = (0..dots.len())
+            .filter(|&i| !kept[i])
+            .min_by(|&a, &b| (a as f32 - worst_at).abs().total_cmp(&(b as f32 - worst_at).abs()));
+        match restore {
+            Some(i) => kept[i] = true,
+            None => return Ok(candidate),
+        }
+    }
+}
+
+// The path through the keys, one polyline per segment
+fn path_polylines(
+    kind: CurveKind,
+    curve_mode: Option<CurveAlgorithm>,
+    keys: &[Dot],
+) -> Result<Vec<Vec<Point>>, CurveError> {
+    let segments = curve::cubic_segments(kind, curve_mode, keys)?.unwrap_or_default();
+    Ok(segments
+        .iter()
+        .map(|segment| {
+            (0..=PATH_STEPS)
+                .map(|i| fit::evaluate_bezier(segment, i as f32 / PATH_STEPS as f32))
+                .collect()
+        })
+        .collect())
+}
+
+// Where and by how much the simplified curve's height strays furthest from the original's. Both are
+// sampled left to right, so one cursor walks the simplified polyline instead of searching it per sample
+fn worst_error(original: &[Point], simplified: &[Point]) -> Option<(f32, f32)> {
+    let (first, last) = (simplified.first()?, simplified.last()?);
+    let mut cursor = 1;
+    original
+        .iter()
+        .filter(|point| !point.x.is_nan())
+        .map(|point| {
+            let y = if point.x <= first.x {
+                first.y
+            } else if point.x >= last.x {
+                last.y
+            } else {
+                while cursor < simplified.len() - 1 && simplified[cursor].x <= point.x {
+                    cursor += 1;
+                }
+                let (a, b) = (simplified[cursor - 1], simplified[cursor]);
+                let t = if b.x > a.x { (point.x - a.x) / (b.x - a.x) } else { 0.0 };
+                a.y + (b.y - a.y) * t
+            };
+            (point.x, (point.y - y).abs())
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wave() -> Vec<Dot> {
+        (0..=40)
+            .map(|i| {
+                let x = i as f32 / 40.0;
+                Dot::new(Point::new(x, 0.5 + 0.3 * (x * 7.0).sin() * (1.0 - x)))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn simplified_curves_stay_within_the_tolerance() {
+        let dots = wave();
+        let settings = SplineSettings::default();
+        for algorithm in [CurveAlgorithm::CatmullRom, CurveAlgorithm::Pchip] {
+            let original = curve::sample_curve(Some(algorithm), &curve::with_edge_keys(&dots), settings).unwrap();
+            for tolerance in [0.05, 0.01, 0.002] {
+                let kept = simplify(&dots, CurveKind::Function, Some(algorithm), settings, tolerance).unwrap();
+                assert!(kept.len() < dots.len(), "{algorithm} kept every key at {tolerance}");
+                let simplified = curve::sample_curve(Some(algorithm), &curve::with_edge_keys(&kept), settings).unwrap();
+                for point in &original {
+                    let y = curve::polyline_y_at(&simplified, point.x).unwrap();
+                    assert!(
+                        (point.y - y).abs() <= tolerance,
+                        "{algorithm} strays at x = {}",
+                        point.x
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn simplifying_keeps_the_end_keys() {
+        let mut dots = wave();
+        dots.reverse(); // Input order doesn't matter
+        for tolerance in [0.5, 0.01] {
+            let kept = simplify(
+                &dots,
+                CurveKind::Function,
+                Some(CurveAlgorithm::CatmullRom),
+                SplineSettings::default(),
+                tolerance,
+            )
+            .unwrap();
+            assert_eq!(kept.first(), dots.last());
+            assert_eq!(kept.last(), dots.first());
+        }
+        // A straight line needs nothing but its ends
+        let line: Vec<Dot> = (0..=10)
+            .map(|i| Dot::new(Point::new(i as f32 / 10.0, i as f32 / 10.0)))
+            .collect();
+        let kept = simplify(
+            &line,
+            CurveKind::Function,
+            Some(CurveAlgorithm::CatmullRom),
+            SplineSettings::default(),
+            1e-3,
+        )
+        .unwrap();
+        assert_eq!(kept, [line[0], line[10]]);
+    }
+
+    #[test]
+    fn simplifying_a_path_keeps_its_order() {
+        // A square with a key halfway along each side, which straight lines don't need
+        let square: Vec<Dot> = [
+            (0.2, 0.2),
+            (0.5, 0.2),
+            (0.8, 0.2),
+            (0.8, 0.5),
+            (0.8, 0.8),
+            (0.5, 0.8),
+            (0.2, 0.8),
+            (0.2, 0.5),
+        ]
+        .map(|(x, y)| Dot::new(Point::new(x, y)))
+        .to_vec();
+        let kept = simplify(
+            &square,
+            CurveKind::Path { closed: true },
+            None,
+            SplineSettings::default(),
+            1e-4,
+        )
+        .unwrap();
+        assert_eq!(kept, [square[0], square[2], square[4], square[6], square[7]]);
+
+        // Most of a circle, running right to left along its top, so x goes both ways
+        let circle: Vec<Dot> = (0..30)
+            .map(|i| {
+                let angle = i as f32 / 30.0 * 5.5;
+                Dot::new(Point::new(0.5 + 0.4 * angle.cos(), 0.5 + 0.4 * angle.sin()))
+            })
+            .collect();
+        let kind = CurveKind::Path { closed: false };
+        let kept = simplify(
+            &circle,
+            kind,
+            Some(CurveAlgorithm::CatmullRom),
+            SplineSettings::default(),
+            0.002,
+        )
+        .unwrap();
+        assert!(kept.len() < circle.len());
+        assert_eq!((kept[0], kept[kept.len() - 1]), (circle[0], circle[29]));
+        let mut rest = circle.iter();
+        assert!(
+            kept.iter().all(|dot| rest.any(|other| other == dot)),
+            "keys out of order"
+        );
+        let simplified = path_polylines(kind, Some(CurveAlgorithm::CatmullRom), &kept)
+            .unwrap()
+            .concat();
+        for point in path_polylines(kind, Some(CurveAlgorithm::CatmullRom), &circle)
+            .unwrap()
+            .concat()
+        {
+            let distance = simplified
+                .iter()
+                .map(|other| other.distance(point))
+                .fold(f32::MAX, f32::min);
+            assert!(distance <= 0.002 + 0.01, "{point:?} is {distance} away");
+        }
+    }
+
+    #[test]
+    fn the_cursor_matches_searching_the_polyline() {
+        let original: Vec<Point> = (0..=100)
+            .map(|i| Point::new(i as f32 / 100.0, (i as f32 * 0.3).sin()))
+            .collect();
+        let simplified: Vec<Point> = original.iter().step_by(7).copied().collect();
+        let searched = original
+            .iter()
+            .map(|point| {
+                (
+                    point.x,
+                    (point.y - curve::polyline_y_at(&simplified, point.x).unwrap()).abs(),
+                )
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        assert_eq!(worst_error(&original, &simplified), searched);
+    }
+}
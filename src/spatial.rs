@@ -0,0 +1,77 @@
+// Grid over curve space for hit testing without scanning every dot
+use iced::{Point, Vector};
+
+use crate::curve::Dot;
+
+/// Dot indices bucketed by position, so finding the dot under the cursor only looks at its
+/// neighbours. Rebuild it whenever the dots change.
+#[derive(Debug, Default)]
+pub struct DotIndex {
+    cells: usize, // Per side of the unit square, dots outside it go in the edge cells
+    buckets: Vec<Vec<usize>>,
+    positions: Vec<Point>,
+}
+
+impl DotIndex {
+    pub fn new(dots: &[Dot]) -> Self {
+        // About one dot per cell when they're spread out
+        let cells = ((dots.len() as f32).sqrt().ceil() as usize).max(1);
+        let mut index = Self {
+            cells,
+            buckets: vec![Vec::new(); cells * cells],
+            positions: dots.iter().map(|dot| dot.position).collect(),
+        };
+        for (i, dot) in dots.iter().enumerate() {
+            let (column, row) = (index.cell(dot.position.x), index.cell(dot.position.y));
+            index.buckets[row * cells + column].push(i);
+        }
+        index
+    }
+
+    // Cell along one axis, NaN ends up in the first one
+    fn cell(&self, value: f32) -> usize {
+        ((value * self.cells as f32).floor().max(0.0) as usize).min(self.cells - 1)
+    }
+
+    /// Lowest index of the dots closer than `reach` to `position` on both axes, the same dot a scan
+    /// in order would find.
+    pub fn find(&self, position: Point, reach: Vector) -> Option<usize> {
+        if self.positions.is_empty() {
+            return None;
+        }
+        let columns = self.cell(position.x - reach.x)..=self.cell(position.x + reach.x);
+        let rows = self.cell(position.y - reach.y)..=self.cell(position.y + reach.y);
+        rows.flat_map(|row| columns.clone().map(move |column| row * self.cells + column))
+            .flat_map(|cell| &self.buckets[cell])
+            .copied()
+            .filter(|&i| {
+                let dot = self.positions[i];
+                (dot.x - position.x).abs() < reach.x && (dot.y - position.y).abs() < reach.y
+            })
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn finds_what_a_scan_finds(
+            positions in vec((-0.2f32..1.2, -0.2f32..1.2), 0..200),
+            cursor in (-0.2f32..1.2, -0.2f32..1.2),
+            reach in 0.001f32..0.3,
+        ) {
+            let dots: Vec<Dot> = positions.iter().map(|&(x, y)| Dot::new(Point::new(x, y))).collect();
+            let cursor = Point::new(cursor.0, cursor.1);
+            let reach = Vector::new(reach, reach);
+            let scanned = dots.iter().position(|dot| {
+                (dot.position.x - cursor.x).abs() < reach.x && (dot.position.y - cursor.y).abs() < reach.y
+            });
+            prop_assert_eq!(DotIndex::new(&dots).find(cursor, reach), scanned);
+        }
+    }
+}
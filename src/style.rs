@@ -0,0 +1,266 @@
+// Colors and sizes used to draw the canvas, shared by the editor and the image exporters, and the
+// theme they're derived from, which the editor remembers between runs
+use std::fmt;
+use std::path::PathBuf;
+
+use iced::{Color, Point, Size, Theme, Vector};
+
+use crate::curve::CurveAlgorithm;
+
+// The editor's theme until another is picked, also used for images rendered without a window
+pub const EDITOR_THEME: Theme = Theme::CatppuccinMocha;
+
+/// How keys are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DotShape {
+    #[default]
+    Circle,
+    Square,
+    Diamond,
+}
+
+impl DotShape {
+    pub const ALL: [DotShape; 3] = [DotShape::Circle, DotShape::Square, DotShape::Diamond];
+
+    /// Corners of the shape around `center`, reaching `radius` from it, or `None` for a circle.
+    pub fn corners(self, center: Point, radius: f32) -> Option<[Point; 4]> {
+        let (x, y) = (center.x, center.y);
+        match self {
+            DotShape::Circle => None,
+            // A square as wide as the circle would look bigger, so it's shrunk to the same area
+            DotShape::Square => {
+                let half = radius * std::f32::consts::PI.sqrt() / 2.0;
+                Some([
+                    Point::new(x - half, y - half),
+                    Point::new(x + half, y - half),
+                    Point::new(x + half, y + half),
+                    Point::new(x - half, y + half),
+                ])
+            }
+            DotShape::Diamond => Some([
+                Point::new(x, y - radius),
+                Point::new(x + radius, y),
+                Point::new(x, y + radius),
+                Point::new(x - radius, y),
+            ]),
+        }
+    }
+}
+
+impl fmt::Display for DotShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DotShape::Circle => "Circle",
+            DotShape::Square => "Square",
+            DotShape::Diamond => "Diamond",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CanvasStyle {
+    pub background: Color,
+    pub grid_spacing: f32, // Spacing between grid lines in pixels
+    pub grid_vertical: Color,
+    pub grid_horizontal: Color,
+    pub border: Color,
+    pub border_width: f32,
+    pub dot: Color,
+    pub dot_shape: DotShape,
+    pub dot_radius: f32,
+    pub handle_radius: f32, // Dots at the ends of Bézier handles
+    pub ring_gap: f32,      // Between a dot and the ring around it while hovered or held
+    pub marker_radius: f32, // Rings around keys outside the bounds
+    pub line: Color,        // Straight connectors and curves
+    pub line_width: f32,
+    pub compare_width: f32, // Each algorithm's curve in compare mode
+    pub break_size: f32,    // Distance from the center to the corners of the curvature break diamonds
+    pub comb_length: f32,   // Longest curvature comb tooth
+    pub polygon: Color,     // Control polygons and Bézier handle lines
+    // Dash and gap lengths in pixels
+    pub polygon_dash: &'static [f32], // B-spline and NURBS control polygons
+    pub ghost_dash: &'static [f32],   // Earlier versions of the curve: before a simplify, the reference
+    pub guide_dash: &'static [f32],   // Axes and limits drawn over the curve
+    pub algorithms: [Color; CurveAlgorithm::ALL.len()], // Each algorithm's curve in compare mode, by index
+    pub channels: [Color; 3],         // Red, green and blue histograms and gradient channels
+}
+
+impl CanvasStyle {
+    pub fn from_theme(theme: &Theme) -> Self {
+        let palette = theme.extended_palette();
+        Self {
+            background: palette.background.base.color,
+            grid_spacing: 50.0,
+            // Faint enough to stay behind the curve, with vertical lines a little stronger
+            grid_vertical: Color {
+                a: 0.35,
+                ..palette.background.strong.color
+            },
+            grid_horizontal: Color {
+                a: 0.25,
+                ..palette.background.strong.color
+            },
+            border: palette.primary.strong.color,
+            border_width: 4.0,
+            dot: palette.primary.base.color,
+            dot_shape: DotShape::default(),
+            dot_radius: 5.0,
+            handle_radius: 3.0,
+            ring_gap: 4.0,
+            marker_radius: 8.0,
+            line: palette.background.base.text,
+            line_width: 2.0,
+            compare_width: 1.5,
+            break_size: 7.0,
+            comb_length: 40.0,
+            polygon: palette.background.strong.color,
+            polygon_dash: &[6.0, 4.0],
+            ghost_dash: &[4.0, 4.0],
+            guide_dash: &[2.0, 4.0],
+            // Hues far enough apart to tell nine curves apart, pale on dark backgrounds and deep on light ones
+            algorithms: if palette.is_dark {
+                [
+                    Color::from_rgb(0.95, 0.55, 0.66), // Catmull-Rom
+                    Color::from_rgb(0.98, 0.70, 0.53),
+                    Color::from_rgb(0.98, 0.89, 0.56),
+                    Color::from_rgb(0.65, 0.89, 0.63),
+                    Color::from_rgb(0.45, 0.78, 0.93),
+                    Color::from_rgb(0.54, 0.71, 0.98),
+                    Color::from_rgb(0.80, 0.65, 0.97),
+                    Color::from_rgb(0.58, 0.89, 0.84),
+                    Color::from_rgb(0.86, 0.86, 0.86), // NURBS
+                ]
+            } else {
+                [
+                    Color::from_rgb(0.82, 0.06, 0.22), // Catmull-Rom
+                    Color::from_rgb(0.90, 0.40, 0.04),
+                    Color::from_rgb(0.70, 0.55, 0.0),
+                    Color::from_rgb(0.15, 0.58, 0.15),
+                    Color::from_rgb(0.02, 0.52, 0.70),
+                    Color::from_rgb(0.12, 0.30, 0.90),
+                    Color::from_rgb(0.53, 0.22, 0.94),
+                    Color::from_rgb(0.09, 0.57, 0.60),
+                    Color::from_rgb(0.35, 0.35, 0.35), // NURBS
+                ]
+            },
+            channels: [
+                Color::from_rgb(0.9, 0.3, 0.3),
+                Color::from_rgb(0.3, 0.8, 0.3),
+                Color::from_rgb(0.3, 0.5, 0.95),
+            ],
+        }
+    }
+
+    pub fn algorithm(&self, algorithm: CurveAlgorithm) -> Color {
+        self.algorithms[algorithm.index()]
+    }
+}
+
+/// Compare mode's legend, a row per algorithm in the top right corner of the canvas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Legend {
+    pub origin: Point, // Top left corner, in canvas pixels
+}
+
+impl Legend {
+    pub const WIDTH: f32 = 140.0;
+    pub const ROW: f32 = 18.0;
+    const MARGIN: f32 = 8.0;
+
+    /// The legend on a canvas of the given size, below anything `top` pixels high along its top.
+    pub fn new(size: Size, top: f32) -> Self {
+        Self {
+            origin: Point::new(size.width - Self::WIDTH - Self::MARGIN, top + Self::MARGIN),
+        }
+    }
+
+    pub fn size(self) -> Size {
+        Size::new(Self::WIDTH, Self::ROW * CurveAlgorithm::ALL.len() as f32)
+    }
+
+    /// Top left corner of the row for `algorithm`.
+    pub fn row(self, algorithm: CurveAlgorithm) -> Point {
+        self.origin + Vector::new(0.0, algorithm.index() as f32 * Self::ROW)
+    }
+
+    /// The algorithm whose row is under a canvas position.
+    pub fn entry_at(self, position: Point) -> Option<CurveAlgorithm> {
+        let offset = position - self.origin;
+        if !(0.0..Self::WIDTH).contains(&offset.x) || !(0.0..self.size().height).contains(&offset.y) {
+            return None;
+        }
+        CurveAlgorithm::ALL.get((offset.y / Self::ROW) as usize).copied()
+    }
+}
+
+/// The theme picked in the editor, or `EDITOR_THEME` if none was saved.
+pub fn load_theme() -> Theme {
+    preferences_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| parse_preferences(&text))
+        .unwrap_or(EDITOR_THEME)
+}
+
+pub fn save_theme(theme: &Theme) -> std::io::Result<()> {
+    let path = preferences_path().ok_or_else(|| std::io::Error::other("no configuration directory"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, format!("theme {theme}\n"))
+}
+
+/// The built-in theme with this name, ignoring case, spaces and dashes so `catppuccin-mocha` finds
+/// Catppuccin Mocha.
+pub fn find_theme(name: &str) -> Option<Theme> {
+    let squash = |name: &str| name.replace([' ', '-'], "").to_lowercase();
+    let name = squash(name);
+    Theme::ALL.iter().find(|theme| squash(&theme.to_string()) == name).cloned()
+}
+
+// One setting per line, like curve documents: `theme Catppuccin Mocha`
+fn parse_preferences(text: &str) -> Option<Theme> {
+    text.lines()
+        .filter_map(|line| line.trim().strip_prefix("theme "))
+        .find_map(find_theme)
+}
+
+fn preferences_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("iced-curve-editor").join("preferences"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn themes_read_back_by_name() {
+        for theme in Theme::ALL {
+            assert_eq!(parse_preferences(&format!("theme {theme}\n")).as_ref(), Some(theme));
+        }
+        assert_eq!(parse_preferences("theme Unknown"), None);
+        assert_eq!(find_theme("tokyo-night-storm"), Some(Theme::TokyoNightStorm));
+    }
+
+    #[test]
+    fn legend_rows_are_hit_by_their_algorithm() {
+        let legend = Legend::new(Size::new(800.0, 600.0), 20.0);
+        assert_eq!(legend.origin, Point::new(652.0, 28.0));
+        for algorithm in CurveAlgorithm::ALL {
+            let row = legend.row(algorithm);
+            for offset in [Vector::new(0.0, 0.0), Vector::new(70.0, 9.0), Vector::new(139.0, 17.5)] {
+                assert_eq!(legend.entry_at(row + offset), Some(algorithm));
+            }
+        }
+        // Just outside on every side
+        let bottom_right = legend.origin + Vector::new(legend.size().width, legend.size().height);
+        for position in [
+            legend.origin - Vector::new(1.0, 0.0),
+            legend.origin - Vector::new(0.0, 1.0),
+            bottom_right - Vector::new(1.0, -1.0),
+            bottom_right - Vector::new(0.0, 1.0),
+            Point::new(f32::NAN, 40.0),
+        ] {
+            assert_eq!(legend.entry_at(position), None, "{position:?}");
+        }
+    }
+}
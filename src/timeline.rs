@@ -0,0 +1,138 @@
+// Reading the x axis as time: a frame range at a frame rate, mapped onto curve space x
+use std::time::Duration;
+
+use crate::curve::{self, Dot};
+
+/// Frames `start..=end` spread over x in [0, 1], the same mapping as baking a frame range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeline {
+    pub fps: f32,
+    pub start: i32,
+    pub end: i32, // Always after start
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            fps: 24.0,
+            start: 0,
+            end: 48,
+        }
+    }
+}
+
+// Ruler labels step through 1, 2, 5, 10, 20, 50... frames
+const RULER_STEPS: [i32; 3] = [1, 2, 5];
+
+impl Timeline {
+    /// Fractional frame at curve space `x`.
+    pub fn frame_at(self, x: f32) -> f32 {
+        self.start as f32 + x * (self.end - self.start) as f32
+    }
+
+    pub fn x_at(self, frame: f32) -> f32 {
+        (frame - self.start as f32) / (self.end - self.start) as f32
+    }
+
+    /// Moves `x` to the nearest whole frame.
+    pub fn snap(self, x: f32) -> f32 {
+        self.x_at(self.frame_at(x).round())
+    }
+
+    /// Moves every key to its nearest frame, returning how many couldn't be. In a function curve
+    /// a key that would land within `MIN_KEY_SPACING` of another stays where it is, rather than
+    /// being merged with it.
+    pub fn snap_keys(self, dots: &mut [Dot], function: bool) -> usize {
+        let mut skipped = 0;
+        for i in 0..dots.len() {
+            let x = self.snap(dots[i].position.x);
+            let collides = function
+                && dots
+                    .iter()
+                    .enumerate()
+                    .any(|(j, other)| j != i && (other.position.x - x).abs() < curve::MIN_KEY_SPACING);
+            if collides {
+                skipped += 1;
+            } else {
+                dots[i].position.x = x;
+            }
+        }
+        skipped
+    }
+
+    /// Time from the first frame to `x`.
+    pub fn seconds_at(self, x: f32) -> f32 {
+        (self.frame_at(x) - self.start as f32) / self.fps
+    }
+
+    pub fn duration(self) -> Duration {
+        Duration::from_secs_f32((self.end - self.start) as f32 / self.fps)
+    }
+
+    /// Smallest frame step between ruler labels that keeps them `min_spacing` pixels apart on a
+    /// ruler `width` pixels wide.
+    pub fn ruler_step(self, width: f32, min_spacing: f32) -> i32 {
+        let pixels_per_frame = width / (self.end - self.start) as f32;
+        let mut scale = 1;
+        loop {
+            for step in RULER_STEPS.map(|step| step * scale) {
+                if step as f32 * pixels_per_frame >= min_spacing {
+                    return step;
+                }
+            }
+            if scale > i32::MAX / 100 {
+                return self.end - self.start;
+            }
+            scale *= 10;
+        }
+    }
+
+    /// Frames to label, the multiples of `step` in the range.
+    pub fn ruler_frames(self, step: i32) -> impl Iterator<Item = i32> {
+        let step = step.max(1);
+        let first = self.start.div_euclid(step) * step;
+        let first = if first < self.start { first + step } else { first };
+        (first..=self.end).step_by(step as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snaps_to_whole_frames() {
+        let timeline = Timeline { fps: 24.0, start: 10, end: 20 };
+        assert_eq!(timeline.frame_at(timeline.snap(0.34)), 13.0);
+        assert_eq!(timeline.snap(0.0), 0.0);
+        assert_eq!(timeline.snap(1.0), 1.0);
+        assert!((timeline.seconds_at(1.0) - 10.0 / 24.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn snapping_a_function_curve_keeps_every_key() {
+        let timeline = Timeline { fps: 24.0, start: 0, end: 10 };
+        let mut dots: Vec<Dot> = [0.02, 0.12, 0.14, 0.5, 0.93]
+            .map(|x| Dot::new(iced::Point::new(x, 0.5)))
+            .to_vec();
+        // 0.12 and 0.14 both round to frame 1, the second stays put
+        assert_eq!(timeline.snap_keys(&mut dots, true), 1);
+        let xs: Vec<f32> = dots.iter().map(|dot| dot.position.x).collect();
+        assert_eq!(xs, [0.0, 0.1, 0.14, 0.5, 0.9]);
+        assert!(curve::make_function_curve(&mut dots).iter().all(Option::is_some));
+
+        // Other curves can stack keys on a frame
+        let mut dots: Vec<Dot> = [0.12, 0.14].map(|x| Dot::new(iced::Point::new(x, 0.5))).to_vec();
+        assert_eq!(timeline.snap_keys(&mut dots, false), 0);
+        assert_eq!(dots[0].position, dots[1].position);
+    }
+
+    #[test]
+    fn ruler_labels_keep_their_spacing() {
+        let timeline = Timeline { fps: 24.0, start: -3, end: 120 };
+        // 123 frames over 600 px is about 4.9 px per frame, so 50 px needs steps of 20
+        assert_eq!(timeline.ruler_step(600.0, 50.0), 20);
+        assert_eq!(timeline.ruler_frames(20).collect::<Vec<_>>(), [0, 20, 40, 60, 80, 100, 120]);
+        assert_eq!(timeline.ruler_step(100_000.0, 50.0), 1);
+    }
+}
@@ -0,0 +1,236 @@
+// Grading images with curves: histograms to draw behind the editor, and the curves applied to pixels
+// through per-channel lookup tables
+use std::fmt::{self, Write};
+use std::path::Path;
+
+use image::{imageops, DynamicImage, ImageResult, RgbaImage};
+
+use crate::curve::{self, CurveAlgorithm, CurveError, Dot, SplineSettings};
+
+// Entries per lookup table and bins per histogram, one per 8 bit level
+const LEVELS: usize = 256;
+
+/// The curve being edited. Each color channel has its own curve, and the RGB curve is applied on top
+/// of all three.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneChannel {
+    #[default]
+    Rgb,
+    Red,
+    Green,
+    Blue,
+}
+
+impl ToneChannel {
+    pub const ALL: [ToneChannel; 4] = [ToneChannel::Rgb, ToneChannel::Red, ToneChannel::Green, ToneChannel::Blue];
+
+    /// Position in `ALL`, which is also the order of the keys passed to `ToneCurves::new`.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl fmt::Display for ToneChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ToneChannel::Rgb => "RGB",
+            ToneChannel::Red => "Red",
+            ToneChannel::Green => "Green",
+            ToneChannel::Blue => "Blue",
+        })
+    }
+}
+
+/// Pixel counts per 8 bit level. Luminance uses the Rec. 709 weights on the sRGB values, like the
+/// luminosity histogram of image editors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub luminance: Vec<u32>,
+    pub channels: [Vec<u32>; 3],
+}
+
+impl Histogram {
+    pub fn new(image: &RgbaImage) -> Self {
+        let mut histogram = Histogram {
+            luminance: vec![0; LEVELS],
+            channels: std::array::from_fn(|_| vec![0; LEVELS]),
+        };
+        for pixel in image.pixels() {
+            let [r, g, b, _] = pixel.0;
+            let luminance = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+            histogram.luminance[(luminance.round() as usize).min(LEVELS - 1)] += 1;
+            for (channel, value) in [r, g, b].into_iter().enumerate() {
+                histogram.channels[channel][value as usize] += 1;
+            }
+        }
+        histogram
+    }
+
+    /// Largest count of any bin, for scaling the histograms to the same height.
+    pub fn peak(&self) -> u32 {
+        self.channels
+            .iter()
+            .chain([&self.luminance])
+            .flat_map(|bins| bins.iter().copied())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// The curve of every channel sampled into a table, indexed like `ToneChannel::ALL`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToneCurves {
+    tables: [Vec<f32>; 4],
+}
+
+impl Default for ToneCurves {
+    fn default() -> Self {
+        Self {
+            tables: std::array::from_fn(|_| identity()),
+        }
+    }
+}
+
+fn identity() -> Vec<f32> {
+    (0..LEVELS).map(|i| i as f32 / (LEVELS - 1) as f32).collect()
+}
+
+impl ToneCurves {
+    /// Samples the curves through `keys`, in `ToneChannel::ALL` order. A channel without keys leaves
+    /// its values as they are.
+    pub fn new(
+        keys: [&[Dot]; 4],
+        curve_mode: Option<CurveAlgorithm>,
+        spline_settings: SplineSettings,
+    ) -> Result<Self, CurveError> {
+        let xs = identity();
+        let mut tables = [const { Vec::new() }; 4];
+        for (table, keys) in tables.iter_mut().zip(keys) {
+            *table = if keys.is_empty() {
+                xs.clone()
+            } else {
+                curve::evaluate_at(curve_mode, keys, spline_settings, &xs)?
+            };
+        }
+        Ok(Self { tables })
+    }
+
+    /// Grades an sRGB color with components in [0, 1], interpolating between table entries.
+    pub fn apply(&self, color: [f32; 3]) -> [f32; 3] {
+        let mut graded = color;
+        for (channel, value) in graded.iter_mut().enumerate() {
+            *value = lookup(&self.tables[0], lookup(&self.tables[channel + 1], *value));
+        }
+        graded
+    }
+
+    /// Grades every pixel, leaving alpha alone.
+    pub fn apply_to_image(&self, image: &RgbaImage) -> RgbaImage {
+        // With 8 bit input every channel only has 256 possible results, so they're computed up front
+        let tables: [[u8; LEVELS]; 3] = std::array::from_fn(|channel| {
+            std::array::from_fn(|level| {
+                let value = lookup(&self.tables[0], self.tables[channel + 1][level]);
+                (value * 255.0).round() as u8
+            })
+        });
+        let mut graded = image.clone();
+        for pixel in graded.pixels_mut() {
+            for channel in 0..3 {
+                pixel.0[channel] = tables[channel][pixel.0[channel] as usize];
+            }
+        }
+        graded
+    }
+
+    /// A 3D `.cube` LUT of `size`³ entries that applies the curves in other grading tools.
+    pub fn format_cube(&self, size: usize) -> String {
+        let size = size.max(2);
+        let mut out = String::new();
+        let _ = writeln!(out, "TITLE \"tone curves\"");
+        let _ = writeln!(out, "LUT_3D_SIZE {size}");
+        let level = |i: usize| i as f32 / (size - 1) as f32;
+        // Red changes fastest, as the format requires
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    let [r, g, b] = self.apply([level(r), level(g), level(b)]);
+                    let _ = writeln!(out, "{r:.6} {g:.6} {b:.6}");
+                }
+            }
+        }
+        out
+    }
+}
+
+// The table's value at `x` in [0, 1], clamped to [0, 1]
+fn lookup(table: &[f32], x: f32) -> f32 {
+    let position = x.clamp(0.0, 1.0) * (table.len() - 1) as f32;
+    let i = (position as usize).min(table.len() - 2);
+    let t = position - i as f32;
+    let value = table[i] + (table[i + 1] - table[i]) * t;
+    if value.is_nan() {
+        0.0
+    } else {
+        value.clamp(0.0, 1.0)
+    }
+}
+
+/// Opens a PNG or JPEG.
+pub fn load(path: impl AsRef<Path>) -> ImageResult<RgbaImage> {
+    Ok(image::open(path)?.to_rgba8())
+}
+
+/// Saves as PNG or JPEG by the extension. JPEG has no alpha, so it's dropped there.
+pub fn save(image: &RgbaImage, path: impl AsRef<Path>) -> ImageResult<()> {
+    let path = path.as_ref();
+    let jpeg = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("jpg") || extension.eq_ignore_ascii_case("jpeg"));
+    if jpeg {
+        DynamicImage::ImageRgba8(image.clone()).to_rgb8().save(path)
+    } else {
+        image.save(path)
+    }
+}
+
+/// The image scaled down to fit in `max_side` pixels, for grading in real time.
+pub fn thumbnail(image: &RgbaImage, max_side: u32) -> RgbaImage {
+    let scale = (max_side as f32 / image.width().max(image.height()) as f32).min(1.0);
+    let width = ((image.width() as f32 * scale).round() as u32).max(1);
+    let height = ((image.height() as f32 * scale).round() as u32).max(1);
+    imageops::thumbnail(image, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::Point;
+
+    fn image() -> RgbaImage {
+        RgbaImage::from_fn(16, 16, |x, y| image::Rgba([(x * 16) as u8, (y * 16) as u8, 200, 128]))
+    }
+
+    #[test]
+    fn curves_without_keys_change_nothing() {
+        let image = image();
+        assert_eq!(ToneCurves::default().apply_to_image(&image), image);
+        let keys: [&[Dot]; 4] = [&[], &[], &[], &[]];
+        let curves = ToneCurves::new(keys, None, SplineSettings::default()).unwrap();
+        assert_eq!(curves, ToneCurves::default());
+    }
+
+    #[test]
+    fn channel_curves_apply_before_the_rgb_curve() {
+        let invert = [Dot::new(Point::new(0.0, 1.0)), Dot::new(Point::new(1.0, 0.0))];
+        let half = [Dot::new(Point::new(0.0, 0.0)), Dot::new(Point::new(1.0, 0.5))];
+        let curves = ToneCurves::new([&half, &invert, &[], &[]], None, SplineSettings::default()).unwrap();
+        let [r, g, b] = curves.apply([0.2, 0.4, 1.0]);
+        assert!((r - 0.4).abs() < 1e-3 && (g - 0.2).abs() < 1e-3 && (b - 0.5).abs() < 1e-3);
+
+        let pixel = curves.apply_to_image(&image()).get_pixel(2, 3).0;
+        assert!(pixel[0].abs_diff(112) <= 1);
+        assert_eq!(pixel[1..], [24, 100, 128]);
+        assert!(curves.format_cube(3).lines().nth(2).is_some_and(|row| row == "0.500000 0.000000 0.000000"));
+    }
+}
@@ -0,0 +1,106 @@
+// Runs the curve-cli binary the way a build pipeline would
+use std::process::{Command, Output};
+
+use iced_curve_editor::document::CurveDocument;
+
+fn curve_cli(arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_curve-cli"))
+        .args(arguments)
+        .output()
+        .expect("curve-cli should start")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+// A file in the temporary directory that no other test or run uses
+fn temporary(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("curve-cli-{}-{name}", std::process::id()));
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn validate_fails_on_a_bad_document() {
+    let bad = temporary("bad.curve");
+    std::fs::write(&bad, "curve pchip\nkey 0.5 0.2\nkey 0.5 0.8\n").unwrap();
+    let output = curve_cli(&["validate", "css:ease", &bad]);
+    std::fs::remove_file(&bad).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let report = stdout(&output);
+    assert!(report.contains("css:ease: ok"), "{report}");
+    assert!(report.contains("more than one key at x = 0.500"), "{report}");
+
+    assert!(curve_cli(&["validate", "css:ease"]).status.success());
+}
+
+#[test]
+fn eval_prints_the_curve_at_each_x() {
+    let output = curve_cli(&["eval", "css:ease", "0.5"]);
+    assert!(output.status.success());
+    let line = stdout(&output);
+    let (x, y) = line.trim().split_once(' ').unwrap();
+    assert_eq!(x, "0.5");
+    // cubic-bezier(0.25, 0.1, 0.25, 1) at x = 0.5
+    assert!((y.parse::<f32>().unwrap() - 0.8024).abs() < 1e-3, "{line}");
+}
+
+#[test]
+fn sample_writes_every_format() {
+    let formats = [
+        ("csv", "x,y\n"),
+        ("json", "[0.000000, 0.802403, 1.000000]"),
+        ("rs", "pub const CURVE: [f32; 3]"),
+        ("glsl", "float curve(float x)"),
+        ("wgsl", "fn curve(x: f32) -> f32"),
+        ("cube", "LUT_1D_SIZE 3"),
+    ];
+    for (format, expected) in formats {
+        let output = curve_cli(&["sample", "css:ease", "--format", format, "--resolution", "3"]);
+        assert!(output.status.success(), "{format}");
+        let text = stdout(&output);
+        assert!(text.contains(expected) && text.contains("0.802403"), "{format}: {text}");
+    }
+}
+
+#[test]
+fn convert_writes_documents_and_images() {
+    let document = temporary("ease.curve");
+    assert!(curve_cli(&["convert", "css:ease", &document]).status.success());
+    let read = CurveDocument::read(&document).unwrap();
+    assert!((read.evaluate_at(&[0.5]).unwrap()[0] - 0.8024).abs() < 1e-3);
+
+    let svg = temporary("ease.svg");
+    let arguments = ["convert", &document, &svg, "--size", "320x200"];
+    assert!(curve_cli(&arguments).status.success());
+    let text = std::fs::read_to_string(&svg).unwrap();
+    assert!(text.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="320" height="200""#));
+    assert!(text.contains(" C "));
+
+    let png = temporary("ease.png");
+    let arguments = ["convert", "css:ease", &png, "--size", "64x48", "--theme", "dracula"];
+    assert!(curve_cli(&arguments).status.success());
+    let image = image::open(&png).unwrap();
+    assert_eq!((image.width(), image.height()), (64, 48));
+
+    for path in [document, svg, png] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn query_answers_on_the_clipped_curve() {
+    // The natural cubic spline overshoots 1 between the two top keys
+    let bounded = temporary("bounded.curve");
+    let keys = "key 0 0\nkey 0.3 1\nkey 0.35 1\nkey 0.7 0\nkey 1 0.1\n";
+    std::fs::write(&bounded, format!("curve natural-cubic\nbounds 0 1 bounded\n{keys}")).unwrap();
+    let output = curve_cli(&["query", &bounded, "extrema", "0", "1"]);
+    std::fs::remove_file(&bounded).unwrap();
+
+    assert!(output.status.success());
+    let report = stdout(&output);
+    let highest = report.lines().find_map(|line| line.strip_prefix("max ")).unwrap();
+    let (_, y) = highest.split_once(' ').unwrap();
+    assert_eq!(y, "1.000000", "{report}");
+}